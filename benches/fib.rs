@@ -1,27 +1,47 @@
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
-use drsm::Machine;
+use drsm::{Core, Machine, Word};
 use itertools::Itertools;
 use std::hint::black_box;
 
-fn fib_machine(n: i64) -> Machine {
+fn fib_machine(n: i64, memo: usize) -> Machine {
     assert!(n < 93, "Too big for i64");
-    let mut m = Machine::default();
-    m.read_eval("def fib_0 1").expect("OK by design");
-    m.read_eval("def fib_1 1").expect("OK by design");
+    let mut m = Machine::builder()
+        .memo_capacity(memo)
+        .build()
+        .expect("OK by design");
+    m.define("fib_0", vec![Word::Num(1)]).expect("OK by design");
+    m.define("fib_1", vec![Word::Num(1)]).expect("OK by design");
     (0..=n).tuple_windows().for_each(|(i, j, k)| {
-        m.read_eval(&format!("def fib_{k} fib_{j} fib_{i} add"))
-            .expect("OK by design");
+        let body = vec![
+            Word::Unbound(format!("fib_{j}").into()),
+            Word::Unbound(format!("fib_{i}").into()),
+            Word::Core(Core::Add),
+        ];
+        m.define(&format!("fib_{k}"), body).expect("OK by design");
     });
     m
 }
 
+/// The `n`th Fibonacci number, counting from `fib(0) = fib(1) = 1`.
+fn fib(n: i64) -> i64 {
+    (0..n).fold((1, 1), |(a, b), _| (b, a + b)).0
+}
+
 fn bench(c: &mut Criterion) {
-    let mut group = c.benchmark_group("Fibonacci");
-    for n in 10..20 {
-        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
-            let mut m = fib_machine(n);
-            b.iter(|| m.read_eval(&black_box(format!("fib_{n}"))));
-        });
+    for (name, memo) in [("Fibonacci", 0), ("Fibonacci (memoized)", 64)] {
+        let mut group = c.benchmark_group(name);
+        for n in 10..20 {
+            group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+                let mut m = fib_machine(n, memo);
+                m.read_eval(&format!("fib_{n}")).expect("OK by design");
+                assert_eq!(m.pop(), Some(fib(n)));
+                b.iter(|| {
+                    let r = m.read_eval(&black_box(format!("fib_{n}")));
+                    m.pop();
+                    r
+                });
+            });
+        }
     }
 }
 
@@ -0,0 +1,37 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use drsm::Machine;
+use std::hint::black_box;
+
+/// A machine defining `poly` in terms of small helpers, which it inlines if they're at most
+/// `threshold` words long.
+fn poly_machine(threshold: usize) -> Machine {
+    let mut m = Machine::builder()
+        .inline_threshold(threshold)
+        .build()
+        .expect("OK by design");
+    m.read_eval(
+        "def sq dup mul ; def cube dup sq mul ; def inc 1 add ; def dec 1 sub ; \
+         def poly dup cube swap sq add inc dec ; \
+         def run 1000 [ 7 poly drop ] times ;",
+    )
+    .expect("OK by design");
+    m.read_eval("run").expect("OK by design");
+    m
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Inline");
+    for threshold in [0, 2, 4, 8] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(threshold),
+            &threshold,
+            |b, &threshold| {
+                let mut m = poly_machine(threshold);
+                b.iter(|| m.read_eval(black_box("run")));
+            },
+        );
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
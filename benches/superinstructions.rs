@@ -0,0 +1,35 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use drsm::Machine;
+use std::hint::black_box;
+
+/// A machine defining `run` as a loop over pairs its optimizer fuses, if `optimize`.
+fn pairs_machine(optimize: bool) -> Machine {
+    let mut m = Machine::builder()
+        .optimize(optimize)
+        .build()
+        .expect("OK by design");
+    m.read_eval(
+        "def pairs dup mul swap drop dup add dup add swap drop dup mul ; \
+         def run 1000 [ 2 3 5 pairs drop ] times ;",
+    )
+    .expect("OK by design");
+    m.read_eval("run").expect("OK by design");
+    m
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Superinstructions");
+    for optimize in [false, true] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(optimize),
+            &optimize,
+            |b, &optimize| {
+                let mut m = pairs_machine(optimize);
+                b.iter(|| m.read_eval(black_box("run")));
+            },
+        );
+    }
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -2,8 +2,11 @@ use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use drsm::Machine;
 use std::hint::black_box;
 
-fn zero_sum_machine(n: i64) -> Machine {
-    let mut m = Machine::default();
+fn zero_sum_machine(n: i64, optimize: bool) -> Machine {
+    let mut m = Machine::builder()
+        .optimize(optimize)
+        .build()
+        .expect("OK by design");
     let mut s = "def zero_sum".to_string();
     for _ in 0..n {
         s.push_str(" 0 0 add");
@@ -14,12 +17,14 @@ fn zero_sum_machine(n: i64) -> Machine {
 }
 
 fn bench(c: &mut Criterion) {
-    let mut group = c.benchmark_group("Zero Sum");
-    for n in 10..20 {
-        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
-            let mut m = zero_sum_machine(n);
-            b.iter(|| m.read_eval(black_box("zero_sum")));
-        });
+    for (name, optimize) in [("Zero Sum", false), ("Zero Sum (optimized)", true)] {
+        let mut group = c.benchmark_group(name);
+        for n in 10..20 {
+            group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+                let mut m = zero_sum_machine(n, optimize);
+                b.iter(|| m.read_eval(black_box("zero_sum")));
+            });
+        }
     }
 }
 
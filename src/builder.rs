@@ -0,0 +1,270 @@
+use crate::{
+    CancelToken, Capabilities, Cell, Channels, Edition, Error, Input, Limits, Machine, Output,
+    Profile, Redefinition, word::Word,
+};
+use std::io::{Read, Write};
+
+/// Configures & builds a machine, for when `Machine::new` & friends don't offer enough knobs.
+///
+/// `Machine::builder()` starts one for the default cell type, & `MachineBuilder::default()` for
+/// any.
+#[derive(Clone, Debug)]
+pub struct MachineBuilder<T: Cell = i64> {
+    edition: Edition,
+    limits: Limits,
+    stack: Vec<T>,
+    stack_capacity: usize,
+    env_capacity: usize,
+    defs: Vec<(String, Vec<Word<T>>)>,
+    channels: Option<Channels<T>>,
+    cancel: Option<CancelToken>,
+    output: Option<Output>,
+    input: Option<Input>,
+    redefinition: Redefinition,
+    inline: usize,
+    optimize: bool,
+    memo: usize,
+    #[cfg(feature = "jit")]
+    jit: Option<usize>,
+    #[cfg(feature = "concurrency")]
+    max_workers: Option<usize>,
+}
+
+impl<T: Cell> Default for MachineBuilder<T> {
+    fn default() -> Self {
+        Self {
+            edition: Edition::default(),
+            limits: Limits::default(),
+            stack: Vec::new(),
+            stack_capacity: 64,
+            env_capacity: 64,
+            defs: Vec::new(),
+            channels: None,
+            cancel: None,
+            output: None,
+            input: None,
+            redefinition: Redefinition::default(),
+            inline: 0,
+            optimize: false,
+            memo: 0,
+            #[cfg(feature = "jit")]
+            jit: None,
+            #[cfg(feature = "concurrency")]
+            max_workers: None,
+        }
+    }
+}
+
+impl<T: Cell> MachineBuilder<T> {
+    /// Evaluate with the semantics (e.g. the arithmetic) of this edition.
+    #[must_use]
+    pub const fn edition(mut self, edition: Edition) -> Self {
+        self.edition = edition;
+        self
+    }
+    /// Enforce these limits.
+    #[must_use]
+    pub const fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+    /// Enforce the limits this profile bundles.
+    #[must_use]
+    pub const fn profile(self, profile: Profile) -> Self {
+        self.limits(profile.limits())
+    }
+    /// Allow only these side effects, overriding the limits' (so call this after `limits` or
+    /// `profile`).
+    #[must_use]
+    pub const fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.limits.capabilities = capabilities;
+        self
+    }
+    /// Start with these values on the stack, the last on top.
+    #[must_use]
+    pub fn stack(mut self, xs: impl IntoIterator<Item = T>) -> Self {
+        self.stack.extend(xs);
+        self
+    }
+    /// Make room for this many values on the stack before it must grow.
+    #[must_use]
+    pub const fn stack_capacity(mut self, n: usize) -> Self {
+        self.stack_capacity = n;
+        self
+    }
+    /// Make room for this many definitions before the environment must grow.
+    #[must_use]
+    pub const fn env_capacity(mut self, n: usize) -> Self {
+        self.env_capacity = n;
+        self
+    }
+    /// Start with a definition of `name`, as if by `def`.
+    #[must_use]
+    pub fn def(mut self, name: &str, body: Vec<Word<T>>) -> Self {
+        self.defs.push((name.into(), body));
+        self
+    }
+    /// Send & receive on (a handle to) these channels, e.g. those of another machine.
+    #[must_use]
+    pub fn channels(mut self, channels: &Channels<T>) -> Self {
+        self.channels = Some(channels.clone());
+        self
+    }
+    /// Let (a handle to) this token cancel the machine's evaluation.
+    #[must_use]
+    pub fn cancel_token(mut self, token: &CancelToken) -> Self {
+        self.cancel = Some(token.clone());
+        self
+    }
+    /// Make printing words write to `sink` rather than standard output.
+    #[must_use]
+    pub fn output(mut self, sink: impl Write + Send + 'static) -> Self {
+        self.output = Some(Output::new(sink));
+        self
+    }
+    /// Make `read` read (whitespace-separated integers) from `source` rather than standard input.
+    #[must_use]
+    pub fn input(mut self, source: impl Read + Send + 'static) -> Self {
+        self.input = Some(Input::new(source));
+        self
+    }
+    /// Make `read` read these integers, in order, rather than standard input.
+    #[must_use]
+    pub fn input_values(
+        mut self,
+        xs: impl IntoIterator<Item = i64, IntoIter: Send + 'static>,
+    ) -> Self {
+        self.input = Some(Input::values(xs));
+        self
+    }
+    /// Treat redefinitions (by `def` or `var`, not `redef`) according to this policy, including
+    /// those of the builder's own definitions.
+    #[must_use]
+    pub fn redefinition(mut self, redefinition: Redefinition) -> Self {
+        self.redefinition = redefinition;
+        self
+    }
+    /// Splice definitions with bodies at most `n` words long into their callers, as
+    /// `Machine::set_inline_threshold` describes.
+    #[must_use]
+    pub const fn inline_threshold(mut self, n: usize) -> Self {
+        self.inline = n;
+        self
+    }
+    /// Optimize the bodies of definitions, as `Machine::set_optimize` describes.
+    #[must_use]
+    pub const fn optimize(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+    /// Memoize at most `n` results of calls to pure definitions, as
+    /// `Machine::set_memo_capacity` describes.
+    #[must_use]
+    pub const fn memo_capacity(mut self, n: usize) -> Self {
+        self.memo = n;
+        self
+    }
+    /// Compile definitions to native code once they've been called `n` times, as
+    /// `Machine::set_jit_threshold` describes.
+    #[cfg(feature = "jit")]
+    #[must_use]
+    pub const fn jit_threshold(mut self, n: usize) -> Self {
+        self.jit = Some(n);
+        self
+    }
+    /// Allow at most `max` tasks to be spawned (and not yet joined) at once.
+    #[cfg(feature = "concurrency")]
+    #[must_use]
+    pub const fn max_workers(mut self, max: usize) -> Self {
+        self.max_workers = Some(max);
+        self
+    }
+    /// Build the machine.
+    ///
+    /// # Errors
+    /// If a definition's name or body is invalid, or the definitions or stack exceed the limits.
+    pub fn build(self) -> Result<Machine<T>, Error> {
+        let mut m = Machine::with_capacity(self.stack_capacity, self.env_capacity);
+        m.set_edition(self.edition);
+        m.set_limits(self.limits);
+        if let Some(channels) = &self.channels {
+            m.connect(channels);
+        }
+        if let Some(token) = &self.cancel {
+            m.set_cancel_token(token);
+        }
+        if let Some(output) = &self.output {
+            m.share_output(output);
+        }
+        if let Some(input) = &self.input {
+            m.share_input(input);
+        }
+        m.set_redefinition(self.redefinition);
+        m.set_inline_threshold(self.inline);
+        m.set_optimize(self.optimize);
+        m.set_memo_capacity(self.memo);
+        #[cfg(feature = "jit")]
+        if let Some(n) = self.jit {
+            m.set_jit_threshold(n);
+        }
+        #[cfg(feature = "concurrency")]
+        if let Some(max) = self.max_workers {
+            m.set_max_workers(max);
+        }
+        for (name, body) in self.defs {
+            m.define(&name, body)?;
+        }
+        for x in self.stack {
+            m.push(x)?;
+        }
+        Ok(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Core;
+
+    #[test]
+    fn build() {
+        let m = Machine::builder()
+            .edition(Edition::Edition2026)
+            .profile(Profile::Standard)
+            .stack([3, 4])
+            .def("sq", vec![Word::Core(Core::Dup), Word::Core(Core::Mul)])
+            .build();
+        let mut m = m.expect("builds");
+        assert_eq!(m.edition(), Edition::Edition2026);
+        assert_eq!(m.limits(), &Profile::Standard.limits());
+        let pure = Machine::builder()
+            .profile(Profile::Standard)
+            .capabilities(Capabilities::NONE)
+            .build();
+        assert_eq!(pure.map(|m| m.capabilities()), Ok(Capabilities::NONE));
+        assert!(m.read_eval("sq add").is_ok());
+        assert_eq!(m.to_source().lines().last(), Some("19"));
+        let strict = Profile::Strict.limits();
+        for (b, e) in [
+            (
+                Machine::builder().def("1", vec![]),
+                Error::NumNotName("1".into()),
+            ),
+            (Machine::builder().def("x", vec![]), Error::DefBody),
+            (Machine::builder().def("a b", vec![]), Error::DefName),
+            (
+                Machine::builder().limits(strict).stack(vec![0; 1 << 20]),
+                Error::StackOverflow(strict.stack_depth.expect("limited")),
+            ),
+            (
+                Machine::builder()
+                    .redefinition(Redefinition::Strict)
+                    .def("x", vec![Word::Num(1)])
+                    .def("x", vec![Word::Num(2)]),
+                Error::Redefined("x".into()),
+            ),
+        ] {
+            assert_eq!(b.build().map(|_| ()), Err(e));
+        }
+    }
+}
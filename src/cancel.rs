@@ -0,0 +1,41 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// A flag for cancelling evaluation, e.g. from another thread or a signal handler.
+///
+/// Cloning is cheap and yields a handle to the _same_ flag. The evaluation that notices the
+/// cancellation fails with `Error::Cancelled` & clears the flag, so the next runs as usual.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Create a fresh token, not yet cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Cancel any evaluation in progress (or, failing that, the next).
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    /// Has evaluation been cancelled, without that being noticed yet?
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    /// Withdraw a cancellation not yet noticed.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+    /// Notice (& clear) a cancellation, if there's been one.
+    pub(crate) fn take(&self) -> bool {
+        self.0.swap(false, Ordering::Relaxed)
+    }
+    /// The flag itself, for compiled code to check (but not clear).
+    #[cfg(feature = "jit")]
+    pub(crate) fn flag(&self) -> &AtomicBool {
+        &self.0
+    }
+}
@@ -0,0 +1,339 @@
+#[cfg(feature = "bignum")]
+use num_bigint::{BigInt, Sign};
+#[cfg(feature = "bignum")]
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::{
+    fmt,
+    hash::Hash,
+    ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr},
+};
+
+/// The integers a machine's stack is made of: `i64` (the default), `i128`, `u64`, or (under
+/// `bignum`) `BigInt`.
+///
+/// Arithmetic & printing follow the cell type's own semantics, so e.g. `0 1 sub` saturates to 0
+/// on `u64`. Everything else a cell can hold (number literals, float bit patterns, handles, error
+/// codes, & truth values) is an `i64`, converted as if by `as`.
+pub trait Cell:
+    Clone
+    + Ord
+    + Hash
+    + Default
+    + fmt::Debug
+    + fmt::Display
+    + Send
+    + Sync
+    + 'static
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+    + Shl<u32, Output = Self>
+    + Shr<u32, Output = Self>
+{
+    /// Zero, which is also false.
+    const ZERO: Self;
+    /// How many bits wide a cell is.
+    const BITS: u32;
+    /// The cell holding an `i64`, wrapping if need be.
+    fn from_i64(n: i64) -> Self;
+    /// This cell as an `i64`, wrapping if need be.
+    fn as_i64(&self) -> i64;
+    /// This cell as an `i64`, if it fits.
+    fn to_i64(&self) -> Option<i64>;
+    /// The cell holding an `i128`, wrapping if need be.
+    fn from_i128(n: i128) -> Self;
+    /// This cell as an `i128`, wrapping if need be.
+    fn as_i128(&self) -> i128;
+    /// The cell nearest a float, rounding towards zero (& saturating).
+    fn from_f64(x: f64) -> Self;
+    /// The float nearest this cell.
+    fn to_f64(&self) -> f64;
+    /// Add, if the result fits.
+    fn checked_add(&self, y: &Self) -> Option<Self>;
+    /// Subtract, if the result fits.
+    fn checked_sub(&self, y: &Self) -> Option<Self>;
+    /// Multiply, if the result fits.
+    fn checked_mul(&self, y: &Self) -> Option<Self>;
+    /// Divide, if the divisor's nonzero & the result fits.
+    fn checked_div(&self, y: &Self) -> Option<Self>;
+    /// Take the (nonnegative) remainder, if the divisor's nonzero & the result fits.
+    fn checked_rem_euclid(&self, y: &Self) -> Option<Self>;
+    /// Raise to a power, if the result fits.
+    fn checked_pow(&self, e: u32) -> Option<Self>;
+    /// Add, saturating.
+    #[must_use]
+    fn saturating_add(&self, y: &Self) -> Self;
+    /// Subtract, saturating.
+    #[must_use]
+    fn saturating_sub(&self, y: &Self) -> Self;
+    /// Multiply, saturating.
+    #[must_use]
+    fn saturating_mul(&self, y: &Self) -> Self;
+    /// Divide (by nonzero), saturating.
+    #[must_use]
+    fn saturating_div(&self, y: &Self) -> Self;
+    /// Raise to a power, saturating.
+    #[must_use]
+    fn saturating_pow(&self, e: u32) -> Self;
+    /// Take the (nonnegative) remainder (by nonzero, & not overflowing).
+    #[must_use]
+    fn rem_euclid(&self, y: &Self) -> Self;
+    /// The floor of the square root (of a nonnegative cell).
+    #[must_use]
+    fn isqrt(&self) -> Self;
+    /// The floor of the base-2 logarithm (of a positive cell).
+    fn ilog2(&self) -> u32;
+    /// How many bits are set.
+    fn count_ones(&self) -> u32;
+    /// Shift left by `n` (less than `BITS`), discarding the bits shifted out.
+    #[must_use]
+    fn wrapping_shl(&self, n: u32) -> Self;
+    /// Shift right by `n` (less than `BITS`), copying the sign bit, if any.
+    #[must_use]
+    fn wrapping_shr(&self, n: u32) -> Self;
+    /// This cell as a bignum.
+    #[cfg(feature = "bignum")]
+    fn to_big(&self) -> num_bigint::BigInt;
+    /// The cell holding a bignum, if it fits.
+    #[cfg(feature = "bignum")]
+    fn from_big(n: &num_bigint::BigInt) -> Option<Self>;
+}
+
+macro_rules! cell {
+    ($($t:ident),*) => {$(
+        #[allow(
+            clippy::cast_lossless,
+            clippy::cast_possible_truncation,
+            clippy::cast_possible_wrap,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss,
+            clippy::unnecessary_cast
+        )]
+        impl Cell for $t {
+            const ZERO: Self = 0;
+            const BITS: u32 = $t::BITS;
+            fn from_i64(n: i64) -> Self {
+                n as Self
+            }
+            fn as_i64(&self) -> i64 {
+                *self as i64
+            }
+            fn to_i64(&self) -> Option<i64> {
+                i64::try_from(*self).ok()
+            }
+            fn from_i128(n: i128) -> Self {
+                n as Self
+            }
+            fn as_i128(&self) -> i128 {
+                *self as i128
+            }
+            fn from_f64(x: f64) -> Self {
+                x as Self
+            }
+            fn to_f64(&self) -> f64 {
+                *self as f64
+            }
+            fn checked_add(&self, y: &Self) -> Option<Self> {
+                $t::checked_add(*self, *y)
+            }
+            fn checked_sub(&self, y: &Self) -> Option<Self> {
+                $t::checked_sub(*self, *y)
+            }
+            fn checked_mul(&self, y: &Self) -> Option<Self> {
+                $t::checked_mul(*self, *y)
+            }
+            fn checked_div(&self, y: &Self) -> Option<Self> {
+                $t::checked_div(*self, *y)
+            }
+            fn checked_rem_euclid(&self, y: &Self) -> Option<Self> {
+                $t::checked_rem_euclid(*self, *y)
+            }
+            fn checked_pow(&self, e: u32) -> Option<Self> {
+                $t::checked_pow(*self, e)
+            }
+            fn saturating_add(&self, y: &Self) -> Self {
+                $t::saturating_add(*self, *y)
+            }
+            fn saturating_sub(&self, y: &Self) -> Self {
+                $t::saturating_sub(*self, *y)
+            }
+            fn saturating_mul(&self, y: &Self) -> Self {
+                $t::saturating_mul(*self, *y)
+            }
+            fn saturating_div(&self, y: &Self) -> Self {
+                $t::saturating_div(*self, *y)
+            }
+            fn saturating_pow(&self, e: u32) -> Self {
+                $t::saturating_pow(*self, e)
+            }
+            fn rem_euclid(&self, y: &Self) -> Self {
+                $t::rem_euclid(*self, *y)
+            }
+            fn isqrt(&self) -> Self {
+                $t::isqrt(*self)
+            }
+            fn ilog2(&self) -> u32 {
+                $t::ilog2(*self)
+            }
+            fn count_ones(&self) -> u32 {
+                $t::count_ones(*self)
+            }
+            fn wrapping_shl(&self, n: u32) -> Self {
+                $t::wrapping_shl(*self, n)
+            }
+            fn wrapping_shr(&self, n: u32) -> Self {
+                $t::wrapping_shr(*self, n)
+            }
+            #[cfg(feature = "bignum")]
+            fn to_big(&self) -> num_bigint::BigInt {
+                (*self).into()
+            }
+            #[cfg(feature = "bignum")]
+            fn from_big(n: &num_bigint::BigInt) -> Option<Self> {
+                n.try_into().ok()
+            }
+        }
+    )*};
+}
+
+cell!(i64, i128, u64);
+
+/// Bignum cells are signed integers `BITS` wide, saturating (or overflowing, in checked editions)
+/// only far beyond any primitive cell, but still bounded so a few words can't exhaust memory.
+#[cfg(feature = "bignum")]
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+impl Cell for BigInt {
+    const ZERO: Self = Self::ZERO;
+    const BITS: u32 = 1 << 16;
+    fn from_i64(n: i64) -> Self {
+        n.into()
+    }
+    fn as_i64(&self) -> i64 {
+        u64::try_from(self & Self::from(u64::MAX)).unwrap_or_default() as i64
+    }
+    fn to_i64(&self) -> Option<i64> {
+        self.try_into().ok()
+    }
+    fn from_i128(n: i128) -> Self {
+        n.into()
+    }
+    fn as_i128(&self) -> i128 {
+        u128::try_from(self & Self::from(u128::MAX)).unwrap_or_default() as i128
+    }
+    fn from_f64(x: f64) -> Self {
+        match FromPrimitive::from_f64(x) {
+            Some(n) => clamp(n),
+            None if x.is_nan() => Self::ZERO,
+            None => clamp(Self::from(if x < 0.0 { -1 } else { 1 }) << Self::BITS),
+        }
+    }
+    fn to_f64(&self) -> f64 {
+        ToPrimitive::to_f64(self).unwrap_or(f64::NAN)
+    }
+    fn checked_add(&self, y: &Self) -> Option<Self> {
+        fits(self + y)
+    }
+    fn checked_sub(&self, y: &Self) -> Option<Self> {
+        fits(self - y)
+    }
+    fn checked_mul(&self, y: &Self) -> Option<Self> {
+        fits(self * y)
+    }
+    fn checked_div(&self, y: &Self) -> Option<Self> {
+        (*y != Self::ZERO).then(|| self / y).and_then(fits)
+    }
+    fn checked_rem_euclid(&self, y: &Self) -> Option<Self> {
+        (*y != Self::ZERO).then(|| Cell::rem_euclid(self, y))
+    }
+    fn checked_pow(&self, e: u32) -> Option<Self> {
+        // Bounding the result's size first, as the power's computed exactly.
+        match self.bits() {
+            0 | 1 => Some(self.pow(e)),
+            b if (b - 1).saturating_mul(e.into()) < u64::from(Self::BITS) => fits(self.pow(e)),
+            _ => None,
+        }
+    }
+    fn saturating_add(&self, y: &Self) -> Self {
+        clamp(self + y)
+    }
+    fn saturating_sub(&self, y: &Self) -> Self {
+        clamp(self - y)
+    }
+    fn saturating_mul(&self, y: &Self) -> Self {
+        clamp(self * y)
+    }
+    fn saturating_div(&self, y: &Self) -> Self {
+        clamp(self / y)
+    }
+    fn saturating_pow(&self, e: u32) -> Self {
+        self.checked_pow(e).unwrap_or_else(|| {
+            let odd = e % 2 == 1;
+            clamp(if self.sign() == Sign::Minus && odd {
+                Self::from(-1) << Self::BITS
+            } else {
+                Self::from(1) << Self::BITS
+            })
+        })
+    }
+    fn rem_euclid(&self, y: &Self) -> Self {
+        let r = self % y;
+        match (r.sign(), y.sign()) {
+            (Sign::Minus, Sign::Minus) => r - y,
+            (Sign::Minus, _) => r + y,
+            _ => r,
+        }
+    }
+    fn isqrt(&self) -> Self {
+        self.sqrt()
+    }
+    fn ilog2(&self) -> u32 {
+        u32::try_from(self.bits().saturating_sub(1)).unwrap_or_default()
+    }
+    fn count_ones(&self) -> u32 {
+        // Negative cells are in two's complement, `BITS` wide, like primitive ones.
+        match self.sign() {
+            Sign::Minus => Self::BITS - ones(&!self),
+            _ => ones(self),
+        }
+    }
+    fn wrapping_shl(&self, n: u32) -> Self {
+        let m = Self::from(1) << Self::BITS;
+        let half = Self::from(1) << (Self::BITS - 1);
+        Cell::rem_euclid(&((self << n) + &half), &m) - half
+    }
+    fn wrapping_shr(&self, n: u32) -> Self {
+        self >> n
+    }
+    fn to_big(&self) -> BigInt {
+        self.clone()
+    }
+    fn from_big(n: &BigInt) -> Option<Self> {
+        fits(n.clone())
+    }
+}
+
+/// The bignum cell nearest `n`.
+#[cfg(feature = "bignum")]
+fn clamp(n: BigInt) -> BigInt {
+    let max = (BigInt::from(1) << (BigInt::BITS - 1)) - 1;
+    if n > max {
+        max
+    } else if n < -&max - 1 {
+        -max - 1
+    } else {
+        n
+    }
+}
+
+/// The bignum cell `n`, if it's within range.
+#[cfg(feature = "bignum")]
+fn fits(n: BigInt) -> Option<BigInt> {
+    (n.bits() < u64::from(BigInt::BITS) || clamp(n.clone()) == n).then_some(n)
+}
+
+/// How many bits of a nonnegative bignum are set.
+#[cfg(feature = "bignum")]
+fn ones(n: &BigInt) -> u32 {
+    u32::try_from(n.magnitude().count_ones()).unwrap_or_default()
+}
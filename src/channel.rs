@@ -0,0 +1,81 @@
+use crate::cell::Cell;
+use lean_string::LeanString;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// Named FIFO channels, shared between every machine connected to them.
+///
+/// Cloning is cheap and yields a handle to the _same_ channels.
+#[derive(Clone, Debug)]
+pub struct Channels<T: Cell = i64>(Arc<Mutex<HashMap<LeanString, VecDeque<T>>>>);
+
+impl<T: Cell> Default for Channels<T> {
+    fn default() -> Self {
+        Self(Arc::default())
+    }
+}
+
+impl Channels {
+    /// Create a fresh, empty set of channels.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: Cell> Channels<T> {
+    /// Send a value on the named channel.
+    pub fn send(&self, name: &str, value: T) {
+        self.lock()
+            .entry(LeanString::from(name))
+            .or_default()
+            .push_back(value);
+    }
+    /// Receive the oldest value on the named channel, if there is one.
+    #[must_use]
+    pub fn recv(&self, name: &str) -> Option<T> {
+        self.lock().get_mut(name).and_then(VecDeque::pop_front)
+    }
+    /// How many values are waiting on the named channel.
+    #[must_use]
+    pub fn len(&self, name: &str) -> usize {
+        self.lock().get(name).map_or(0, VecDeque::len)
+    }
+    /// Is the named channel empty?
+    #[must_use]
+    pub fn is_empty(&self, name: &str) -> bool {
+        self.len(name) == 0
+    }
+    /// Copy these channels & the values waiting on them, rather than making another handle.
+    pub(crate) fn fork(&self) -> Self {
+        Self(Arc::new(Mutex::new(self.lock().clone())))
+    }
+    fn lock(&self) -> MutexGuard<'_, HashMap<LeanString, VecDeque<T>>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn fifo(ns in prop::collection::vec(any::<i64>(), 0..64), name in r"\S+") {
+            let c = Channels::new();
+            let d = c.clone();
+            for &n in &ns {
+                c.send(&name, n);
+            }
+            prop_assert_eq!(d.len(&name), ns.len());
+            for n in ns {
+                prop_assert_eq!(d.recv(&name), Some(n));
+            }
+            prop_assert!(c.is_empty(&name));
+            prop_assert_eq!(c.recv(&name), None);
+        }
+    }
+}
@@ -1,10 +1,14 @@
 /// Core words/tokens
+///
+/// Where a word takes a truth value, 0 is false & anything else true; words producing one push 1
+/// for true.
 #[derive(
     PartialEq,
     Eq,
     Clone,
     Copy,
     Debug,
+    Hash,
     documented::Documented,
     documented::DocumentedFields,
     strum::Display,
@@ -20,6 +24,53 @@ pub enum Core {
     Swap,
     /// Duplicate the first element of the stack.
     Dup,
+    /// Push a copy of the second element of the stack.
+    Over,
+    /// Move the third element of the stack to the top.
+    Rot,
+    /// Drop the second element of the stack.
+    Nip,
+    /// Push a copy of the first element of the stack below the second.
+    Tuck,
+    /// Pop a depth `n`; push a copy of the element that deep (so `0 pick` is `dup`).
+    Pick,
+    /// Pop a depth `n`; move the element that deep to the top (so `1 roll` is `swap`).
+    Roll,
+    /// Drop the first two elements of the stack.
+    #[documented_fields(rename = "2drop")]
+    #[strum(serialize = "2drop")]
+    TwoDrop,
+    /// Swap the first two pairs of elements on the stack.
+    #[documented_fields(rename = "2swap")]
+    #[strum(serialize = "2swap")]
+    TwoSwap,
+    /// Duplicate the first pair of elements on the stack.
+    #[documented_fields(rename = "2dup")]
+    #[strum(serialize = "2dup")]
+    TwoDup,
+    /// Push a copy of the second pair of elements on the stack.
+    #[documented_fields(rename = "2over")]
+    #[strum(serialize = "2over")]
+    TwoOver,
+    /// Push the number of elements on the stack.
+    Depth,
+    /// Empty the stack (and forget any markers on it).
+    Clear,
+    /// Print the whole stack, bottom first, preceded by its depth, leaving it unchanged.
+    #[documented_fields(rename = ".s")]
+    #[strum(serialize = ".s")]
+    ShowStack,
+    /// Replace the whole stack with its sum (as if by repeated `add`, so 0 if it's empty).
+    Sum,
+    /// Replace the whole stack with its product (as if by repeated `mul`, so 1 if it's empty).
+    Product,
+    /// Reverse the whole stack.
+    Reverse,
+    /// Pop a count `n`; push 0 through `n - 1`.
+    Iota,
+    /// Pop an upper bound & a lower bound below it; push the lower bound up to (but not including)
+    /// the upper.
+    Range,
     /// Add the first two elements of the stack.
     Add,
     /// Subtract the second from the first element of the stack.
@@ -30,18 +81,254 @@ pub enum Core {
     Div,
     /// Take the remainder of the second in the first element of the stack.
     Mod,
-    /// Pop 3 elements. If the first is zero, push the second back on; otherwise, push the third.
+    /// Pop an element; push 1 if it's zero, 0 otherwise. (Before the 2026 edition, this acts like `select`.)
     #[documented_fields(rename = "zero?")]
     #[strum(serialize = "zero?")]
     Zero,
+    /// Pop an element; push 1 if it's negative, 0 otherwise.
+    #[documented_fields(rename = "neg?")]
+    #[strum(serialize = "neg?")]
+    Neg,
+    /// Pop an element; push 1 if it's positive, 0 otherwise.
+    #[documented_fields(rename = "pos?")]
+    #[strum(serialize = "pos?")]
+    Pos,
+    /// Pop 3 elements. If the first is zero, push the second back on; otherwise, push the third.
+    Select,
     /// Pop an element off the stack and print it.
     Print,
+    /// Print the first element of the stack, leaving it there; also spelled `.`.
+    #[strum(to_string = "peek", serialize = ".")]
+    Peek,
+    /// Pop an element off the stack and print it, without a newline.
+    #[documented_fields(rename = "print-")]
+    #[strum(serialize = "print-")]
+    PrintBare,
+    /// Pop a width & an element below it; print the element right-aligned in (at least) that many
+    /// characters, padded with spaces, without a newline.
+    #[documented_fields(rename = "print-w")]
+    #[strum(serialize = "print-w")]
+    PrintWidth,
+    /// Pop a width & an element below it; print the element right-aligned in (at least) that many
+    /// characters, padded with zeroes after any sign, without a newline.
+    #[documented_fields(rename = "print-0w")]
+    #[strum(serialize = "print-0w")]
+    PrintZeroes,
+    /// Pop an element off the stack and print it in hexadecimal, as it'd be written (e.g. `-#ff`).
+    HexPrint,
+    /// Pop an element off the stack and print it in binary, as it'd be written (e.g. `0b101`).
+    BinPrint,
+    /// Push the value most recently printed again; also spelled `_`.
+    #[strum(to_string = "last", serialize = "_")]
+    Last,
+    /// Place a marker on the stack, for `clear-to-marker` to clear back to.
+    Mark,
+    /// Pop everything down to (and including) the nearest marker.
+    #[documented_fields(rename = "clear-to-marker")]
+    #[strum(serialize = "clear-to-marker")]
+    ClearToMarker,
+    /// Pop a quotation (pushed by `[ ... ]`) and execute it.
+    Call,
+    /// Pop a quotation and a condition; execute the quotation if the condition is nonzero.
+    If,
+    /// Pop two quotations and a condition; execute the first if the condition is nonzero, otherwise the second.
+    #[documented_fields(rename = "if-else")]
+    #[strum(serialize = "if-else")]
+    IfElse,
+    /// Pop a quotation and a count; execute the quotation that many times.
+    Times,
+    /// Pop a body and a condition quotation; execute the condition, pop, and (if that's nonzero) the body, repeatedly.
+    While,
+    /// Pop an error code and throw it, unwinding to the nearest `catch`.
+    Throw,
+    /// Pop a quotation and execute it, then push 0; if it fails, restore the stack as it was
+    /// beforehand and push the error code (what was thrown, or negative for the machine's errors).
+    Catch,
+    /// Stop executing the current definition, returning to whatever used it.
+    Exit,
+    /// Add the first two elements of the stack, as floats.
+    FAdd,
+    /// Subtract the second from the first element of the stack, as floats.
+    FSub,
+    /// Multiply the first two elements of the stack, as floats.
+    FMul,
+    /// Divide the second into the first element of the stack, as floats.
+    FDiv,
+    /// Pop a float; push it as an integer, rounding towards zero (& saturating).
+    #[documented_fields(rename = "f>i")]
+    #[strum(serialize = "f>i")]
+    FToI,
+    /// Pop an integer; push it as a float.
+    #[documented_fields(rename = "i>f")]
+    #[strum(serialize = "i>f")]
+    IToF,
+    /// Pop a float off the stack and print it.
+    FPrint,
+    /// Pop two strings; push the second followed by the first.
+    Concat,
+    /// Pop a string; push its length, in characters.
+    Length,
+    /// Pop a string off the stack and print it.
+    SPrint,
+    /// Push 1.
+    True,
+    /// Push 0.
+    False,
+    /// Pop 2 elements; push 1 if both are true, 0 otherwise.
+    And,
+    /// Pop 2 elements; push 1 if either is true, 0 otherwise.
+    Or,
+    /// Pop an element; push 1 if it's false, 0 otherwise.
+    Not,
+    /// Pop 2 elements; push 1 if exactly one is true, 0 otherwise.
+    Xor,
+    /// Pop an element; push its absolute value.
+    Abs,
+    /// Pop an element; push its negation.
+    #[documented_fields(rename = "neg")]
+    #[strum(serialize = "neg")]
+    Negate,
+    /// Pop 2 elements; push the lesser.
+    Min,
+    /// Pop 2 elements; push the greater.
+    Max,
+    /// Raise the second element of the stack to the power of the first.
+    Pow,
+    /// Pop 2 elements; push their greatest common divisor (which is nonnegative).
+    Gcd,
+    /// Pop a nonnegative element; push the floor of its square root.
+    Isqrt,
+    /// Pop a positive element; push the floor of its base-2 logarithm.
+    Log2,
+    /// Pop an element; push how many of its bits are set (so `-1 popcount` is 64, on `i64`).
+    Popcount,
+    /// Pop 2 elements; push 1 if they're equal, 0 otherwise.
+    #[documented_fields(rename = "eq?")]
+    #[strum(serialize = "eq?")]
+    Eq,
+    /// Pop 2 elements; push 1 if they differ, 0 otherwise.
+    #[documented_fields(rename = "neq?")]
+    #[strum(serialize = "neq?")]
+    Neq,
+    /// Pop 2 elements; push 1 if the first is less than the second, 0 otherwise.
+    #[documented_fields(rename = "lt?")]
+    #[strum(serialize = "lt?")]
+    Lt,
+    /// Pop 2 elements; push 1 if the first is greater than the second, 0 otherwise.
+    #[documented_fields(rename = "gt?")]
+    #[strum(serialize = "gt?")]
+    Gt,
+    /// Pop 2 elements; push 1 if the first is at most the second, 0 otherwise.
+    #[documented_fields(rename = "le?")]
+    #[strum(serialize = "le?")]
+    Le,
+    /// Pop 2 elements; push 1 if the first is at least the second, 0 otherwise.
+    #[documented_fields(rename = "ge?")]
+    #[strum(serialize = "ge?")]
+    Ge,
+    /// Pop 2 elements; push their bitwise and.
+    BAnd,
+    /// Pop 2 elements; push their bitwise or.
+    BOr,
+    /// Pop 2 elements; push their bitwise exclusive or.
+    BXor,
+    /// Pop an element; push its bitwise complement.
+    BNot,
+    /// Shift the second element left by the first (modulo the cell's width, e.g. 64).
+    Shl,
+    /// Shift the second element right by the first (modulo the cell's width, e.g. 64), keeping
+    /// the sign of a signed cell.
+    Shr,
+    /// Add the first two elements of the stack, as ratios.
+    RAdd,
+    /// Subtract the second from the first element of the stack, as ratios.
+    RSub,
+    /// Multiply the first two elements of the stack, as ratios.
+    RMul,
+    /// Divide the second into the first element of the stack, as ratios.
+    RDiv,
+    /// Pop a ratio; push its numerator (in lowest terms).
+    Num,
+    /// Pop a ratio; push its denominator (in lowest terms, so positive).
+    Den,
+    /// Pop a ratio; push the greatest integer no greater than it.
+    Floor,
+    /// Pop an integer; push it as a ratio.
+    #[documented_fields(rename = "i>r")]
+    #[strum(serialize = "i>r")]
+    IToR,
+    /// Pop a ratio off the stack and print it.
+    RPrint,
+    /// Pop a code point off the stack and print its character, without a newline.
+    Emit,
+    /// Push the next integer from the machine's input (standard input, unless it's given another).
+    Read,
+    /// Pop a length; push a fresh array of that many zeroes.
+    Array,
+    /// Pop an array & an index into it; push the element there.
+    Get,
+    /// Pop an array, an index into it, & a value; store the value there.
+    Set,
+    /// Pop an array; push its length.
+    Len,
+    /// Pop a variable; push its value.
+    #[documented_fields(rename = "@")]
+    #[strum(serialize = "@")]
+    Fetch,
+    /// Pop a variable & a value; store the value in the variable.
+    #[documented_fields(rename = "!")]
+    #[strum(serialize = "!")]
+    Store,
+    /// Pop an integer; push it as a bignum.
+    #[cfg(feature = "bignum")]
+    Big,
+    /// Add the first two elements of the stack, as bignums.
+    #[cfg(feature = "bignum")]
+    BAdd,
+    /// Subtract the second from the first element of the stack, as bignums.
+    #[cfg(feature = "bignum")]
+    BSub,
+    /// Multiply the first two elements of the stack, as bignums.
+    #[cfg(feature = "bignum")]
+    BMul,
+    /// Divide the second into the first element of the stack, as bignums.
+    #[cfg(feature = "bignum")]
+    BDiv,
+    /// Take the remainder of the second in the first element of the stack, as bignums.
+    #[cfg(feature = "bignum")]
+    BMod,
+    /// Pop a bignum; push it as an integer, if it fits.
+    #[cfg(feature = "bignum")]
+    #[documented_fields(rename = "b>i")]
+    #[strum(serialize = "b>i")]
+    BToI,
+    /// Pop a bignum off the stack and print it.
+    #[cfg(feature = "bignum")]
+    BPrint,
+}
+
+/// Serialized by name, as it's written in source.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Core {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Core {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = std::borrow::Cow::<str>::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|_| serde::de::Error::custom(format!("unknown core word `{s}`")))
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
     use proptest::prelude::*;
+    use strum::IntoEnumIterator;
 
     proptest! {
         #[test]
@@ -54,17 +341,6 @@ pub mod tests {
     }
 
     pub fn core() -> impl Strategy<Value = Core> {
-        prop_oneof![
-            Just(Core::Drop),
-            Just(Core::Swap),
-            Just(Core::Dup),
-            Just(Core::Add),
-            Just(Core::Sub),
-            Just(Core::Mul),
-            Just(Core::Div),
-            Just(Core::Mod),
-            Just(Core::Zero),
-            Just(Core::Print),
-        ]
+        prop::sample::select(Core::iter().collect::<Vec<_>>())
     }
 }
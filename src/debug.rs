@@ -0,0 +1,86 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// Where evaluation's paused, as a debugger's handler is told.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Pause<T> {
+    /// The word about to be executed.
+    pub word: String,
+    /// How many custom words' bodies it's within.
+    pub depth: usize,
+    /// The stack, bottom first.
+    pub stack: Vec<T>,
+    /// The definitions in the environment, each name with its body.
+    pub definitions: Vec<(String, String)>,
+}
+
+/// How to go on from a pause.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Resume {
+    /// Execute the word, then pause again before the next.
+    Step,
+    /// Execute words until the next breakpoint.
+    Continue,
+    /// Give up, failing with `Error::Cancelled`.
+    Abort,
+}
+
+/// Decides how to go on from a pause.
+type Handler<T> = Arc<Mutex<dyn FnMut(Pause<T>) -> Resume + Send>>;
+
+/// Pauses evaluation before words at breakpoints (or, while stepping, before every word), letting a
+/// handler examine the machine & decide how to go on.
+///
+/// Cloning yields a debugger with the same breakpoints & a handle to the same handler.
+#[derive(Clone)]
+pub struct Debugger<T> {
+    breakpoints: HashSet<String>,
+    stepping: bool,
+    on_pause: Handler<T>,
+}
+
+impl<T> Debugger<T> {
+    /// A debugger, with no breakpoints yet, that calls `on_pause` whenever evaluation pauses.
+    #[must_use]
+    pub fn new(on_pause: impl FnMut(Pause<T>) -> Resume + Send + 'static) -> Self {
+        Self {
+            breakpoints: HashSet::new(),
+            stepping: false,
+            on_pause: Arc::new(Mutex::new(on_pause)),
+        }
+    }
+    /// Pause before each execution of the word `w`.
+    pub fn set_breakpoint(&mut self, w: &str) {
+        self.breakpoints.insert(w.into());
+    }
+    /// Stop pausing before `w`, returning whether it was a breakpoint.
+    pub fn remove_breakpoint(&mut self, w: &str) -> bool {
+        self.breakpoints.remove(w)
+    }
+    /// The words evaluation pauses before, in no particular order.
+    pub fn breakpoints(&self) -> impl Iterator<Item = &str> {
+        self.breakpoints.iter().map(String::as_str)
+    }
+    /// Should evaluation pause before the word `w`?
+    pub(crate) fn pauses_at(&self, w: &str) -> bool {
+        self.stepping || self.breakpoints.contains(w)
+    }
+    /// Let the handler decide how to go on from a pause.
+    pub(crate) fn pause(&mut self, p: Pause<T>) -> Resume {
+        let resume = (self.on_pause.lock().unwrap_or_else(PoisonError::into_inner))(p);
+        self.stepping = resume == Resume::Step;
+        resume
+    }
+}
+
+impl<T> fmt::Debug for Debugger<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Debugger")
+            .field("breakpoints", &self.breakpoints)
+            .field("stepping", &self.stepping)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    core::Core,
+    edition::Edition,
+    symbol::{BuildSymbolHasher, Symbol},
+    word::{Fused, Word},
+};
+use indexmap::IndexMap;
+use std::sync::Arc;
+
+/// Definitions, keyed by (interned) name.
+pub type Env<T> = IndexMap<Symbol, Def<T>, BuildSymbolHasher>;
+
+/// A definition: its body, & how much of that needn't be checked word by word.
+///
+/// Cloning is cheap and yields a handle to the _same_ body, so copying an environment (e.g. when
+/// a machine's cloned or forked & then defines something) doesn't copy the bodies in it.
+#[derive(PartialEq, Eq, Clone, Debug, Hash)]
+pub struct Def<T> {
+    body: Arc<[Word<T>]>,
+    /// How many words at the start of the body can only fail for want of items, & how many items
+    /// they need between them: first in editions without checked arithmetic, then with.
+    prefixes: [(usize, usize); 2],
+}
+
+impl<T> Def<T> {
+    /// Define a body, working out how much of it `check` can be hoisted out of.
+    pub fn new(body: Vec<Word<T>>) -> Self {
+        let prefixes = [false, true].map(|checked| {
+            let (mut len, mut need, mut extra) = (0, 0, 0);
+            for w in &body {
+                let Some((taken, left)) = effect(w).filter(|_| !(checked && overflowable(w)))
+                else {
+                    break;
+                };
+                need += taken.saturating_sub(extra);
+                extra = extra.saturating_sub(taken) + left;
+                len += 1;
+            }
+            (len, need)
+        });
+        Self {
+            body: body.into(),
+            prefixes,
+        }
+    }
+    /// How many words at the start of the body needn't be `check`ed before they're executed,
+    /// given the edition & how many items the stack holds as it starts.
+    pub const fn unchecked(&self, edition: Edition, depth: usize) -> usize {
+        match self.prefixes[edition.checked_arithmetic() as usize] {
+            (len, need) if depth >= need => len,
+            _ => 0,
+        }
+    }
+}
+
+impl<T> std::ops::Deref for Def<T> {
+    type Target = [Word<T>];
+    fn deref(&self) -> &[Word<T>] {
+        &self.body
+    }
+}
+
+/// How many items a word takes from the stack & leaves on it, if that's always the same & it can
+/// only fail for want of items (or, if `overflowable`, by overflowing).
+pub const fn effect<T>(word: &Word<T>) -> Option<(usize, usize)> {
+    Some(match word {
+        Word::Num(_) | Word::Float(_) | Word::Ratio(_, 1..) | Word::Str(_) | Word::Quote(_) => {
+            (0, 1)
+        }
+        #[cfg(feature = "bignum")]
+        Word::Big(_) => (0, 1),
+        Word::Fused(f) => (f.arity(), 1),
+        Word::Core(c) => match c {
+            Core::True | Core::False | Core::Depth => (0, 1),
+            Core::Drop => (1, 0),
+            Core::Dup => (1, 2),
+            Core::Neg
+            | Core::Pos
+            | Core::Not
+            | Core::BNot
+            | Core::Abs
+            | Core::Negate
+            | Core::Popcount => (1, 1),
+            Core::Swap => (2, 2),
+            Core::Over | Core::Tuck => (2, 3),
+            Core::Nip
+            | Core::Add
+            | Core::Sub
+            | Core::Mul
+            | Core::And
+            | Core::Or
+            | Core::Xor
+            | Core::Min
+            | Core::Max
+            | Core::Eq
+            | Core::Neq
+            | Core::Lt
+            | Core::Gt
+            | Core::Le
+            | Core::Ge
+            | Core::BAnd
+            | Core::BOr
+            | Core::BXor
+            | Core::Shl
+            | Core::Shr => (2, 1),
+            Core::TwoDrop => (2, 0),
+            Core::TwoDup => (2, 4),
+            Core::Rot => (3, 3),
+            Core::Select => (3, 1),
+            Core::TwoSwap => (4, 4),
+            Core::TwoOver => (4, 6),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+/// Can this word fail by overflowing, in editions with checked arithmetic?
+const fn overflowable<T>(word: &Word<T>) -> bool {
+    matches!(
+        word,
+        Word::Core(Core::Add | Core::Sub | Core::Mul | Core::Abs | Core::Negate)
+            | Word::Fused(Fused::DupAdd | Fused::DupMul)
+    )
+}
+
+/// Serialized as its body.
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Def<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (*self.body).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Def<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes() {
+        let body = ["3", "swap", "over", "add", "rot", "0", "div", "dup"]
+            .map(|s| s.parse::<Core>().map_or(Word::Num(3), Word::Core));
+        let def = Def::new(body.to_vec());
+        assert_eq!(def.prefixes, [(6, 2), (3, 1)]);
+        assert_eq!(def.unchecked(Edition::Edition2025, 2), 6);
+        assert_eq!(def.unchecked(Edition::Edition2025, 1), 0);
+        assert_eq!(def.unchecked(Edition::Edition2026, 1), 3);
+        assert_eq!(
+            Def::new(vec![Word::<i64>::Unbound("x".into())]).prefixes,
+            [(0, 0); 2]
+        );
+        assert_eq!(
+            Def::new(vec![Word::<i64>::Ratio(1, 0)]).prefixes,
+            [(0, 0); 2]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+/// Language editions, selecting between semantic choices as the language evolves.
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialOrd,
+    Ord,
+    Hash,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumString,
+)]
+pub enum Edition {
+    /// The original semantics: saturating arithmetic.
+    #[default]
+    #[strum(serialize = "2025")]
+    Edition2025,
+    /// Checked arithmetic (overflow is an error instead of saturating), and `zero?` is a predicate.
+    #[strum(serialize = "2026")]
+    Edition2026,
+}
+
+impl Edition {
+    /// The most recent edition.
+    pub const LATEST: Self = Self::Edition2026;
+    /// Does this edition report arithmetic overflow as an error?
+    #[must_use]
+    pub const fn checked_arithmetic(self) -> bool {
+        matches!(self, Self::Edition2026)
+    }
+    /// Is `zero?` a predicate (pushing 0 or 1), rather than the older three-way `select`?
+    #[must_use]
+    pub const fn zero_predicate(self) -> bool {
+        matches!(self, Self::Edition2026)
+    }
+}
+
+/// Serialized by name, as `#edition` takes it.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Edition {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Edition {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = std::borrow::Cow::<str>::deserialize(deserializer)?;
+        s.parse()
+            .map_err(|_| serde::de::Error::custom(format!("unknown edition `{s}`")))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use strum::IntoEnumIterator;
+
+    proptest! {
+        #[test]
+        fn roundtrip(e in edition()) {
+            let s = e.to_string();
+            let e2 = s.parse::<Edition>();
+            prop_assert!(e2.is_ok());
+            prop_assert_eq!(e2.expect("is_ok"), e);
+        }
+    }
+
+    #[test]
+    fn latest_is_last() {
+        assert_eq!(Edition::iter().next_back(), Some(Edition::LATEST));
+    }
+
+    pub fn edition() -> impl Strategy<Value = Edition> {
+        prop_oneof![Just(Edition::Edition2025), Just(Edition::Edition2026)]
+    }
+}
@@ -1,12 +1,8 @@
-use std::num::ParseIntError;
+use std::{num::ParseIntError, ops::Range};
 
 /// Our Error type.
-#[derive(Clone, Debug, Default, PartialEq, Eq, thiserror::Error)]
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
 pub enum Error {
-    #[default]
-    /// Something bad happened.
-    #[error("Something bad happened.")]
-    Bad,
     /// The stack is too small for `{0}`; it requires {1}, but the stack only has {2}.
     #[error("The stack is too small for `{0}`; it requires {1}, but the stack only has {2}.")]
     Small(String, usize, usize),
@@ -16,8 +12,8 @@ pub enum Error {
     /// Unknown op: `{0}`.
     #[error("Unknown op: `{0}`.")]
     Unknown(String),
-    /// Self reference: `{0}` refers to itself.
-    #[error("Self reference: `{0}` refers to itself.")]
+    /// `{0}` calls itself, so can't be exported.
+    #[error("`{0}` calls itself, so can't be exported.")]
     SelfRef(String),
     /// `def` is a reserved keyword.
     #[error("`def` is a reserved keyword.")]
@@ -27,7 +23,7 @@ pub enum Error {
     DefName,
     /// A name was expected, but a number `{0}` was supplied.
     #[error("A name was expected, but a number `{0}` was supplied.")]
-    NumNotName(i64),
+    NumNotName(String),
     /// A name was expected, but a core word `{0}` was supplied.
     #[error("A name was expected, but a core word `{0}` was supplied.")]
     CoreNotName(String),
@@ -40,4 +36,271 @@ pub enum Error {
     /// `mod` would error with arguments (`i64::MIN`, -1)
     #[error("`mod` would error with arguments (`i64::MIN`, -1)")]
     ModEdge,
+    /// `{0}` overflowed, which is an error in checked editions.
+    #[error("`{0}` overflowed, which is an error in checked editions.")]
+    Overflow(String),
+    /// `#edition` is a reserved directive.
+    #[error("`#edition` is a reserved directive.")]
+    EditionReserved,
+    /// `#edition` needs an edition, but none was supplied.
+    #[error("`#edition` needs an edition, but none was supplied.")]
+    EditionName,
+    /// Unknown edition: `{0}`.
+    #[error("Unknown edition: `{0}`.")]
+    UnknownEdition(String),
+    /// `{0}` needs a channel name, but none was supplied.
+    #[error("`{0}` needs a channel name, but none was supplied.")]
+    ChannelName(String),
+    /// Channel `{0}` is empty.
+    #[error("Channel `{0}` is empty.")]
+    ChannelEmpty(String),
+    /// `spawn` needs the name of a custom word, but none was supplied.
+    #[error("`spawn` needs the name of a custom word, but none was supplied.")]
+    SpawnName,
+    /// `{0}` requires a nonnegative count.
+    #[error("`{0}` requires a nonnegative count.")]
+    Negative(String),
+    /// Too many outstanding tasks; at most {0} may be spawned before joining.
+    #[error("Too many outstanding tasks; at most {0} may be spawned before joining.")]
+    WorkerLimit(usize),
+    /// Unknown task: `{0}`.
+    #[error("Unknown task: `{0}`.")]
+    UnknownTask(i64),
+    /// Task `{0}` panicked.
+    #[error("Task `{0}` panicked.")]
+    WorkerPanicked(i64),
+    /// Evaluation budget of {0} words exceeded.
+    #[error("Evaluation budget of {0} words exceeded.")]
+    BudgetExceeded(u64),
+    /// Stack overflow: the stack may hold at most {0} values.
+    #[error("Stack overflow: the stack may hold at most {0} values.")]
+    StackOverflow(usize),
+    /// Recursion limit: custom words may be nested at most {0} deep.
+    #[error("Recursion limit: custom words may be nested at most {0} deep.")]
+    RecursionLimit(usize),
+    /// The environment is full: it may hold at most {0} definitions.
+    #[error("The environment is full: it may hold at most {0} definitions.")]
+    EnvFull(usize),
+    /// `{0}` is not permitted on this machine.
+    #[error("`{0}` is not permitted on this machine.")]
+    CapabilityDenied(String),
+    /// `clear-to-marker` needs a marker, but there are none on the stack.
+    #[error("`clear-to-marker` needs a marker, but there are none on the stack.")]
+    NoMarker,
+    /// Nothing has been printed yet, so `last` has nothing to push.
+    #[error("Nothing has been printed yet, so `last` has nothing to push.")]
+    NoLast,
+    /// `#include` is only allowed in files, and needs a path.
+    #[error("`#include` is only allowed in files, and needs a path.")]
+    Include,
+    /// Include cycle: `{0}` includes itself.
+    #[error("Include cycle: `{0}` includes itself.")]
+    IncludeCycle(String),
+    /// I/O error: {0}
+    #[error("I/O error: {0}")]
+    Io(String),
+    /// `[` needs a matching `]`.
+    #[error("`[` needs a matching `]`.")]
+    Unclosed,
+    /// `]` needs a matching `[`.
+    #[error("`]` needs a matching `[`.")]
+    Unopened,
+    /// Unknown quotation: `{0}`.
+    #[error("Unknown quotation: `{0}`.")]
+    UnknownQuote(i64),
+    /// `{0}` has no static stack effect, so can't be exported.
+    #[error("`{0}` has no static stack effect, so can't be exported.")]
+    NotExportable(String),
+    /// Unknown string: `{0}`.
+    #[error("Unknown string: `{0}`.")]
+    UnknownString(i64),
+    /// A name was expected, but a string `{0}` was supplied.
+    #[error("A name was expected, but a string `{0}` was supplied.")]
+    StrNotName(String),
+    /// Unknown bignum: `{0}`.
+    #[error("Unknown bignum: `{0}`.")]
+    UnknownBig(i64),
+    /// Unknown ratio: `{0}`.
+    #[error("Unknown ratio: `{0}`.")]
+    UnknownRatio(i64),
+    /// Not a character's code point: `{0}`.
+    #[error("Not a character's code point: `{0}`.")]
+    NotChar(i64),
+    /// Unknown array: `{0}`.
+    #[error("Unknown array: `{0}`.")]
+    UnknownArray(i64),
+    /// Index {0} is out of bounds for an array of length {1}.
+    #[error("Index {0} is out of bounds for an array of length {1}.")]
+    OutOfBounds(i64, usize),
+    /// The heap already holds its maximum of {0} array elements.
+    #[error("The heap already holds its maximum of {0} array elements.")]
+    HeapFull(usize),
+    /// `var` is a reserved keyword.
+    #[error("`var` is a reserved keyword.")]
+    VarReserved,
+    /// `var` needs a name, but none was supplied.
+    #[error("`var` needs a name, but none was supplied.")]
+    VarName,
+    /// `{0}` would push more than {1} values at once.
+    #[error("`{0}` would push more than {1} values at once.")]
+    TooMany(String, usize),
+    /// `const` is a reserved keyword.
+    #[error("`const` is a reserved keyword.")]
+    ConstReserved,
+    /// `const` needs a name, but none was supplied.
+    #[error("`const` needs a name, but none was supplied.")]
+    ConstName,
+    /// `{0}` is already defined, and constants can't be redefined.
+    #[error("`{0}` is already defined, and constants can't be redefined.")]
+    AlreadyDefined(String),
+    /// `;` ends a definition, but none was begun.
+    #[error("`;` ends a definition, but none was begun.")]
+    Unbegun,
+    /// The definition of `{0}` wasn't ended with `;`.
+    #[error("The definition of `{0}` wasn't ended with `;`.")]
+    Unended(String),
+    /// Couldn't lex `{slice}`.
+    #[error("Couldn't lex `{slice}`.")]
+    Lex {
+        /// The input rejected.
+        slice: String,
+        /// Where in the input it was.
+        span: Range<usize>,
+    },
+    /// `{0}` can't be undefined, as `{1}` refers to it.
+    #[error("`{0}` can't be undefined, as `{1}` refers to it.")]
+    Referenced(String, String),
+    /// Not a snapshot, or a corrupted one.
+    #[error("Not a snapshot, or a corrupted one.")]
+    Snapshot,
+    /// Snapshot format version {0} isn't supported.
+    #[error("Snapshot format version {0} isn't supported.")]
+    SnapshotVersion(u8),
+    /// The snapshot's of a machine with another cell type.
+    #[error("The snapshot's of a machine with another cell type.")]
+    SnapshotCell,
+    /// Out of memory: the environment, stack, & values they refer to may take up at most {0} bytes.
+    #[error(
+        "Out of memory: the environment, stack, & values they refer to may take up at most {0} bytes."
+    )]
+    MemoryFull(usize),
+    /// Evaluation was cancelled.
+    #[error("Evaluation was cancelled.")]
+    Cancelled,
+    /// `{0}` is already defined; `redef` redefines it.
+    #[error("`{0}` is already defined; `redef` redefines it.")]
+    Redefined(String),
+    /// `redef` is a reserved keyword.
+    #[error("`redef` is a reserved keyword.")]
+    RedefReserved,
+    /// Input is exhausted, so `read` has nothing to push.
+    #[error("Input is exhausted, so `read` has nothing to push.")]
+    EndOfInput,
+    /// `{0}` pads to a width of at most {1}.
+    #[error("`{0}` pads to a width of at most {1}.")]
+    TooWide(String, usize),
+    /// A statement (or definition) is over {0} bytes long.
+    #[error("A statement (or definition) is over {0} bytes long.")]
+    TooLong(usize),
+    /// Uncaught `throw` of error code {0}.
+    #[error("Uncaught `throw` of error code {0}.")]
+    Thrown(i64),
+    /// {0}:{1}: {2}
+    #[error("{0}:{1}: {2}")]
+    At(String, usize, Box<Self>),
+    /// column {col}: {1}
+    #[error("column {col}: {1}", col = .0.start + 1)]
+    Span(Range<usize>, Box<Self>),
+}
+
+/// `logos` requires a default error, though `lex_error` builds every one it reports.
+impl Default for Error {
+    fn default() -> Self {
+        Self::Lex {
+            slice: String::new(),
+            span: 0..0,
+        }
+    }
+}
+
+impl Error {
+    /// The code `catch` pushes for this error: whatever was thrown, for `throw`; otherwise a
+    /// negative number, fixed for each kind of error.
+    #[must_use]
+    pub fn code(&self) -> i64 {
+        match self {
+            Self::Thrown(n) => *n,
+            Self::At(_, _, e) | Self::Span(_, e) => e.code(),
+            Self::Small(..) => -2,
+            Self::Parsing(..) => -3,
+            Self::Unknown(..) => -4,
+            Self::SelfRef(..) => -5,
+            Self::DefReserved => -6,
+            Self::DefName => -7,
+            Self::NumNotName(..) => -8,
+            Self::CoreNotName(..) => -9,
+            Self::DefBody => -10,
+            Self::NotNonzero(..) => -11,
+            Self::ModEdge => -12,
+            Self::Overflow(..) => -13,
+            Self::EditionReserved => -14,
+            Self::EditionName => -15,
+            Self::UnknownEdition(..) => -16,
+            Self::ChannelName(..) => -17,
+            Self::ChannelEmpty(..) => -18,
+            Self::SpawnName => -19,
+            Self::Negative(..) => -20,
+            Self::WorkerLimit(..) => -21,
+            Self::UnknownTask(..) => -22,
+            Self::WorkerPanicked(..) => -23,
+            Self::BudgetExceeded(..) => -24,
+            Self::StackOverflow(..) => -25,
+            Self::RecursionLimit(..) => -26,
+            Self::EnvFull(..) => -27,
+            Self::CapabilityDenied(..) => -28,
+            Self::NoMarker => -29,
+            Self::NoLast => -30,
+            Self::Include => -31,
+            Self::IncludeCycle(..) => -32,
+            Self::Io(..) => -33,
+            Self::Unclosed => -34,
+            Self::Unopened => -35,
+            Self::UnknownQuote(..) => -36,
+            Self::NotExportable(..) => -37,
+            Self::UnknownString(..) => -38,
+            Self::StrNotName(..) => -39,
+            Self::UnknownBig(..) => -40,
+            Self::UnknownRatio(..) => -41,
+            Self::NotChar(..) => -42,
+            Self::UnknownArray(..) => -43,
+            Self::OutOfBounds(..) => -44,
+            Self::HeapFull(..) => -45,
+            Self::VarReserved => -46,
+            Self::VarName => -47,
+            Self::TooMany(..) => -48,
+            Self::ConstReserved => -49,
+            Self::ConstName => -50,
+            Self::AlreadyDefined(..) => -51,
+            Self::Unbegun => -52,
+            Self::Unended(..) => -53,
+            Self::Lex { .. } => -54,
+            Self::Referenced(..) => -55,
+            Self::Snapshot => -56,
+            Self::SnapshotVersion(..) => -57,
+            Self::SnapshotCell => -58,
+            Self::MemoryFull(..) => -59,
+            Self::Cancelled => -60,
+            Self::Redefined(..) => -61,
+            Self::RedefReserved => -62,
+            Self::EndOfInput => -63,
+            Self::TooWide(..) => -64,
+            Self::TooLong(..) => -65,
+        }
+    }
+    /// Can `catch` recover from this error? Not once the evaluation budget is spent, which a
+    /// sandboxed program mustn't be able to get around, or evaluation's cancelled.
+    #[must_use]
+    pub const fn catchable(&self) -> bool {
+        !matches!(self, Self::BudgetExceeded(_) | Self::Cancelled)
+    }
 }
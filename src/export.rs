@@ -0,0 +1,658 @@
+use crate::{Error, Machine, core::Core, symbol::Symbol, word::Word};
+
+impl Machine {
+    /// Translate a definition (inlining the custom words it uses) into a standalone Rust function
+    /// performing the same computation under this machine's edition.
+    ///
+    /// The function takes the values the definition consumes, in stack order (so `f(1, 2)` acts
+    /// like `1 2 f`), and returns those it leaves. Where evaluation could fail (e.g. `div` by zero,
+    /// or overflow in checked editions), it returns an `Option` instead.
+    ///
+    /// # Errors
+    /// If the definition is unknown, or uses a word without a static stack effect, e.g. `print`.
+    pub fn to_rust(&self, name: &str) -> Result<String, Error> {
+        let p = self.analyze(name)?;
+        let checked = self.edition().checked_arithmetic();
+        let fallible = p.ops.iter().any(|op| op.fallible(checked));
+        let params = (0..p.params)
+            .rev()
+            .map(|k| {
+                let v = Value::Param(k);
+                let used = p.results.contains(&v) || p.ops.iter().any(|op| op.uses(v));
+                format!("{}{}: i64", if used { "" } else { "_" }, p.rust(v))
+            })
+            .collect::<Vec<_>>();
+        let results = p.results.iter().map(|&v| p.rust(v)).collect::<Vec<_>>();
+        let (ty, ret) = match results.as_slice() {
+            [] => ("()".into(), "()".into()),
+            [r] => ("i64".into(), r.clone()),
+            rs => (
+                format!("({})", vec!["i64"; rs.len()].join(", ")),
+                format!("({})", rs.join(", ")),
+            ),
+        };
+        let (ty, ret) = if fallible {
+            (format!("Option<{ty}>"), format!("Some({ret})"))
+        } else {
+            (ty, ret)
+        };
+        let mut lines = vec![
+            format!(
+                "/// `{name}`, exported from drsm (edition {}): `{}`",
+                self.edition(),
+                self.lookup(name).unwrap_or_default()
+            ),
+            "#[must_use]".into(),
+            format!(
+                "pub fn {}({}){} {{",
+                rust_ident(name),
+                params.join(", "),
+                if ty == "()" {
+                    String::new()
+                } else {
+                    format!(" -> {ty}")
+                }
+            ),
+        ];
+        lines.extend(
+            p.ops
+                .iter()
+                .enumerate()
+                .map(|(i, op)| format!("    let v{i} = {};", p.rust_op(op, checked))),
+        );
+        if ret != "()" {
+            lines.push(format!("    {ret}"));
+        }
+        lines.push("}".into());
+        lines.push(String::new());
+        Ok(lines.join("\n"))
+    }
+    /// Translate a definition (inlining the custom words it uses) into a standalone WebAssembly
+    /// text module, exporting a function performing the same computation under this machine's
+    /// edition.
+    ///
+    /// The function's parameters & results are as with `to_rust`; where evaluation would fail, it
+    /// traps instead.
+    ///
+    /// # Errors
+    /// If the definition is unknown, or uses a word without a static stack effect, e.g. `print`.
+    pub fn to_wat(&self, name: &str) -> Result<String, Error> {
+        let p = self.analyze(name)?;
+        let checked = self.edition().checked_arithmetic();
+        let sig = format!(
+            "  (func (export \"{}\"){}{}",
+            wat_escape(name),
+            (0..p.params).fold(String::new(), |s, i| s + &format!(" (param $a{i} i64)")),
+            " (result i64)".repeat(p.results.len())
+        );
+        let mut lines = vec![
+            format!(
+                ";; `{name}`, exported from drsm (edition {}): `{}`",
+                self.edition(),
+                self.lookup(name).unwrap_or_default()
+            ),
+            "(module".into(),
+            sig,
+        ];
+        if !p.ops.is_empty() {
+            lines.push(format!(
+                "    {}",
+                (0..p.ops.len())
+                    .map(|i| format!("(local $v{i} i64)"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+        lines.extend(
+            p.ops
+                .iter()
+                .enumerate()
+                .map(|(i, op)| format!("    (local.set $v{i} {})", p.wat_op(op))),
+        );
+        lines.extend(p.results.iter().map(|&v| format!("    {}", p.wat(v))));
+        if let Some(l) = lines.last_mut() {
+            l.push(')');
+        }
+        let mut helpers = Vec::new();
+        for c in [Core::Add, Core::Sub, Core::Mul, Core::Div, Core::Mod] {
+            if p.ops
+                .iter()
+                .any(|op| matches!(op, Op::Arith(d, ..) if *d == c))
+            {
+                helpers.extend(wat_helper(c, checked));
+            }
+        }
+        lines.extend(helpers.into_iter().map(String::from));
+        if let Some(l) = lines.last_mut() {
+            l.push(')');
+        }
+        lines.push(String::new());
+        Ok(lines.join("\n"))
+    }
+    /// Work out the static stack effect of a definition, as straight-line operations.
+    fn analyze(&self, name: &str) -> Result<Program, Error> {
+        let mut p = Program::default();
+        let k = self
+            .defined(name)
+            .ok_or_else(|| Error::Unknown(name.into()))?;
+        p.word(self, &Word::Custom(k), &mut Vec::new())?;
+        Ok(p)
+    }
+}
+
+/// A value on the stack during analysis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Value {
+    /// The `k`th value consumed from the caller's stack, counting down from the top.
+    Param(usize),
+    /// The result of the `i`th operation.
+    Var(usize),
+    /// A constant.
+    Num(i64),
+}
+
+/// An operation computing a new value; operands are listed from the top of the stack down.
+#[derive(Debug)]
+enum Op {
+    Arith(Core, Value, Value),
+    Test(Core, Value),
+    Logic(Core, Value, Value),
+    Compare(Core, Value, Value),
+    Bits(Core, Value, Value),
+    Select(Value, Value, Value),
+}
+
+impl Op {
+    /// Could this operation fail?
+    const fn fallible(&self, checked: bool) -> bool {
+        match self {
+            Self::Arith(Core::Div | Core::Mod, ..) => true,
+            Self::Arith(..) => checked,
+            Self::Test(..)
+            | Self::Logic(..)
+            | Self::Compare(..)
+            | Self::Bits(..)
+            | Self::Select(..) => false,
+        }
+    }
+    /// Does this operation read `v`?
+    fn uses(&self, v: Value) -> bool {
+        match *self {
+            Self::Arith(_, x, y)
+            | Self::Logic(_, x, y)
+            | Self::Compare(_, x, y)
+            | Self::Bits(_, x, y) => x == v || y == v,
+            Self::Test(_, x) => x == v,
+            Self::Select(x, y, z) => x == v || y == v || z == v,
+        }
+    }
+}
+
+/// A definition's static stack effect: operations on the parameters, leaving the results.
+#[derive(Debug, Default)]
+struct Program {
+    params: usize,
+    ops: Vec<Op>,
+    /// The stack left behind, from the bottom up.
+    results: Vec<Value>,
+}
+
+impl Program {
+    fn pop(&mut self) -> Value {
+        self.results.pop().unwrap_or_else(|| {
+            self.params += 1;
+            Value::Param(self.params - 1)
+        })
+    }
+    fn push(&mut self, op: Op) {
+        self.ops.push(op);
+        self.results.push(Value::Var(self.ops.len() - 1));
+    }
+    /// Analyze `word`, with `active` the custom words currently being inlined.
+    fn word(
+        &mut self,
+        machine: &Machine,
+        word: &Word,
+        active: &mut Vec<Symbol>,
+    ) -> Result<(), Error> {
+        match word {
+            Word::Num(n) => self.results.push(Value::Num(*n)),
+            Word::Custom(c) => {
+                if active.contains(c) {
+                    return Err(Error::SelfRef(c.to_string()));
+                }
+                let body = machine
+                    .definition(c)
+                    .ok_or_else(|| Error::Unknown(c.to_string()))?;
+                active.push(c.clone());
+                for w in body {
+                    self.word(machine, w, active)?;
+                }
+                active.pop();
+            }
+            Word::Core(c) => self.core(machine, *c)?,
+            Word::Fused(f) => {
+                for c in f.parts() {
+                    self.core(machine, c)?;
+                }
+            }
+            _ => return Err(Error::NotExportable(word.to_string())),
+        }
+        Ok(())
+    }
+    /// Analyze the core word `c`.
+    fn core(&mut self, machine: &Machine, c: Core) -> Result<(), Error> {
+        match c {
+            Core::Drop => {
+                self.pop();
+            }
+            Core::Dup => {
+                let x = self.pop();
+                self.results.extend([x, x]);
+            }
+            Core::Swap => {
+                let (x, y) = (self.pop(), self.pop());
+                self.results.push(x);
+                self.results.push(y);
+            }
+            Core::Over => {
+                let (x, y) = (self.pop(), self.pop());
+                self.results.extend([y, x, y]);
+            }
+            Core::Rot => {
+                let (x, y, z) = (self.pop(), self.pop(), self.pop());
+                self.results.extend([y, x, z]);
+            }
+            Core::Nip => {
+                let x = self.pop();
+                self.pop();
+                self.results.push(x);
+            }
+            Core::TwoDrop => {
+                self.pop();
+                self.pop();
+            }
+            Core::TwoSwap => {
+                let v: [_; 4] = std::array::from_fn(|_| self.pop());
+                self.results.extend([v[1], v[0], v[3], v[2]]);
+            }
+            Core::TwoDup => {
+                let (x, y) = (self.pop(), self.pop());
+                self.results.extend([y, x, y, x]);
+            }
+            Core::TwoOver => {
+                let v: [_; 4] = std::array::from_fn(|_| self.pop());
+                self.results.extend([v[3], v[2], v[1], v[0], v[3], v[2]]);
+            }
+            Core::Tuck => {
+                let (x, y) = (self.pop(), self.pop());
+                self.results.extend([x, y, x]);
+            }
+            Core::Add | Core::Sub | Core::Mul | Core::Div | Core::Mod => {
+                let (x, y) = (self.pop(), self.pop());
+                self.push(Op::Arith(c, x, y));
+            }
+            Core::Zero if machine.edition().zero_predicate() => {
+                let x = self.pop();
+                self.push(Op::Test(c, x));
+            }
+            Core::True => self.results.push(Value::Num(1)),
+            Core::False => self.results.push(Value::Num(0)),
+            Core::And | Core::Or | Core::Xor => {
+                let (x, y) = (self.pop(), self.pop());
+                self.push(Op::Logic(c, x, y));
+            }
+            Core::Eq | Core::Neq | Core::Lt | Core::Gt | Core::Le | Core::Ge => {
+                let (x, y) = (self.pop(), self.pop());
+                self.push(Op::Compare(c, x, y));
+            }
+            Core::BAnd | Core::BOr | Core::BXor | Core::Shl | Core::Shr => {
+                let (x, y) = (self.pop(), self.pop());
+                self.push(Op::Bits(c, x, y));
+            }
+            Core::BNot => {
+                let x = self.pop();
+                self.push(Op::Bits(Core::BXor, x, Value::Num(-1)));
+            }
+            Core::Neg | Core::Pos | Core::Not => {
+                let x = self.pop();
+                self.push(Op::Test(c, x));
+            }
+            Core::Zero | Core::Select => {
+                let (x, y, z) = (self.pop(), self.pop(), self.pop());
+                self.push(Op::Select(x, y, z));
+            }
+            // Words with effects, or on values other than integers.
+            _ => return Err(Error::NotExportable(c.to_string())),
+        }
+        Ok(())
+    }
+    /// Parameters are named from the bottom of the stack up.
+    const fn param_index(&self, k: usize) -> usize {
+        self.params - 1 - k
+    }
+    fn rust(&self, v: Value) -> String {
+        match v {
+            Value::Param(k) => format!("a{}", self.param_index(k)),
+            Value::Var(i) => format!("v{i}"),
+            Value::Num(n) => format!("{n}_i64"),
+        }
+    }
+    /// As `rust`, but parenthesized where need be to call a method on it.
+    fn rust_receiver(&self, v: Value) -> String {
+        match v {
+            Value::Num(n) if n < 0 => format!("({})", self.rust(v)),
+            _ => self.rust(v),
+        }
+    }
+    fn rust_op(&self, op: &Op, checked: bool) -> String {
+        match *op {
+            Op::Arith(c, x, y) => {
+                let (x, y) = (self.rust_receiver(x), self.rust(y));
+                match c {
+                    Core::Div if !checked => {
+                        format!("if {y} == 0 {{ return None }} else {{ {x}.saturating_div({y}) }}")
+                    }
+                    Core::Mod => format!("{x}.checked_rem_euclid({y})?"),
+                    _ if checked => format!("{x}.checked_{c}({y})?"),
+                    _ => format!("{x}.saturating_{c}({y})"),
+                }
+            }
+            Op::Test(c, x) => {
+                let cmp = match c {
+                    Core::Neg => "<",
+                    Core::Pos => ">",
+                    _ => "==",
+                };
+                format!("i64::from({} {cmp} 0)", self.rust(x))
+            }
+            Op::Logic(c, x, y) => {
+                let op = match c {
+                    Core::And => "&&",
+                    Core::Or => "||",
+                    _ => "!=",
+                };
+                format!(
+                    "i64::from(({} != 0) {op} ({} != 0))",
+                    self.rust(x),
+                    self.rust(y)
+                )
+            }
+            Op::Compare(c, x, y) => {
+                let op = match c {
+                    Core::Eq => "==",
+                    Core::Neq => "!=",
+                    Core::Lt => "<",
+                    Core::Gt => ">",
+                    Core::Le => "<=",
+                    _ => ">=",
+                };
+                format!("i64::from({} {op} {})", self.rust(x), self.rust(y))
+            }
+            Op::Bits(c, x, y) => {
+                let (x, y) = (self.rust(x), self.rust(y));
+                match c {
+                    Core::BAnd => format!("{x} & {y}"),
+                    Core::BOr => format!("{x} | {y}"),
+                    Core::BXor => format!("{x} ^ {y}"),
+                    Core::Shl => format!("{y} << ({x} & 63)"),
+                    _ => format!("{y} >> ({x} & 63)"),
+                }
+            }
+            Op::Select(x, y, z) => format!(
+                "if {} == 0 {{ {} }} else {{ {} }}",
+                self.rust(x),
+                self.rust(y),
+                self.rust(z)
+            ),
+        }
+    }
+    fn wat(&self, v: Value) -> String {
+        match v {
+            Value::Param(k) => format!("(local.get $a{})", self.param_index(k)),
+            Value::Var(i) => format!("(local.get $v{i})"),
+            Value::Num(n) => format!("(i64.const {n})"),
+        }
+    }
+    fn wat_op(&self, op: &Op) -> String {
+        match *op {
+            Op::Arith(c, x, y) => format!("(call ${c} {} {})", self.wat(x), self.wat(y)),
+            Op::Test(c, x) => {
+                let test = match c {
+                    Core::Neg => format!("(i64.lt_s {} (i64.const 0))", self.wat(x)),
+                    Core::Pos => format!("(i64.gt_s {} (i64.const 0))", self.wat(x)),
+                    _ => format!("(i64.eqz {})", self.wat(x)),
+                };
+                format!("(i64.extend_i32_u {test})")
+            }
+            Op::Logic(c, x, y) => format!(
+                "(i64.extend_i32_u (i32.{c} (i64.ne {} (i64.const 0)) (i64.ne {} (i64.const 0))))",
+                self.wat(x),
+                self.wat(y)
+            ),
+            Op::Compare(c, x, y) => {
+                let op = match c {
+                    Core::Eq => "eq",
+                    Core::Neq => "ne",
+                    Core::Lt => "lt_s",
+                    Core::Gt => "gt_s",
+                    Core::Le => "le_s",
+                    _ => "ge_s",
+                };
+                format!(
+                    "(i64.extend_i32_u (i64.{op} {} {}))",
+                    self.wat(x),
+                    self.wat(y)
+                )
+            }
+            Op::Bits(c, x, y) => {
+                let op = match c {
+                    Core::BAnd => "and",
+                    Core::BOr => "or",
+                    Core::BXor => "xor",
+                    Core::Shl => "shl",
+                    _ => "shr_s",
+                };
+                match c {
+                    Core::Shl | Core::Shr => format!("(i64.{op} {} {})", self.wat(y), self.wat(x)),
+                    _ => format!("(i64.{op} {} {})", self.wat(x), self.wat(y)),
+                }
+            }
+            Op::Select(x, y, z) => format!(
+                "(select {} {} (i64.eqz {}))",
+                self.wat(y),
+                self.wat(z),
+                self.wat(x)
+            ),
+        }
+    }
+}
+
+/// A Rust identifier resembling `name`.
+fn rust_ident(name: &str) -> String {
+    let s = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if s.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{s}")
+    } else {
+        s
+    }
+}
+
+/// Escape `name` for use in a WAT string.
+fn wat_escape(name: &str) -> String {
+    name.chars().fold(String::new(), |mut s, c| {
+        if matches!(c, '"' | '\\') {
+            s.push('\\');
+        }
+        s.push(c);
+        s
+    })
+}
+
+/// Helper functions implementing an arithmetic word with drsm's semantics: saturating (or, in
+/// checked editions, trapping on overflow), and trapping wherever drsm would error.
+fn wat_helper(c: Core, checked: bool) -> Vec<&'static str> {
+    match (c, checked) {
+        (Core::Add, false) => vec![ADD_OVERFLOWS, SATURATING_ADD],
+        (Core::Add, true) => vec![ADD_OVERFLOWS, CHECKED_ADD],
+        (Core::Sub, false) => vec![SUB_OVERFLOWS, SATURATING_SUB],
+        (Core::Sub, true) => vec![SUB_OVERFLOWS, CHECKED_SUB],
+        (Core::Mul, false) => vec![MUL_OVERFLOWS, SATURATING_MUL],
+        (Core::Mul, true) => vec![MUL_OVERFLOWS, CHECKED_MUL],
+        (Core::Div, false) => vec![SATURATING_DIV],
+        (Core::Div, true) => vec![CHECKED_DIV],
+        _ => vec![MOD],
+    }
+}
+
+const ADD_OVERFLOWS: &str = "  (func $add_overflows (param $x i64) (param $y i64) (result i32)
+    (local $r i64)
+    (local.set $r (i64.add (local.get $x) (local.get $y)))
+    (i64.lt_s
+      (i64.and
+        (i64.xor (local.get $x) (local.get $r))
+        (i64.xor (local.get $y) (local.get $r)))
+      (i64.const 0)))";
+const SATURATING_ADD: &str = "  (func $add (param $x i64) (param $y i64) (result i64)
+    (if (result i64) (call $add_overflows (local.get $x) (local.get $y))
+      (then (select (i64.const -9223372036854775808) (i64.const 9223372036854775807)
+        (i64.lt_s (local.get $x) (i64.const 0))))
+      (else (i64.add (local.get $x) (local.get $y)))))";
+const CHECKED_ADD: &str = "  (func $add (param $x i64) (param $y i64) (result i64)
+    (if (call $add_overflows (local.get $x) (local.get $y)) (then unreachable))
+    (i64.add (local.get $x) (local.get $y)))";
+const SUB_OVERFLOWS: &str = "  (func $sub_overflows (param $x i64) (param $y i64) (result i32)
+    (i64.lt_s
+      (i64.and
+        (i64.xor (local.get $x) (local.get $y))
+        (i64.xor (local.get $x) (i64.sub (local.get $x) (local.get $y))))
+      (i64.const 0)))";
+const SATURATING_SUB: &str = "  (func $sub (param $x i64) (param $y i64) (result i64)
+    (if (result i64) (call $sub_overflows (local.get $x) (local.get $y))
+      (then (select (i64.const -9223372036854775808) (i64.const 9223372036854775807)
+        (i64.lt_s (local.get $x) (i64.const 0))))
+      (else (i64.sub (local.get $x) (local.get $y)))))";
+const CHECKED_SUB: &str = "  (func $sub (param $x i64) (param $y i64) (result i64)
+    (if (call $sub_overflows (local.get $x) (local.get $y)) (then unreachable))
+    (i64.sub (local.get $x) (local.get $y)))";
+const MUL_OVERFLOWS: &str = "  (func $mul_overflows (param $x i64) (param $y i64) (result i32)
+    (if (result i32) (i64.eqz (local.get $x))
+      (then (i32.const 0))
+      (else
+        (if (result i32) (i64.eq (local.get $x) (i64.const -1))
+          (then (i64.eq (local.get $y) (i64.const -9223372036854775808)))
+          (else
+            (i64.ne
+              (i64.div_s (i64.mul (local.get $x) (local.get $y)) (local.get $x))
+              (local.get $y)))))))";
+const SATURATING_MUL: &str = "  (func $mul (param $x i64) (param $y i64) (result i64)
+    (if (result i64) (call $mul_overflows (local.get $x) (local.get $y))
+      (then (select (i64.const -9223372036854775808) (i64.const 9223372036854775807)
+        (i64.lt_s (i64.xor (local.get $x) (local.get $y)) (i64.const 0))))
+      (else (i64.mul (local.get $x) (local.get $y)))))";
+const CHECKED_MUL: &str = "  (func $mul (param $x i64) (param $y i64) (result i64)
+    (if (call $mul_overflows (local.get $x) (local.get $y)) (then unreachable))
+    (i64.mul (local.get $x) (local.get $y)))";
+const SATURATING_DIV: &str = "  (func $div (param $x i64) (param $y i64) (result i64)
+    (if (result i64)
+      (i32.and
+        (i64.eq (local.get $x) (i64.const -9223372036854775808))
+        (i64.eq (local.get $y) (i64.const -1)))
+      (then (i64.const 9223372036854775807))
+      (else (i64.div_s (local.get $x) (local.get $y)))))";
+const CHECKED_DIV: &str = "  (func $div (param $x i64) (param $y i64) (result i64)
+    (i64.div_s (local.get $x) (local.get $y)))";
+const MOD: &str = "  (func $mod (param $x i64) (param $y i64) (result i64)
+    (local $r i64)
+    (if
+      (i32.and
+        (i64.eq (local.get $x) (i64.const -9223372036854775808))
+        (i64.eq (local.get $y) (i64.const -1)))
+      (then unreachable))
+    (local.set $r (i64.rem_s (local.get $x) (local.get $y)))
+    (if (result i64) (i64.lt_s (local.get $r) (i64.const 0))
+      (then
+        (i64.add (local.get $r)
+          (select (i64.sub (i64.const 0) (local.get $y)) (local.get $y)
+            (i64.lt_s (local.get $y) (i64.const 0)))))
+      (else (local.get $r))))";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Edition;
+
+    #[test]
+    fn rust() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("def sq dup mul").is_ok());
+        assert!(m.read_eval("def f sq -1 swap sub swap drop").is_ok());
+        assert_eq!(
+            m.to_rust("f"),
+            Ok(
+                "/// `f`, exported from drsm (edition 2025): `sq -1 swap sub swap drop`
+#[must_use]
+pub fn f(_a0: i64, a1: i64) -> i64 {
+    let v0 = a1.saturating_mul(a1);
+    let v1 = v0.saturating_sub(-1_i64);
+    v1
+}
+"
+                .into()
+            )
+        );
+        assert!(m.read_eval("def g 2 div").is_ok());
+        assert!(m.to_rust("g").is_ok_and(|s| s.contains("-> Option<i64>")));
+        assert!(m.read_eval("def both true and and").is_ok());
+        assert!(m.to_rust("both").is_ok_and(|s| s.contains(
+            "let v0 = i64::from((1_i64 != 0) && (a1 != 0));
+    let v1 = i64::from((v0 != 0) && (a0 != 0));"
+        )));
+        assert!(m.read_eval("def diff over sub nip").is_ok());
+        assert!(
+            m.to_rust("diff")
+                .is_ok_and(|s| s.contains("let v0 = a0.saturating_sub(a1);"))
+        );
+        assert!(m.read_eval("def low 4 shl bnot").is_ok());
+        assert!(m.to_rust("low").is_ok_and(|s| s.contains(
+            "let v0 = a0 << (4_i64 & 63);
+    let v1 = v0 ^ -1_i64;"
+        )));
+        assert!(m.read_eval("def p dup print").is_ok());
+        assert_eq!(m.to_rust("p"), Err(Error::NotExportable("print".into())));
+        assert_eq!(m.to_rust("nope"), Err(Error::Unknown("nope".into())));
+        assert!(m.read_eval("def r dup r").is_ok());
+        assert_eq!(m.to_rust("r"), Err(Error::SelfRef("r".into())));
+    }
+
+    #[test]
+    fn wat() {
+        let mut m = Machine::with_edition(Edition::Edition2026);
+        assert!(m.read_eval("def inc 1 add").is_ok());
+        assert!(m.read_eval("def nz? zero? zero?").is_ok());
+        let s = m.to_wat("inc").expect("exportable");
+        assert!(s.contains("(func (export \"inc\") (param $a0 i64) (result i64)"));
+        assert!(s.contains("(local.set $v0 (call $add (i64.const 1) (local.get $a0)))"));
+        assert!(s.contains(CHECKED_ADD));
+        assert!(!s.contains("$mul"));
+        let s = m.to_wat("nz?").expect("exportable");
+        assert!(s.contains("(i64.extend_i32_u (i64.eqz (local.get $v0)))"));
+        assert!(m.read_eval("def differ xor not").is_ok());
+        let s = m.to_wat("differ").expect("exportable");
+        assert!(s.contains(
+            "(i32.xor (i64.ne (local.get $a1) (i64.const 0)) (i64.ne (local.get $a0) (i64.const 0)))"
+        ));
+        assert!(m.read_eval("def below? lt?").is_ok());
+        let s = m.to_wat("below?").expect("exportable");
+        assert!(s.contains("(i64.extend_i32_u (i64.lt_s (local.get $a1) (local.get $a0)))"));
+        for s in [m.to_wat("inc"), m.to_wat("nz?"), m.to_wat("differ")]
+            .into_iter()
+            .flatten()
+        {
+            assert_eq!(s.matches('(').count(), s.matches(')').count());
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// The most characters `print-w` & `print-0w` may pad a number to.
+pub const MAX_WIDTH: usize = 1 << 10;
+
+/// What to pad a number with, on its left.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Pad {
+    /// Spaces, before any sign.
+    Space,
+    /// Zeroes, after any sign (so `-5` padded to 4 is `-005`).
+    Zero,
+}
+
+/// A number right-aligned in (at least) a width, e.g. for a column of a table.
+#[derive(Clone, Copy, Debug)]
+pub struct Padded<T> {
+    /// The number.
+    pub n: T,
+    /// How many characters it takes up, unless it needs more.
+    pub width: usize,
+    /// What it's padded with.
+    pub pad: Pad,
+}
+
+impl<T: fmt::Display> fmt::Display for Padded<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (n, w) = (&self.n, self.width);
+        match self.pad {
+            Pad::Space => write!(f, "{n:>w$}"),
+            Pad::Zero => write!(f, "{n:0w$}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded() {
+        let p = |n: i64, width, pad| Padded { n, width, pad }.to_string();
+        assert_eq!(p(42, 5, Pad::Space), "   42");
+        assert_eq!(p(-5, 4, Pad::Space), "  -5");
+        assert_eq!(p(-5, 4, Pad::Zero), "-005");
+        assert_eq!(p(12_345, 3, Pad::Zero), "12345");
+        assert_eq!(p(7, 0, Pad::Space), "7");
+    }
+}
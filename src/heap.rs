@@ -0,0 +1,65 @@
+use crate::Cell;
+use std::collections::BTreeMap;
+
+/// The arrays a machine has allocated, which (unlike its other values) may be mutated.
+///
+/// Arrays are sparse, keeping only their nonzero elements, so allocating a huge one is cheap
+/// rather than running the process out of memory.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Heap<T: Cell> {
+    arrays: Vec<Array<T>>,
+    /// The total length of the arrays.
+    used: usize,
+}
+
+/// An array's length & its nonzero elements, by index.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Array<T> {
+    len: usize,
+    cells: BTreeMap<usize, T>,
+}
+
+impl<T: Cell> Heap<T> {
+    /// How many arrays have been allocated.
+    pub const fn count(&self) -> usize {
+        self.arrays.len()
+    }
+    /// The total length of the arrays.
+    pub const fn used(&self) -> usize {
+        self.used
+    }
+    /// Allocate an array of `len` zeroes, returning its handle.
+    pub fn alloc(&mut self, len: usize) -> usize {
+        self.arrays.push(Array {
+            len,
+            cells: BTreeMap::new(),
+        });
+        self.used = self.used.saturating_add(len);
+        self.arrays.len() - 1
+    }
+    /// The length of array `h`.
+    pub fn len(&self, h: usize) -> usize {
+        self.arrays[h].len
+    }
+    /// The element at index `i` of array `h`.
+    pub fn get(&self, h: usize, i: usize) -> T {
+        self.arrays[h].cells.get(&i).cloned().unwrap_or(T::ZERO)
+    }
+    /// Store `v` at index `i` of array `h`.
+    pub fn set(&mut self, h: usize, i: usize, v: T) {
+        let cells = &mut self.arrays[h].cells;
+        if v == T::ZERO {
+            cells.remove(&i);
+        } else {
+            cells.insert(i, v);
+        }
+    }
+    /// Each array's length & nonzero elements (by index), in order of allocation.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, impl Iterator<Item = (usize, T)>)> {
+        self.arrays
+            .iter()
+            .map(|a| (a.len, a.cells.iter().map(|(&i, v)| (i, v.clone()))))
+    }
+}
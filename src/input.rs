@@ -0,0 +1,92 @@
+use crate::Error;
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// Where `read` gets integers from: standard input, unless a machine's given another source.
+///
+/// Cloning is cheap and yields a handle to the _same_ source, so a machine's clones & the workers
+/// it spawns read from it too, each integer going to just one of them.
+#[derive(Clone)]
+pub struct Input(Arc<Mutex<dyn Iterator<Item = Result<i64, Error>> + Send>>);
+
+impl Input {
+    /// Read whitespace-separated integers from this source, e.g. a file or a socket.
+    #[must_use]
+    pub fn new(source: impl Read + Send + 'static) -> Self {
+        Self::from_iter(Integers {
+            lines: BufReader::new(source).lines(),
+            pending: Vec::new(),
+        })
+    }
+    /// Read these integers, in order.
+    #[must_use]
+    pub fn values(xs: impl IntoIterator<Item = i64, IntoIter: Send + 'static>) -> Self {
+        Self::from_iter(xs.into_iter().map(Ok))
+    }
+    /// Read integers from standard input.
+    #[must_use]
+    pub fn stdin() -> Self {
+        Self::new(io::stdin())
+    }
+    fn from_iter(xs: impl Iterator<Item = Result<i64, Error>> + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(xs)))
+    }
+    /// Read the next integer, failing with `Error::EndOfInput` if there are no more, or another
+    /// error if the source does or holds something else.
+    pub(crate) fn read(&self) -> Result<i64, Error> {
+        let mut source = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        source.next().unwrap_or(Err(Error::EndOfInput))
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::stdin()
+    }
+}
+
+impl fmt::Debug for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Input(..)")
+    }
+}
+
+/// The integers in some lines, read a line at a time.
+struct Integers<R> {
+    lines: io::Lines<R>,
+    /// The rest of the current line's words, last first.
+    pending: Vec<String>,
+}
+
+impl<R: BufRead> Iterator for Integers<R> {
+    type Item = Result<i64, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            match self.lines.next()? {
+                Ok(line) => self.pending = line.split_whitespace().rev().map(Into::into).collect(),
+                Err(e) => return Some(Err(Error::Io(format!("input: {e}")))),
+            }
+        }
+        self.pending.pop().map(|s| s.parse().map_err(Error::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        let input = Input::new(&b"1 -2\n\n  3\nx 4"[..]);
+        let xs: Vec<_> = std::iter::repeat_with(|| input.read()).take(6).collect();
+        assert_eq!(xs[..3], [Ok(1), Ok(-2), Ok(3)]);
+        assert!(matches!(xs[3], Err(Error::Parsing(_))));
+        assert_eq!(xs[4..], [Ok(4), Err(Error::EndOfInput)]);
+        let input = Input::values(vec![5]);
+        assert_eq!(input.read(), Ok(5));
+        assert_eq!(input.read(), Err(Error::EndOfInput));
+    }
+}
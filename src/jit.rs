@@ -0,0 +1,603 @@
+use crate::{
+    cell::Cell,
+    core::Core,
+    def::{self, Env},
+    edition::Edition,
+    symbol::{BuildSymbolHasher, Symbol},
+    word::{Fused, Word},
+};
+use cranelift_codegen::{
+    ir::{
+        AbiParam, Block, InstBuilder, MemFlags, StackSlotData, StackSlotKind, Value,
+        condcodes::IntCC, types,
+    },
+    settings::{self, Configurable},
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{FuncId, Module};
+use std::{collections::HashMap, fmt, sync::atomic::AtomicBool};
+
+/// Definitions compiled to native code once they're hot: called at least `threshold` times.
+///
+/// Only definitions of pure integer arithmetic, literal numbers, stack shuffling, & calls to other
+/// such definitions are compiled, & only for cells with `i64`'s semantics. Compiled code gives up
+/// (leaving the interpreter to run the call) wherever the interpreter would fail, so it needn't
+/// report errors itself.
+pub struct Jit {
+    /// How many calls make a definition hot, or 0 not to compile any.
+    threshold: usize,
+    /// The edition the code was compiled for.
+    edition: Edition,
+    entries: HashMap<Symbol, Entry, BuildSymbolHasher>,
+    /// Where the code lives, once there's some.
+    module: Option<JITModule>,
+}
+
+// SAFETY: the module (whose raw pointers & `RefCell` keep it from being `Send` or `Sync`) is only
+// used through `&mut self`, & the code in it isn't written once it's finalized, so it may move
+// to (& run on) another thread, & sharing `&self` only shares the plain fields.
+#[allow(unsafe_code)]
+unsafe impl Send for Jit {}
+// SAFETY: as above.
+#[allow(unsafe_code)]
+unsafe impl Sync for Jit {}
+
+/// How many calls make a definition hot, unless the threshold's set.
+const THRESHOLD: usize = 64;
+
+/// The most calls compiled code nests, which bounds the native stack it uses.
+const MAX_NESTING: usize = 1 << 10;
+
+/// What's known about a definition.
+enum Entry {
+    /// It's been called this many times, but not compiled yet.
+    Cold(usize),
+    /// It can't be compiled.
+    Unfit,
+    Compiled(Compiled),
+}
+
+/// A compiled definition, & what running it does beyond the values it leaves, so the machine can
+/// account for it as if it'd been interpreted.
+#[derive(Clone, Copy, Debug)]
+pub struct Compiled {
+    /// How many values it takes from the stack.
+    pub inputs: usize,
+    /// How many values it leaves in their place.
+    pub outputs: usize,
+    /// How many words it executes, each burning a unit of fuel.
+    pub words: u64,
+    /// How far above where it starts the stack rises after any word.
+    pub rise: usize,
+    /// How far below where it starts the stack falls after any word.
+    pub fall: usize,
+    /// How many frames deep the interpreter would nest, tail calls replacing their callers'.
+    pub depth: usize,
+    /// How many native calls deep it nests.
+    nesting: usize,
+    id: FuncId,
+    /// The native code, once it's finalized.
+    code: Option<Code>,
+}
+
+/// Compiled code: given the values a definition takes (bottom first) in a buffer with room for
+/// those it leaves, & a cancellation flag, it writes the latter over the former & returns 0, or
+/// gives up & returns 1.
+type Code = extern "C" fn(*mut i64, *const AtomicBool) -> i8;
+
+impl Default for Jit {
+    fn default() -> Self {
+        Self {
+            threshold: THRESHOLD,
+            edition: Edition::default(),
+            entries: HashMap::default(),
+            module: None,
+        }
+    }
+}
+
+/// A clone starts afresh with the same threshold, as compiled code can't be shared.
+impl Clone for Jit {
+    fn clone(&self) -> Self {
+        let mut jit = Self::default();
+        jit.threshold = self.threshold;
+        jit
+    }
+}
+
+impl fmt::Debug for Jit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Jit")
+            .field("threshold", &self.threshold)
+            .field("edition", &self.edition)
+            .field("entries", &self.entries.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Jit {
+    fn drop(&mut self) {
+        self.forget();
+    }
+}
+
+impl Compiled {
+    /// Run the code on `io`, which holds the values it takes (bottom first) & has room for those it
+    /// leaves, returning whether it finished; if it gave up, `io` may hold anything.
+    pub fn run(&self, io: &mut [i64], cancel: &AtomicBool) -> bool {
+        debug_assert!(io.len() >= self.inputs.max(self.outputs));
+        let code = self.code.expect("Internal error @ jit code");
+        code(io.as_mut_ptr(), cancel) == 0
+    }
+}
+
+impl Jit {
+    /// How many calls make a definition hot, or 0 if none's compiled.
+    pub const fn threshold(&self) -> usize {
+        self.threshold
+    }
+    /// Compile definitions once they've been called `n` times, or none if `n` is 0.
+    pub fn set_threshold(&mut self, n: usize) {
+        self.threshold = n;
+        if n == 0 {
+            self.forget();
+        }
+    }
+    /// Forget everything compiled from the environment, as it's changed.
+    pub fn forget(&mut self) {
+        self.entries.clear();
+        if let Some(module) = self.module.take() {
+            // SAFETY: the only pointers into the module's code are in the entries just cleared.
+            #[allow(unsafe_code)]
+            unsafe {
+                module.free_memory();
+            };
+        }
+    }
+    /// Note a call to `k`, returning its compiled code if it's (now) hot & can be compiled.
+    pub fn call<T: Cell>(
+        &mut self,
+        env: &Env<T>,
+        edition: Edition,
+        k: &Symbol,
+    ) -> Option<Compiled> {
+        // Compiled code has `i64`'s semantics.
+        if self.threshold == 0 || T::BITS != 64 || T::from_i64(-1) > T::ZERO {
+            return None;
+        }
+        if edition != self.edition {
+            self.forget();
+            self.edition = edition;
+        }
+        match self.entries.entry(k.clone()).or_insert(Entry::Cold(0)) {
+            Entry::Compiled(c) => return Some(*c),
+            Entry::Unfit => return None,
+            Entry::Cold(n) if *n + 1 < self.threshold => {
+                *n += 1;
+                return None;
+            }
+            Entry::Cold(_) => {}
+        }
+        if self.module.is_none() {
+            self.module = module();
+        }
+        if self.module.is_none() {
+            self.entries.insert(k.clone(), Entry::Unfit);
+            return None;
+        }
+        let mut fresh = Vec::new();
+        let c = self.compile(env, k, &mut Vec::new(), &mut fresh);
+        let module = self.module.as_mut().expect("Internal error @ jit module");
+        if module.finalize_definitions().is_err() {
+            self.forget();
+            return None;
+        }
+        for j in fresh {
+            if let Some(Entry::Compiled(c)) = self.entries.get_mut(&j) {
+                let code = module.get_finalized_function(c.id);
+                // SAFETY: the function was compiled with `Code`'s signature, in the platform's
+                // default calling convention.
+                #[allow(unsafe_code)]
+                let code = unsafe { std::mem::transmute::<*const u8, Code>(code) };
+                c.code = Some(code);
+            }
+        }
+        c?;
+        match self.entries.get(k) {
+            Some(Entry::Compiled(c)) => Some(*c),
+            _ => None,
+        }
+    }
+    /// Compile `k` & whatever it calls that isn't yet, given the definitions being compiled
+    /// (innermost last), noting those compiled in `fresh`.
+    fn compile<T: Cell>(
+        &mut self,
+        env: &Env<T>,
+        k: &Symbol,
+        visiting: &mut Vec<Symbol>,
+        fresh: &mut Vec<Symbol>,
+    ) -> Option<Compiled> {
+        match self.entries.get(k) {
+            Some(Entry::Compiled(c)) => return Some(*c),
+            Some(Entry::Unfit) => return None,
+            _ if visiting.contains(k) || visiting.len() >= MAX_NESTING => return None,
+            _ => {}
+        }
+        visiting.push(k.clone());
+        let c = env.get(k).and_then(|body| {
+            let callees = body
+                .iter()
+                .map(|w| match w {
+                    Word::Custom(c) => self.compile(env, c, visiting, fresh).map(Some),
+                    _ => supported(w).then_some(None),
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let module = self.module.as_mut().expect("Internal error @ jit module");
+            define(module, self.edition, body, &callees)
+        });
+        visiting.pop();
+        match c {
+            Some(c) => {
+                self.entries.insert(k.clone(), Entry::Compiled(c));
+                fresh.push(k.clone());
+            }
+            None => {
+                self.entries.insert(k.clone(), Entry::Unfit);
+            }
+        }
+        c
+    }
+}
+
+/// A module for code native to this machine, if there's a backend for it.
+fn module() -> Option<JITModule> {
+    let mut flags = settings::builder();
+    flags.set("opt_level", "speed").ok()?;
+    let isa = cranelift_native::builder()
+        .ok()?
+        .finish(settings::Flags::new(flags))
+        .ok()?;
+    Some(JITModule::new(JITBuilder::with_isa(
+        isa,
+        cranelift_module::default_libcall_names(),
+    )))
+}
+
+/// Can this word (other than a call) be compiled?
+const fn supported<T>(word: &Word<T>) -> bool {
+    match word {
+        Word::Core(Core::Depth) => false,
+        Word::Num(_)
+        | Word::Float(_)
+        | Word::Fused(_)
+        | Word::Core(Core::Div | Core::Mod | Core::Zero) => true,
+        Word::Core(_) => def::effect(word).is_some(),
+        _ => false,
+    }
+}
+
+/// How many values a word (other than a call) takes & leaves.
+const fn effect<T>(word: &Word<T>, edition: Edition) -> (usize, usize) {
+    match word {
+        Word::Core(Core::Div | Core::Mod) => (2, 1),
+        Word::Core(Core::Zero) if edition.zero_predicate() => (1, 1),
+        Word::Core(Core::Zero) => (3, 1),
+        _ => def::effect(word).expect("Internal error @ jit effect"),
+    }
+}
+
+/// Compile a body, given the compiled definitions its calls are to (in order), returning it if it
+/// doesn't execute more words than can be counted or nest too deeply.
+fn define<T: Cell>(
+    module: &mut JITModule,
+    edition: Edition,
+    body: &[Word<T>],
+    callees: &[Option<Compiled>],
+) -> Option<Compiled> {
+    let (mut height, mut inputs, mut rise, mut fall) = (0, 0, 0, 0);
+    let (mut words, mut depth, mut nesting, mut scratch) = (body.len() as u64, 1, 1, 0);
+    for (i, (w, callee)) in body.iter().zip(callees).enumerate() {
+        let (taken, left) = match callee {
+            Some(c) => {
+                words = words.checked_add(c.words)?;
+                depth = depth.max(c.depth + usize::from(i + 1 < body.len()));
+                nesting = nesting.max(c.nesting + 1);
+                scratch = scratch.max(c.inputs.max(c.outputs));
+                rise = rise.max(height + c.rise.cast_signed());
+                fall = fall.min(height - c.fall.cast_signed());
+                (c.inputs, c.outputs)
+            }
+            None => effect(w, edition),
+        };
+        height -= taken.cast_signed();
+        inputs = inputs.max(-height);
+        height += left.cast_signed();
+        (rise, fall) = (rise.max(height), fall.min(height));
+    }
+    if nesting > MAX_NESTING {
+        return None;
+    }
+    let mut sig = module.make_signature();
+    let ptr = module.target_config().pointer_type();
+    sig.params = vec![AbiParam::new(ptr), AbiParam::new(ptr)];
+    sig.returns = vec![AbiParam::new(types::I8)];
+    let id = module.declare_anonymous_function(&sig).ok()?;
+    let mut ctx = module.make_context();
+    ctx.func.signature = sig;
+    let mut fctx = FunctionBuilderContext::new();
+    let mut b = FunctionBuilder::new(&mut ctx.func, &mut fctx);
+    let slot = b.create_sized_stack_slot(StackSlotData::new(
+        StackSlotKind::ExplicitSlot,
+        u32::try_from(8 * scratch).ok()?,
+        3,
+    ));
+    let (entry, run, bail) = (b.create_block(), b.create_block(), b.create_block());
+    b.append_block_params_for_function_params(entry);
+    b.switch_to_block(entry);
+    let (io, cancel) = (b.block_params(entry)[0], b.block_params(entry)[1]);
+    let cancelled = b.ins().atomic_load(types::I8, MemFlags::trusted(), cancel);
+    b.ins().brif(cancelled, bail, &[], run, &[]);
+    b.switch_to_block(run);
+    let inputs = inputs.cast_unsigned();
+    let mut stack = (0..inputs).map(|i| load(&mut b, io, i)).collect::<Vec<_>>();
+    let mut g = Gen { b, edition, bail };
+    for (w, callee) in body.iter().zip(callees) {
+        match callee {
+            Some(c) => {
+                let args = stack.split_off(stack.len() - c.inputs);
+                let addr = g.b.ins().stack_addr(ptr, slot, 0);
+                for (i, &x) in args.iter().enumerate() {
+                    store(&mut g.b, x, addr, i);
+                }
+                let f = module.declare_func_in_func(c.id, g.b.func);
+                let call = g.b.ins().call(f, &[addr, cancel]);
+                let status = g.b.inst_results(call)[0];
+                g.unless(status);
+                stack.extend((0..c.outputs).map(|i| load(&mut g.b, addr, i)));
+            }
+            None => g.word(&mut stack, w),
+        }
+    }
+    let outputs = stack.len();
+    for (i, &x) in stack.iter().enumerate() {
+        store(&mut g.b, x, io, i);
+    }
+    let done = g.b.ins().iconst(types::I8, 0);
+    g.b.ins().return_(&[done]);
+    g.b.switch_to_block(bail);
+    let failed = g.b.ins().iconst(types::I8, 1);
+    g.b.ins().return_(&[failed]);
+    g.b.seal_all_blocks();
+    g.b.finalize();
+    module.define_function(id, &mut ctx).ok()?;
+    module.clear_context(&mut ctx);
+    Some(Compiled {
+        inputs,
+        outputs,
+        words,
+        rise: rise.cast_unsigned(),
+        fall: (-fall).cast_unsigned(),
+        depth,
+        nesting,
+        id,
+        code: None,
+    })
+}
+
+/// Load the `i`th value in a buffer.
+fn load(b: &mut FunctionBuilder, at: Value, i: usize) -> Value {
+    let offset = i32::try_from(8 * i).expect("Internal error @ jit load");
+    b.ins().load(types::I64, MemFlags::trusted(), at, offset)
+}
+
+/// Store a value as the `i`th in a buffer.
+fn store(b: &mut FunctionBuilder, x: Value, at: Value, i: usize) {
+    let offset = i32::try_from(8 * i).expect("Internal error @ jit store");
+    b.ins().store(MemFlags::trusted(), x, at, offset);
+}
+
+/// Generates a body's code, word by word, on a stack of values as the interpreter would.
+struct Gen<'a> {
+    b: FunctionBuilder<'a>,
+    edition: Edition,
+    /// Where to go to give up.
+    bail: Block,
+}
+
+impl Gen<'_> {
+    /// Give up if `c` is nonzero, & otherwise go on.
+    fn unless(&mut self, c: Value) {
+        let next = self.b.create_block();
+        self.b.ins().brif(c, self.bail, &[], next, &[]);
+        self.b.switch_to_block(next);
+    }
+    /// An `i64` constant.
+    fn int(&mut self, n: i64) -> Value {
+        self.b.ins().iconst(types::I64, n)
+    }
+    /// The truth value (1 or 0) of a comparison.
+    fn truth(&mut self, cc: IntCC, x: Value, y: Value) -> Value {
+        let c = self.b.ins().icmp(cc, x, y);
+        self.b.ins().uextend(types::I64, c)
+    }
+    /// The truth value of `x` as a boolean (1 if nonzero, or 0).
+    fn bool(&mut self, x: Value) -> Value {
+        let zero = self.int(0);
+        self.truth(IntCC::NotEqual, x, zero)
+    }
+    /// The result of `x op y` if it didn't overflow, & otherwise the bound it overflowed past, or
+    /// (in editions with checked arithmetic) give up.
+    fn saturating(&mut self, (r, overflowed): (Value, Value), negative: Value) -> Value {
+        if self.edition.checked_arithmetic() {
+            self.unless(overflowed);
+            return r;
+        }
+        let zero = self.int(0);
+        let below = self.b.ins().icmp(IntCC::SignedLessThan, negative, zero);
+        let (min, max) = (self.int(i64::MIN), self.int(i64::MAX));
+        let bound = self.b.ins().select(below, min, max);
+        self.b.ins().select(overflowed, bound, r)
+    }
+    /// Generate a word's code.
+    fn word<T: Cell>(&mut self, stack: &mut Vec<Value>, word: &Word<T>) {
+        let s = stack.len();
+        match word {
+            Word::Num(n) => {
+                let n = self.int(n.as_i64());
+                stack.push(n);
+            }
+            Word::Float(x) => {
+                let n = self.int(x.to_bits().cast_signed());
+                stack.push(n);
+            }
+            Word::Fused(Fused::DupAdd) => {
+                let x = stack[s - 1];
+                stack[s - 1] = self.arithmetic(Core::Add, x, x);
+            }
+            Word::Fused(Fused::DupMul) => {
+                let x = stack[s - 1];
+                stack[s - 1] = self.arithmetic(Core::Mul, x, x);
+            }
+            Word::Fused(Fused::SwapDrop) => drop(stack.swap_remove(s - 2)),
+            Word::Core(c) => self.core(stack, *c),
+            _ => unreachable!("`{word}` isn't compiled"),
+        }
+    }
+    /// Generate a core word's code, as `eval_core` & co. evaluate it.
+    fn core(&mut self, stack: &mut Vec<Value>, c: Core) {
+        let s = stack.len();
+        match c {
+            Core::Drop => drop(stack.pop()),
+            Core::Swap => stack.swap(s - 1, s - 2),
+            Core::Dup => stack.push(stack[s - 1]),
+            Core::Over => stack.push(stack[s - 2]),
+            Core::Rot => stack[s - 3..].rotate_left(1),
+            Core::Nip => drop(stack.remove(s - 2)),
+            Core::Tuck => stack.insert(s - 2, stack[s - 1]),
+            Core::TwoDrop => stack.truncate(s - 2),
+            Core::TwoSwap => stack[s - 4..].rotate_left(2),
+            Core::TwoDup => stack.extend_from_within(s - 2..),
+            Core::TwoOver => stack.extend_from_within(s - 4..s - 2),
+            Core::True | Core::False => {
+                let b = self.int(i64::from(c == Core::True));
+                stack.push(b);
+            }
+            _ => {
+                let (taken, _) = effect(&Word::<i64>::Core(c), self.edition);
+                let xs = stack.split_off(s - taken);
+                let r = self.operation(c, &xs);
+                stack.push(r);
+            }
+        }
+    }
+    /// The value an operation leaves, given those it takes (bottom first).
+    fn operation(&mut self, c: Core, xs: &[Value]) -> Value {
+        let (x, y) = match xs {
+            [.., y, x] => (*x, *y),
+            _ => (xs[0], xs[0]),
+        };
+        match c {
+            Core::Add | Core::Sub | Core::Mul | Core::Div | Core::Mod => self.arithmetic(c, x, y),
+            Core::Zero if self.edition.zero_predicate() => {
+                let zero = self.int(0);
+                self.truth(IntCC::Equal, x, zero)
+            }
+            Core::Zero | Core::Select => {
+                let zero = self.int(0);
+                let c = self.b.ins().icmp(IntCC::Equal, x, zero);
+                self.b.ins().select(c, y, xs[0])
+            }
+            Core::Neg => {
+                let zero = self.int(0);
+                self.truth(IntCC::SignedLessThan, x, zero)
+            }
+            Core::Pos => {
+                let zero = self.int(0);
+                self.truth(IntCC::SignedGreaterThan, x, zero)
+            }
+            Core::Not => {
+                let zero = self.int(0);
+                self.truth(IntCC::Equal, x, zero)
+            }
+            Core::And | Core::Or | Core::Xor => {
+                let (x, y) = (self.bool(x), self.bool(y));
+                match c {
+                    Core::And => self.b.ins().band(x, y),
+                    Core::Or => self.b.ins().bor(x, y),
+                    _ => self.b.ins().bxor(x, y),
+                }
+            }
+            Core::Eq => self.truth(IntCC::Equal, x, y),
+            Core::Neq => self.truth(IntCC::NotEqual, x, y),
+            Core::Lt => self.truth(IntCC::SignedLessThan, x, y),
+            Core::Gt => self.truth(IntCC::SignedGreaterThan, x, y),
+            Core::Le => self.truth(IntCC::SignedLessThanOrEqual, x, y),
+            Core::Ge => self.truth(IntCC::SignedGreaterThanOrEqual, x, y),
+            Core::Min => self.b.ins().smin(x, y),
+            Core::Max => self.b.ins().smax(x, y),
+            Core::Abs | Core::Negate => {
+                let zero = self.int(0);
+                let r = self.b.ins().ssub_overflow(zero, x);
+                let r = self.saturating(r, zero);
+                if c == Core::Abs {
+                    let negative = self.b.ins().icmp(IntCC::SignedLessThan, x, zero);
+                    self.b.ins().select(negative, r, x)
+                } else {
+                    r
+                }
+            }
+            Core::Popcount => self.b.ins().popcnt(x),
+            Core::BAnd => self.b.ins().band(x, y),
+            Core::BOr => self.b.ins().bor(x, y),
+            Core::BXor => self.b.ins().bxor(x, y),
+            Core::BNot => self.b.ins().bnot(x),
+            // Shifts only use the amount's low bits, as `eval_bits` masks it.
+            Core::Shl => self.b.ins().ishl(y, x),
+            Core::Shr => self.b.ins().sshr(y, x),
+            _ => unreachable!("`{c}` isn't compiled"),
+        }
+    }
+    /// Generate the code for `x op y`, saturating, or giving up where the interpreter would fail.
+    fn arithmetic(&mut self, c: Core, x: Value, y: Value) -> Value {
+        match c {
+            Core::Add => {
+                let r = self.b.ins().sadd_overflow(x, y);
+                self.saturating(r, x)
+            }
+            Core::Sub => {
+                let r = self.b.ins().ssub_overflow(x, y);
+                self.saturating(r, x)
+            }
+            Core::Mul => {
+                let r = self.b.ins().smul_overflow(x, y);
+                let sign = self.b.ins().bxor(x, y);
+                self.saturating(r, sign)
+            }
+            _ => {
+                let zero = self.b.ins().icmp_imm(IntCC::Equal, y, 0);
+                self.unless(zero);
+                let min = self.b.ins().icmp_imm(IntCC::Equal, x, i64::MIN);
+                let minus_one = self.b.ins().icmp_imm(IntCC::Equal, y, -1);
+                let edge = self.b.ins().band(min, minus_one);
+                if c == Core::Mod || self.edition.checked_arithmetic() {
+                    self.unless(edge);
+                }
+                // Dividing by 1 instead keeps the instruction from trapping at the edge.
+                let one = self.int(1);
+                let divisor = self.b.ins().select(edge, one, y);
+                if c == Core::Div {
+                    let q = self.b.ins().sdiv(x, divisor);
+                    let max = self.int(i64::MAX);
+                    return self.b.ins().select(edge, max, q);
+                }
+                let r = self.b.ins().srem(x, divisor);
+                let zero = self.int(0);
+                let negative = self.b.ins().icmp(IntCC::SignedLessThan, r, zero);
+                let magnitude = self.b.ins().iabs(y);
+                let adjusted = self.b.ins().iadd(r, magnitude);
+                self.b.ins().select(negative, adjusted, r)
+            }
+        }
+    }
+}
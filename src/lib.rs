@@ -5,10 +5,57 @@
 #![deny(missing_docs)]
 #![deny(unsafe_code)]
 
+mod builder;
+mod cancel;
+mod cell;
+mod channel;
 mod core;
+mod debug;
+mod def;
+mod edition;
 mod error;
+mod export;
+mod format;
+mod heap;
+mod input;
+#[cfg(feature = "jit")]
+mod jit;
+mod limits;
 mod machine;
+mod memo;
+mod output;
+pub mod paths;
+mod program;
+mod ratio;
+mod reader;
+mod redefinition;
+#[cfg(feature = "repl")]
+pub mod repl;
+mod snapshot;
+mod stats;
+mod symbol;
 mod token;
 mod word;
+#[cfg(feature = "concurrency")]
+mod worker;
 
-pub use crate::{core::Core, error::Error, machine::Machine};
+pub use crate::{
+    builder::MachineBuilder,
+    cancel::CancelToken,
+    cell::Cell,
+    channel::Channels,
+    core::Core,
+    debug::{Debugger, Pause, Resume},
+    edition::Edition,
+    error::Error,
+    input::Input,
+    limits::{Capabilities, Limits, Profile},
+    machine::Machine,
+    output::Output,
+    program::{Program, Statement, parse},
+    redefinition::Redefinition,
+    stats::{EvalStats, Stats},
+    symbol::Symbol,
+    token::{Based, SpannedToken, Token, lex},
+    word::{Fused, Word},
+};
@@ -0,0 +1,154 @@
+use crate::{cell::Cell, core::Core, word::Word};
+
+/// Resource limits enforced during evaluation; `None` means unlimited.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Limits {
+    /// The most words a single call to `read_eval` may execute.
+    pub fuel: Option<u64>,
+    /// The most values the stack may hold.
+    pub stack_depth: Option<usize>,
+    /// The most deeply custom words may be nested while executing.
+    pub recursion_depth: Option<usize>,
+    /// The most definitions the environment may hold.
+    pub env_size: Option<usize>,
+    /// The most elements the heap's arrays may hold between them.
+    pub heap_size: Option<usize>,
+    /// The most bytes the environment, stack, & values they refer to may take up, as
+    /// `Machine::memory_usage` reckons.
+    pub memory: Option<usize>,
+    /// Which words with side effects may be executed.
+    pub capabilities: Capabilities,
+}
+
+/// Which kinds of side effect evaluation may have; words having a disallowed one fail with
+/// `Error::CapabilityDenied`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Capabilities {
+    /// May words print (e.g. `print`, `emit`, & `.s`)?
+    pub print: bool,
+    /// May words read input (e.g. `read`)?
+    pub read: bool,
+    /// May words communicate with other machines, over channels (`send` & `recv`) or by
+    /// spawning workers & joining them (`spawn` & `join`)?
+    pub communicate: bool,
+}
+
+impl Capabilities {
+    /// Every side effect allowed.
+    pub const ALL: Self = Self {
+        print: true,
+        read: true,
+        communicate: true,
+    };
+    /// No side effects allowed, so evaluation is pure.
+    pub const NONE: Self = Self {
+        print: false,
+        read: false,
+        communicate: false,
+    };
+    /// May this word be executed?
+    pub(crate) const fn allow<T: Cell>(self, w: &Word<T>) -> bool {
+        let Word::Core(c) = w else {
+            return match w {
+                Word::Send(_) | Word::Recv(_) => self.communicate,
+                #[cfg(feature = "concurrency")]
+                Word::Spawn(_) | Word::Join => self.communicate,
+                _ => true,
+            };
+        };
+        match c {
+            Core::Print
+            | Core::Peek
+            | Core::PrintBare
+            | Core::PrintWidth
+            | Core::PrintZeroes
+            | Core::HexPrint
+            | Core::BinPrint
+            | Core::FPrint
+            | Core::SPrint
+            | Core::RPrint
+            | Core::Emit
+            | Core::ShowStack => self.print,
+            Core::Read => self.read,
+            #[cfg(feature = "bignum")]
+            Core::BPrint => self.print,
+            _ => true,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Profile::default().limits()
+    }
+}
+
+/// Preset combinations of limits, from most to least restrictive.
+#[derive(
+    PartialEq, Eq, Clone, Copy, Debug, Default, strum::Display, strum::EnumIter, strum::EnumString,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum Profile {
+    /// For untrusted input: small limits and no side effects.
+    Strict,
+    /// Generous limits that still stop runaway programs.
+    Standard,
+    /// No limits at all.
+    #[default]
+    Trusted,
+}
+
+impl Profile {
+    /// The limits this profile bundles.
+    #[must_use]
+    pub const fn limits(self) -> Limits {
+        match self {
+            Self::Strict => Limits {
+                fuel: Some(1 << 20),
+                stack_depth: Some(1 << 10),
+                recursion_depth: Some(1 << 6),
+                env_size: Some(1 << 8),
+                heap_size: Some(1 << 16),
+                memory: Some(1 << 20),
+                capabilities: Capabilities::NONE,
+            },
+            Self::Standard => Limits {
+                fuel: Some(1 << 30),
+                stack_depth: Some(1 << 16),
+                recursion_depth: Some(1 << 10),
+                env_size: Some(1 << 12),
+                heap_size: Some(1 << 24),
+                memory: Some(1 << 30),
+                capabilities: Capabilities::ALL,
+            },
+            Self::Trusted => Limits {
+                fuel: None,
+                stack_depth: None,
+                recursion_depth: None,
+                env_size: None,
+                heap_size: None,
+                memory: None,
+                capabilities: Capabilities::ALL,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn roundtrip() {
+        for p in Profile::iter() {
+            assert_eq!(p.to_string().parse::<Profile>(), Ok(p));
+        }
+    }
+}
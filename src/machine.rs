@@ -1,179 +1,3095 @@
-use crate::{core::Core, error::Error, token::Token, word::Word};
-use indexmap::IndexMap;
+#[cfg(feature = "jit")]
+use crate::jit::Jit;
+#[cfg(feature = "concurrency")]
+use crate::worker::Workers;
+use crate::{
+    builder::MachineBuilder,
+    cancel::CancelToken,
+    cell::Cell,
+    channel::Channels,
+    core::Core,
+    debug::{Debugger, Pause, Resume},
+    def::{Def, Env},
+    edition::Edition,
+    error::Error,
+    format::{MAX_WIDTH, Pad, Padded},
+    heap::Heap,
+    input::Input,
+    limits::{Capabilities, Limits, Profile},
+    memo::Memo,
+    output::Output,
+    ratio::{self, Ratio},
+    redefinition::Redefinition,
+    snapshot::{Decoder, Encoder},
+    stats::{EvalStats, Stats},
+    symbol::{BuildSymbolHasher, Symbol, Symbols},
+    token::{Token, Wide, documents},
+    word::{Fused, Word},
+};
+use indexmap::{IndexMap, IndexSet};
 use lean_string::LeanString;
 use logos::Logos;
-use std::{convert::TryFrom, fmt};
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+use std::{
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    io::{Read, Write},
+    ops::Range,
+    sync::Arc,
+    time::Instant,
+    vec,
+};
 use strum::IntoEnumIterator;
 
+/// A definition begun: its name, stack effect (if documented), body so far, & whether it's a
+/// `redef`.
+type Open<T> = (LeanString, Option<LeanString>, Vec<Word<T>>, bool);
+
 /// The main data structure: a stack machine with an environment of local definitions.
-#[derive(Debug)]
-pub struct Machine {
-    env: IndexMap<LeanString, Vec<Word>>,
-    stack: Vec<i64>,
+///
+/// The stack's made of `i64`s unless another `Cell` type is chosen, e.g. `Machine::<u64>::new()`.
+///
+/// A clone shares its original's channels & cancel token, but not its outstanding tasks. Machines are equal (&
+/// hash alike) if their environments & stacks are, definitions compared in order.
+///
+/// With the `serde` feature, a machine (de)serializes its definitions, constants, stack, & the
+/// values its handles refer to, but not its configuration: limits, channels, or workers.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Saved<T>"))]
+pub struct Machine<T: Cell = i64> {
+    /// Shared, so worker threads can read a frozen copy; `def` copies on write.
+    env: Arc<Env<T>>,
+    /// Values bound by `const`, which replace their names as input's read.
+    consts: IndexMap<LeanString, T>,
+    /// Stack-effect comments documenting definitions, by name.
+    effects: IndexMap<LeanString, LeanString>,
+    /// A definition begun but not yet ended by `;`, if any.
+    open: Option<Open<T>>,
+    /// What `def` & `var` do when their name's already defined.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    redefinition: Redefinition,
+    /// The longest body `def` splices into its callers, or 0 not to.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    inline: usize,
+    /// Does `def` optimize bodies?
+    #[cfg_attr(feature = "serde", serde(skip))]
+    optimize: bool,
+    state: State<T>,
 }
 
-impl Default for Machine {
+/// Everything evaluation may mutate, kept apart from the environment it reads.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct State<T: Cell> {
+    stack: Vec<T>,
+    edition: Edition,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stats: Stats,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    channels: Channels<T>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cancel: CancelToken,
+    /// Where printing words write.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    output: Output,
+    /// Where `read` reads from.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    input: Input,
+    /// Where each word executed is traced, if anywhere.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    trace: Option<Output>,
+    /// What pauses evaluation, if anything.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    debugger: Option<Debugger<T>>,
+    #[cfg(feature = "concurrency")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    workers: Workers<T>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    limits: Limits,
+    /// Words left to execute in the current `read_eval`, if limited.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    fuel: Option<u64>,
+    /// The most values the stack may hold in the current `read_eval` without exceeding the
+    /// memory limit, if any, given the environment's size.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    room: Option<usize>,
+    /// The stack heights at which markers sit, in increasing order.
+    marks: Vec<usize>,
+    /// The value most recently printed.
+    last: Option<T>,
+    // The tables below are copied on write, so cloning the state (e.g. to roll back, or for
+    // `?undo`) shares them until one copy changes.
+    /// Quotations pushed so far, interned; the stack holds their indices as handles.
+    quotes: Arc<IndexSet<Arc<[Word<T>]>>>,
+    /// Strings pushed or made so far, interned; the stack holds their indices as handles.
+    strings: Arc<IndexSet<Arc<str>>>,
+    /// Ratios pushed or made so far, in lowest terms & interned; the stack holds their indices as
+    /// handles.
+    ratios: Arc<IndexSet<Ratio>>,
+    /// Bignums pushed or made so far, interned; the stack holds their indices as handles.
+    #[cfg(feature = "bignum")]
+    bigs: Arc<IndexSet<BigInt>>,
+    /// Arrays allocated so far; the stack holds their indices as handles.
+    heap: Arc<Heap<T>>,
+    /// The names of words defined so far, interned; the environment's keyed by their symbols.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    symbols: Symbols,
+    /// The results of calls to pure definitions, if they're memoized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    memo: Arc<Memo<T>>,
+    /// Definitions compiled to native code once they're hot.
+    #[cfg(feature = "jit")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    jit: Jit,
+}
+
+impl<T: Cell> Default for State<T> {
     fn default() -> Self {
         Self {
-            env: IndexMap::with_capacity(64),
             stack: Vec::with_capacity(64),
+            edition: Edition::default(),
+            stats: Stats::default(),
+            channels: Channels::default(),
+            cancel: CancelToken::default(),
+            output: Output::default(),
+            input: Input::default(),
+            trace: None,
+            debugger: None,
+            #[cfg(feature = "concurrency")]
+            workers: Workers::default(),
+            limits: Limits::default(),
+            fuel: None,
+            room: None,
+            marks: Vec::new(),
+            last: None,
+            quotes: Arc::default(),
+            strings: Arc::default(),
+            ratios: Arc::default(),
+            #[cfg(feature = "bignum")]
+            bigs: Arc::default(),
+            heap: Arc::default(),
+            symbols: Symbols::default(),
+            memo: Arc::default(),
+            #[cfg(feature = "jit")]
+            jit: Jit::default(),
         }
     }
 }
 
-impl fmt::Display for Machine {
+impl Default for Machine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Cell> PartialEq for Machine<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.env.as_slice() == other.env.as_slice() && self.state.stack == other.state.stack
+    }
+}
+
+impl<T: Cell> Eq for Machine<T> {}
+
+impl<T: Cell> Hash for Machine<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.env.as_slice().hash(state);
+        self.state.stack.hash(state);
+    }
+}
+
+/// A precision (e.g. `{:.32}`) shows at most that many items per section, and the alternate flag
+/// (`{:#}`) puts each item on its own line.
+impl<T: Cell> fmt::Display for Machine<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "edition: {}", self.state.edition)?;
         f.write_str("core:")?;
-        for c in Core::iter() {
-            write!(f, " {c}")?;
-        }
+        items(f, Core::iter())?;
         f.write_str("\nenv:")?;
-        for k in self.env.keys() {
-            write!(f, " {k}")?;
-        }
+        let names = self.env.keys().map(Symbol::as_str);
+        let names = names.chain(self.consts.keys().map(LeanString::as_str));
+        items(f, names.collect::<Vec<_>>().into_iter())?;
         f.write_str("\nstack: [")?;
-        for t in self.stack.iter().rev() {
-            write!(f, " {t}")?;
+        items(f, self.state.stack.iter().rev())?;
+        f.write_str(if f.alternate() { "\n]" } else { " ]" })
+    }
+}
+
+/// Join items' string representations with spaces.
+fn unwords<T: fmt::Display>(ts: &[T]) -> String {
+    ts.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Write (up to the formatter's precision) items, separated by spaces or (alternately) lines.
+fn items<T: fmt::Display>(
+    f: &mut fmt::Formatter,
+    ts: impl ExactSizeIterator<Item = T>,
+) -> fmt::Result {
+    let n = ts.len();
+    let limit = f.precision().unwrap_or(n);
+    let sep = if f.alternate() { "\n  " } else { " " };
+    for t in ts.take(limit) {
+        write!(f, "{sep}{t}")?;
+    }
+    if n > limit {
+        write!(f, "{sep}… and {} more", n - limit)?;
+    }
+    Ok(())
+}
+
+/// A machine as it deserializes, before the names of its definitions (& the words they call) are
+/// interned.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct Saved<T: Cell> {
+    env: IndexMap<LeanString, Def<T>>,
+    consts: IndexMap<LeanString, T>,
+    effects: IndexMap<LeanString, LeanString>,
+    open: Option<Open<T>>,
+    state: State<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Cell> From<Saved<T>> for Machine<T> {
+    fn from(saved: Saved<T>) -> Self {
+        let mut state = saved.state;
+        let env = saved
+            .env
+            .into_iter()
+            .map(|(k, def)| {
+                let us = def.iter().map(|w| bind(&mut state.symbols, w.clone()));
+                let us = us.collect();
+                (state.symbols.intern(&k), Def::new(us))
+            })
+            .collect();
+        Self {
+            env: Arc::new(env),
+            consts: saved.consts,
+            effects: saved.effects,
+            open: saved.open,
+            state,
+            ..Self::new()
         }
-        f.write_str(" ]")
+    }
+}
+
+/// The machine as `to_json` renders it.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct Json<'a, T> {
+    edition: Edition,
+    core: Vec<String>,
+    env: IndexMap<&'a str, String>,
+    stack: &'a [T],
+}
+
+#[cfg(feature = "serde")]
+impl<T: Cell + serde::Serialize> Machine<T> {
+    /// Render the sections `Display` shows as JSON, for scripts & editors to read: the edition,
+    /// the core words, the environment (each definition's source, or constant's value, by name),
+    /// & the stack, bottom first.
+    ///
+    /// # Panics
+    /// If the cell type's `Serialize` fails, which the built-in cell types' don't.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut env: IndexMap<_, _> = self
+            .env
+            .keys()
+            .map(|k| (k.as_str(), self.source(k)))
+            .collect();
+        env.extend(self.consts.iter().map(|(k, v)| (k.as_str(), v.to_string())));
+        let json = Json {
+            edition: self.state.edition,
+            core: Core::iter().map(|c| c.to_string()).collect(),
+            env,
+            stack: &self.state.stack,
+        };
+        serde_json::to_string(&json).expect("Internal error @ json")
     }
 }
 
 impl Machine {
+    /// Create a machine using the semantics of the given edition.
+    #[must_use]
+    pub fn with_edition(edition: Edition) -> Self {
+        let mut m = Self::default();
+        m.state.edition = edition;
+        m
+    }
+    /// Start building a machine, with more control than the other constructors give.
+    #[must_use]
+    pub fn builder() -> MachineBuilder {
+        MachineBuilder::default()
+    }
+    /// Create a machine whose limits are given by a preset profile.
+    #[must_use]
+    pub fn with_profile(profile: Profile) -> Self {
+        let mut m = Self::default();
+        m.state.limits = profile.limits();
+        m
+    }
+}
+
+impl<T: Cell> Machine<T> {
+    /// Create a machine with the default edition & limits, and an empty stack.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_capacity(64, 64)
+    }
+    /// Create a machine with room for `stack` values & `env` definitions before either must grow.
+    pub(crate) fn with_capacity(stack: usize, env: usize) -> Self {
+        Self {
+            env: Arc::new(Env::with_capacity_and_hasher(
+                env,
+                BuildSymbolHasher::default(),
+            )),
+            consts: IndexMap::new(),
+            effects: IndexMap::new(),
+            open: None,
+            redefinition: Redefinition::default(),
+            inline: 0,
+            optimize: false,
+            state: State {
+                stack: Vec::with_capacity(stack),
+                ..State::default()
+            },
+        }
+    }
+    /// Push a value onto the stack.
+    ///
+    /// # Errors
+    /// If the stack's already as deep as the limits allow.
+    pub fn push(&mut self, x: T) -> Result<(), Error> {
+        match self.state.limits.stack_depth {
+            Some(n) if self.state.stack.len() >= n => Err(Error::StackOverflow(n)),
+            _ => match self.state.limits.memory {
+                Some(n) if self.memory_usage() + size_of::<T>() > n => Err(Error::MemoryFull(n)),
+                _ => {
+                    self.state.stack.push(x);
+                    Ok(())
+                }
+            },
+        }
+    }
+    /// Pop the value off the top of the stack, if there is one.
+    pub fn pop(&mut self) -> Option<T> {
+        self.state.stack.pop()
+    }
+    /// The value on top of the stack, if there is one.
+    #[must_use]
+    pub fn peek(&self) -> Option<T> {
+        self.state.stack.last().cloned()
+    }
+    /// The stack, from bottom to top.
+    #[must_use]
+    pub fn stack(&self) -> &[T] {
+        &self.state.stack
+    }
+    /// How many values are on the stack.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.state.stack.len()
+    }
+    /// Is the stack empty?
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.state.stack.is_empty()
+    }
+    /// Empty the stack (& remove any markers on it).
+    pub fn clear_stack(&mut self) {
+        self.state.stack.clear();
+        self.state.marks.clear();
+    }
+    /// Remove every definition & constant, & abandon any definition begun.
+    pub fn clear_env(&mut self) {
+        Arc::make_mut(&mut self.env).clear();
+        Arc::make_mut(&mut self.state.memo).forget();
+        #[cfg(feature = "jit")]
+        self.state.jit.forget();
+        self.consts.clear();
+        self.effects.clear();
+        self.open = None;
+    }
+    /// Return to the state of a new machine, but keeping the edition, limits, channels, cancel
+    /// token, output, input, trace, debugger, & workers (with any tasks still running) this one was
+    /// configured with.
+    pub fn reset(&mut self) {
+        self.clear_env();
+        let state = std::mem::take(&mut self.state);
+        self.state = State {
+            edition: state.edition,
+            limits: state.limits,
+            channels: state.channels,
+            cancel: state.cancel,
+            output: state.output,
+            input: state.input,
+            trace: state.trace,
+            debugger: state.debugger,
+            #[cfg(feature = "concurrency")]
+            workers: state.workers,
+            memo: state.memo,
+            #[cfg(feature = "jit")]
+            jit: state.jit,
+            ..State::default()
+        };
+    }
+    /// Save the definitions, constants, stack, & values its handles refer to (as with `serde`) in
+    /// a compact binary format, versioned & checksummed, for `restore` to load.
+    #[must_use]
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut e = Encoder::new::<T>();
+        e.str(&self.state.edition.to_string());
+        e.len(self.env.len());
+        for (k, ws) in self.env.iter() {
+            e.str(k);
+            e.words(ws);
+        }
+        e.len(self.consts.len());
+        for (k, n) in &self.consts {
+            e.str(k);
+            e.cell(n);
+        }
+        e.len(self.effects.len());
+        for (k, effect) in &self.effects {
+            e.str(k);
+            e.str(effect);
+        }
+        e.len(
+            self.open
+                .as_ref()
+                .map_or(0, |&(.., redef)| 1 + usize::from(redef)),
+        );
+        if let Some((k, effect, ws, _)) = &self.open {
+            e.str(k);
+            e.bool(effect.is_some());
+            if let Some(effect) = effect {
+                e.str(effect);
+            }
+            e.words(ws);
+        }
+        let state = &self.state;
+        e.len(state.stack.len());
+        for n in &state.stack {
+            e.cell(n);
+        }
+        e.len(state.marks.len());
+        for &i in &state.marks {
+            e.len(i);
+        }
+        e.bool(state.last.is_some());
+        if let Some(n) = &state.last {
+            e.cell(n);
+        }
+        e.len(state.quotes.len());
+        for q in state.quotes.iter() {
+            e.words(q);
+        }
+        e.len(state.strings.len());
+        for s in state.strings.iter() {
+            e.str(s);
+        }
+        e.len(state.ratios.len());
+        for &(n, d) in state.ratios.iter() {
+            e.int(n.into());
+            e.int(d.into());
+        }
+        #[cfg(feature = "bignum")]
+        {
+            e.len(state.bigs.len());
+            for n in state.bigs.iter() {
+                e.bytes(&n.to_signed_bytes_le());
+            }
+        }
+        #[cfg(not(feature = "bignum"))]
+        e.len(0);
+        e.len(state.heap.count());
+        for (len, cells) in state.heap.iter() {
+            let cells = cells.collect::<Vec<_>>();
+            e.len(len);
+            e.len(cells.len());
+            for (i, n) in cells {
+                e.len(i);
+                e.cell(&n);
+            }
+        }
+        e.finish()
+    }
+    /// Load a snapshot, replacing whatever `snapshot` saves but keeping this machine's limits,
+    /// channels, & workers. On error, the machine's left as it was.
+    ///
+    /// # Errors
+    /// If the bytes aren't a snapshot (or are corrupted), are of an unsupported version or a
+    /// machine with another cell type, or the snapshot exceeds this machine's limits.
+    pub fn restore(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let mut d = Decoder::new::<T>(bytes)?;
+        let edition = d.str()?.parse().map_err(|_| Error::Snapshot)?;
+        let (mut env, mut symbols) = (Env::default(), Symbols::default());
+        for _ in 0..d.len()? {
+            let k = symbols.intern(d.str()?);
+            let us = d.words()?.into_iter().map(|w| bind(&mut symbols, w));
+            env.insert(k, Def::new(us.collect()));
+        }
+        let mut consts = IndexMap::new();
+        for _ in 0..d.len()? {
+            consts.insert(d.str()?.into(), d.cell()?);
+        }
+        let mut effects = IndexMap::new();
+        for _ in 0..d.len()? {
+            effects.insert(d.str()?.into(), d.str()?.into());
+        }
+        // 0 for no open definition, or 1 for a `def` & 2 for a `redef`.
+        let open = match d.index()? {
+            0 => None,
+            n @ 1..=2 => {
+                let k = d.str()?.into();
+                let effect = if d.bool()? {
+                    Some(d.str()?.into())
+                } else {
+                    None
+                };
+                Some((k, effect, d.words()?, n == 2))
+            }
+            _ => return Err(Error::Snapshot),
+        };
+        let stack = (0..d.len()?)
+            .map(|_| d.cell())
+            .collect::<Result<Vec<_>, _>>()?;
+        let marks = (0..d.len()?)
+            .map(|_| d.index())
+            .collect::<Result<Vec<_>, _>>()?;
+        if !marks.is_sorted() || marks.last().is_some_and(|&i| i > stack.len()) {
+            return Err(Error::Snapshot);
+        }
+        let last = if d.bool()? { Some(d.cell()?) } else { None };
+        let quotes = (0..d.len()?)
+            .map(|_| d.words().map(Arc::from))
+            .collect::<Result<IndexSet<_>, _>>()?;
+        let strings = (0..d.len()?)
+            .map(|_| d.str().map(Arc::from))
+            .collect::<Result<IndexSet<_>, _>>()?;
+        let ratios = (0..d.len()?)
+            .map(|_| Ok((d.i64()?, d.i64()?)))
+            .collect::<Result<IndexSet<_>, Error>>()?;
+        #[cfg(feature = "bignum")]
+        let bigs = (0..d.len()?)
+            .map(|_| d.bytes().map(BigInt::from_signed_bytes_le))
+            .collect::<Result<IndexSet<_>, _>>()?;
+        #[cfg(not(feature = "bignum"))]
+        if d.len()? > 0 {
+            return Err(Error::Snapshot);
+        }
+        let mut heap = Heap::default();
+        for _ in 0..d.len()? {
+            let h = heap.alloc(d.index()?);
+            for _ in 0..d.len()? {
+                let i = d.index()?;
+                if i >= heap.len(h) {
+                    return Err(Error::Snapshot);
+                }
+                heap.set(h, i, d.cell()?);
+            }
+        }
+        d.finish()?;
+        let limits = self.state.limits;
+        if let Some(n) = limits.stack_depth.filter(|&n| stack.len() > n) {
+            return Err(Error::StackOverflow(n));
+        }
+        if let Some(n) = limits.env_size.filter(|&n| env.len() + consts.len() > n) {
+            return Err(Error::EnvFull(n));
+        }
+        if let Some(n) = limits.heap_size.filter(|&n| heap.used() > n) {
+            return Err(Error::HeapFull(n));
+        }
+        self.env = Arc::new(env);
+        self.consts = consts;
+        self.effects = effects;
+        self.open = open;
+        let state = &mut self.state;
+        state.edition = edition;
+        state.stack = stack;
+        state.marks = marks;
+        state.last = last;
+        state.quotes = Arc::new(quotes);
+        state.strings = Arc::new(strings);
+        state.ratios = Arc::new(ratios);
+        #[cfg(feature = "bignum")]
+        {
+            state.bigs = Arc::new(bigs);
+        }
+        state.heap = Arc::new(heap);
+        state.symbols = symbols;
+        Arc::make_mut(&mut state.memo).forget();
+        #[cfg(feature = "jit")]
+        state.jit.forget();
+        Ok(())
+    }
+    /// The name of the definition begun but not yet ended by `;`, if any.
+    #[must_use]
+    pub fn defining(&self) -> Option<&str> {
+        self.open.as_ref().map(|(k, ..)| k.as_str())
+    }
+    /// Abandon the definition begun but not yet ended, returning its name.
+    pub(crate) fn abandon(&mut self) -> Option<LeanString> {
+        self.open.take().map(|(k, ..)| k)
+    }
+    /// The limits this machine enforces.
+    #[must_use]
+    pub const fn limits(&self) -> &Limits {
+        &self.state.limits
+    }
+    /// Change the limits this machine enforces.
+    pub const fn set_limits(&mut self, limits: Limits) {
+        self.state.limits = limits;
+    }
+    /// Which words with side effects this machine may execute.
+    #[must_use]
+    pub const fn capabilities(&self) -> Capabilities {
+        self.state.limits.capabilities
+    }
+    /// Change which words with side effects this machine may execute, e.g. to
+    /// `Capabilities::NONE` to evaluate untrusted input purely.
+    pub const fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.state.limits.capabilities = capabilities;
+    }
+    /// The edition this machine currently evaluates with.
+    #[must_use]
+    pub const fn edition(&self) -> Edition {
+        self.state.edition
+    }
+    /// Change the edition this machine evaluates with.
+    pub const fn set_edition(&mut self, edition: Edition) {
+        self.state.edition = edition;
+    }
+    /// The value most recently printed, which `last` (or `_`) pushes again.
+    #[must_use]
+    pub fn last(&self) -> Option<T> {
+        self.state.last.clone()
+    }
+    /// Resource usage accumulated over this machine's lifetime.
+    #[must_use]
+    pub const fn stats(&self) -> &Stats {
+        &self.state.stats
+    }
+    /// The channels this machine's `send` and `recv` words use.
+    #[must_use]
+    pub const fn channels(&self) -> &Channels<T> {
+        &self.state.channels
+    }
+    /// Connect this machine to (a handle to) some channels, e.g. those of another machine.
+    pub fn connect(&mut self, channels: &Channels<T>) {
+        self.state.channels = channels.clone();
+    }
+    /// Branch off a copy to try things out on, e.g. to see what some input would do without
+    /// committing to it.
+    ///
+    /// It's cheap: the copy shares this machine's definitions (& their bodies) until either
+    /// machine changes them. Unlike a clone, it gets channels of its own, holding the values
+    /// waiting on this machine's, so what it sends & receives leaves this machine's untouched.
+    #[must_use]
+    pub fn fork(&self) -> Self {
+        let mut m = self.clone();
+        m.state.channels = self.state.channels.fork();
+        m
+    }
+    /// What `def` & `var` do when their name's already defined.
+    #[must_use]
+    pub const fn redefinition(&self) -> &Redefinition {
+        &self.redefinition
+    }
+    /// Change what `def` & `var` do when their name's already defined.
+    pub fn set_redefinition(&mut self, redefinition: Redefinition) {
+        self.redefinition = redefinition;
+    }
+    /// The longest body a definition may have for `def` to splice it into its callers, or 0 if
+    /// it doesn't.
+    #[must_use]
+    pub const fn inline_threshold(&self) -> usize {
+        self.inline
+    }
+    /// Have `def` splice definitions with bodies at most `n` words long (that don't call
+    /// themselves or `exit`) into the definitions calling them, saving a call each time those
+    /// run; or, if `n` is 0, not.
+    ///
+    /// Words spliced in are bound early: redefining (or undefining) one later doesn't change
+    /// callers defined before, whose bodies no longer mention it.
+    pub const fn set_inline_threshold(&mut self, n: usize) {
+        self.inline = n;
+    }
+    /// Does `def` optimize the bodies of definitions?
+    #[must_use]
+    pub const fn optimize(&self) -> bool {
+        self.optimize
+    }
+    /// Have `def` optimize the bodies of definitions (& the quotations in them), or not: folding
+    /// constants, e.g. `2 3 add` into `5`, eliminating no-ops, e.g. `swap swap`, & fusing common
+    /// pairs of core words, e.g. `dup mul`, into superinstructions (`Word::Fused`).
+    ///
+    /// Constants are folded with the edition's arithmetic at the time, and eliminating a no-op
+    /// also eliminates the error it'd fail with on too small a stack.
+    pub const fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+    /// The most results of calls to pure definitions memoized at once, or 0 if none are.
+    #[must_use]
+    pub fn memo_capacity(&self) -> usize {
+        self.state.memo.capacity()
+    }
+    /// Memoize the results of calls to definitions that are pure—computing what they leave on the
+    /// stack from the values they take alone, with no side effects—so a call with the same values
+    /// on top of the stack as before just leaves the same results, e.g. in a naive Fibonacci.
+    /// Keep at most `n` results, forgetting them all when there'd be more, or none if `n` is 0.
+    ///
+    /// A definition's pure if it's made of pure core words & literal numbers, of `if` & `ifelse`
+    /// after literal quotations, & of calls to other pure definitions (or itself), & it always
+    /// takes & leaves as many values. A call answered from memory takes a single unit of fuel &
+    /// can't overflow the stack midway, and results are forgotten whenever a definition changes.
+    pub fn set_memo_capacity(&mut self, n: usize) {
+        Arc::make_mut(&mut self.state.memo).set_capacity(n);
+    }
+    /// How many results of calls to pure definitions are memoized.
+    #[must_use]
+    pub fn memo_len(&self) -> usize {
+        self.state.memo.len()
+    }
+    /// Forget the memoized results of calls to pure definitions.
+    pub fn clear_memo(&mut self) {
+        Arc::make_mut(&mut self.state.memo).clear();
+    }
+    /// How many calls make a definition hot enough to compile to native code, or 0 if none are
+    /// compiled.
+    #[cfg(feature = "jit")]
+    #[must_use]
+    pub const fn jit_threshold(&self) -> usize {
+        self.state.jit.threshold()
+    }
+    /// Compile definitions to native code once they've been called `n` times (64, unless this is
+    /// called), or none if `n` is 0.
+    ///
+    /// Only definitions made of literal numbers, core words on integers alone (arithmetic,
+    /// comparisons, logic, bitwise words, & stack shuffling), & calls to other such definitions
+    /// are compiled, & only on `i64` cells. A call runs compiled code if it'll finish as the body
+    /// would—within the limits & the fuel left, & without failing—& the machine isn't tracing,
+    /// debugging, or memoizing; otherwise the body's evaluated as usual. Either way, the stack,
+    /// stats, & fuel left end up the same. Compiled code is thrown away whenever a definition
+    /// changes.
+    #[cfg(feature = "jit")]
+    pub fn set_jit_threshold(&mut self, n: usize) {
+        self.state.jit.set_threshold(n);
+    }
+    /// The token with which this machine's evaluation may be cancelled.
+    #[must_use]
+    pub const fn cancel_token(&self) -> &CancelToken {
+        &self.state.cancel
+    }
+    /// Let (a handle to) this token cancel this machine's evaluation, e.g. one shared with other
+    /// machines.
+    pub fn set_cancel_token(&mut self, token: &CancelToken) {
+        self.state.cancel = token.clone();
+    }
+    /// Where this machine's printing words write.
+    #[must_use]
+    pub const fn output(&self) -> &Output {
+        &self.state.output
+    }
+    /// Make this machine's printing words write to `sink` rather than standard output, e.g. to
+    /// capture what a program prints.
+    pub fn set_output(&mut self, sink: impl Write + Send + 'static) {
+        self.state.output = Output::new(sink);
+    }
+    /// Make this machine's printing words write to (a handle to) this output, e.g. one shared
+    /// with other machines.
+    pub fn share_output(&mut self, output: &Output) {
+        self.state.output = output.clone();
+    }
+    /// Where this machine's `read` reads from.
+    #[must_use]
+    pub const fn input(&self) -> &Input {
+        &self.state.input
+    }
+    /// Make this machine's `read` read (whitespace-separated integers) from `source` rather than
+    /// standard input.
+    pub fn set_input(&mut self, source: impl Read + Send + 'static) {
+        self.state.input = Input::new(source);
+    }
+    /// Make this machine's `read` read these integers, in order, rather than standard input.
+    pub fn set_input_values(
+        &mut self,
+        xs: impl IntoIterator<Item = i64, IntoIter: Send + 'static>,
+    ) {
+        self.state.input = Input::values(xs);
+    }
+    /// Make this machine's `read` read from (a handle to) this input, e.g. one shared with other
+    /// machines.
+    pub fn share_input(&mut self, input: &Input) {
+        self.state.input = input.clone();
+    }
+    /// Where this machine traces each word it executes, if anywhere.
+    #[must_use]
+    pub const fn trace(&self) -> Option<&Output> {
+        self.state.trace.as_ref()
+    }
+    /// Write each word this machine executes, & the stack after it, on a line of (a handle to) this
+    /// output, indented by how many custom words' bodies it's within; or, given `None`, stop.
+    /// Workers it spawns aren't traced.
+    pub fn set_trace(&mut self, output: Option<&Output>) {
+        self.state.trace = output.cloned();
+    }
+    /// What pauses this machine's evaluation, if anything.
+    #[must_use]
+    pub const fn debugger(&self) -> Option<&Debugger<T>> {
+        self.state.debugger.as_ref()
+    }
+    /// Let this debugger pause this machine's evaluation; or, given `None`, stop. Workers it
+    /// spawns aren't paused.
+    pub fn set_debugger(&mut self, debugger: Option<Debugger<T>>) {
+        self.state.debugger = debugger;
+    }
+    /// Allow at most `max` tasks to be spawned (and not yet joined) at once.
+    #[cfg(feature = "concurrency")]
+    pub const fn set_max_workers(&mut self, max: usize) {
+        self.state.workers.set_max(max);
+    }
     /// Read a string & evaluate it.
     ///
+    /// `var <name>` defines `name` as a fresh variable: a word pushing a handle to a cell on the
+    /// heap, which `@` & `!` fetch from & store to.
+    ///
+    /// `const <name>` pops the top of the stack & binds `name` to it for good; from then on, the
+    /// name's replaced by the value wherever it's read (even in definitions).
+    ///
+    /// A `def <name> <body>` runs to a `;` (after which evaluation continues), or else the end of
+    /// the input. A `def <name>` with nothing after it begins a definition spanning later input:
+    /// each later string adds to its body, until a `;` ends it. An error abandons the definition.
+    ///
+    /// A `#edition <edition>` directive switches the machine's edition for the rest of the input
+    /// (and all later input).
+    ///
     /// # Errors
-    /// If something goes wrong in lexing or evaluation.
+    /// If something goes wrong in lexing or evaluation, or a limit is exceeded.
     pub fn read_eval(&mut self, s: &str) -> Result<(), Error> {
-        let mut ts = Token::lexer(s).collect::<Result<Vec<_>, _>>()?.into_iter();
-        while let Some(t) = ts.next() {
-            if t == Token::Def {
+        self.read_eval_tokens(s).map_err(|(e, _)| e)
+    }
+    /// Read a string & evaluate it, as with `read_eval`, but wrapping any error in `Error::Span`
+    /// to say where in the string it arose.
+    ///
+    /// # Errors
+    /// As with `read_eval`.
+    pub fn read_eval_spanned(&mut self, s: &str) -> Result<(), Error> {
+        self.read_eval_tokens(s)
+            .map_err(|(e, span)| Error::Span(span, Box::new(e)))
+    }
+    /// Read a string & evaluate it, as with `read_eval`, but executing at most `budget` words
+    /// (or the machine's own fuel limit, if that's less), e.g. to bound a possibly
+    /// non-terminating program.
+    ///
+    /// # Errors
+    /// As with `read_eval`, & `Error::BudgetExceeded` if the budget runs out.
+    pub fn read_eval_limited(&mut self, s: &str, budget: u64) -> Result<(), Error> {
+        let limits = self.state.limits;
+        self.state.limits.fuel = Some(limits.fuel.map_or(budget, |f| f.min(budget)));
+        let result = self.read_eval(s);
+        self.state.limits = limits;
+        result
+    }
+    /// Read a string & evaluate it, as with `read_eval`, also reporting the resource usage of just
+    /// this evaluation (which still counts towards `stats`).
+    pub fn read_eval_stats(&mut self, s: &str) -> (Result<(), Error>, EvalStats) {
+        let lifetime = std::mem::take(&mut self.state.stats);
+        let start = Instant::now();
+        let result = self.read_eval(s);
+        let duration = start.elapsed();
+        let stats = &mut self.state.stats;
+        let eval = EvalStats {
+            words_executed: stats.words_executed,
+            max_stack_depth: stats.max_stack_depth,
+            defs_added: stats.defs_added,
+            fuel_consumed: stats.fuel_consumed,
+            duration,
+        };
+        stats.words_executed += lifetime.words_executed;
+        stats.max_stack_depth = stats.max_stack_depth.max(lifetime.max_stack_depth);
+        stats.defs_added += lifetime.defs_added;
+        stats.fuel_consumed += lifetime.fuel_consumed;
+        (result, eval)
+    }
+    /// Read a string & evaluate it, as with `read_eval`, but all or nothing: if anything goes
+    /// wrong, the machine's rolled back to how it was before (save for side effects like printing
+    /// or sending, which can't be undone).
+    ///
+    /// # Errors
+    /// As with `read_eval`.
+    pub fn read_eval_atomic(&mut self, s: &str) -> Result<(), Error> {
+        let saved = self.clone();
+        let result = self.read_eval(s);
+        if result.is_err() {
+            self.env = saved.env;
+            self.consts = saved.consts;
+            self.effects = saved.effects;
+            self.open = saved.open;
+            let state = &mut self.state;
+            state.stack = saved.state.stack;
+            state.edition = saved.state.edition;
+            state.marks = saved.state.marks;
+            state.last = saved.state.last;
+            state.quotes = saved.state.quotes;
+            state.strings = saved.state.strings;
+            state.ratios = saved.state.ratios;
+            #[cfg(feature = "bignum")]
+            {
+                state.bigs = saved.state.bigs;
+            }
+            state.heap = saved.state.heap;
+            state.symbols = saved.state.symbols;
+            Arc::make_mut(&mut state.memo).forget();
+            #[cfg(feature = "jit")]
+            state.jit.forget();
+        }
+        result
+    }
+    /// Lex & evaluate a string, returning any error along with the span of input it arose in.
+    fn read_eval_tokens(&mut self, s: &str) -> Result<(), (Error, Range<usize>)> {
+        self.state.fuel = self.state.limits.fuel;
+        self.make_room();
+        let open = self.open.take();
+        let (mut tokens, mut spans) = (Vec::new(), Vec::new());
+        for (t, span) in Token::lexer(s).spanned() {
+            let t = t.map_err(|e| (e, span.clone()))?;
+            if !matches!(t, Token::Effect(_)) || documents(&tokens) {
+                tokens.push(t);
+                spans.push(span);
+            }
+        }
+        let count = tokens.len();
+        let (mut ts, mut left) = (tokens.into_iter(), count);
+        let r = self.statements(open, &mut ts, &mut left);
+        r.map_err(|e| {
+            // From the token being read when the error arose, to the last one read since.
+            let (first, read) = (count - left, count - ts.len());
+            let span = spans.get(first).map_or(s.len()..s.len(), |a| {
+                a.start..spans[read.max(first + 1) - 1].end
+            });
+            (e, span)
+        })
+    }
+    /// Evaluate tokens, continuing the `open` definition if there is one, & keeping `left` at the
+    /// number of tokens left when the statement (or word of a definition) being read began.
+    fn statements(
+        &mut self,
+        open: Option<Open<T>>,
+        ts: &mut vec::IntoIter<Token>,
+        left: &mut usize,
+    ) -> Result<(), Error> {
+        if let Some((k, effect, mut us, redef)) = open {
+            let begun = *left;
+            if !self.body(&mut us, ts, left)? {
+                self.open = Some((k, effect, us, redef));
+                return Ok(());
+            }
+            *left = begun;
+            self.add_def(k, effect, us, redef)?;
+        }
+        loop {
+            *left = ts.len();
+            let Some(t) = ts.next() else { break };
+            if t == Token::Def || t == Token::Redef {
+                let begun = *left;
                 let k = ts
                     .next()
                     .ok_or(Error::DefName)
-                    .and_then(Word::try_from)
+                    .and_then(Word::<T>::try_from)
                     .and_then(Word::into_name)?;
-                let us = ts.map(Word::try_from).collect::<Result<Vec<_>, _>>()?;
-                if us.is_empty() {
-                    return Err(Error::DefBody);
-                } else if us.iter().any(|u| u == &k) {
-                    return Err(Error::SelfRef(k.to_string()));
+                let redef = t == Token::Redef;
+                let effect = match ts.as_slice().first() {
+                    Some(&Token::Effect(e)) => ts.next().map(|_| e.into()),
+                    _ => None,
+                };
+                let mut us = Vec::new();
+                if !self.body(&mut us, ts, left)? && us.is_empty() {
+                    self.open = Some((k, effect, us, redef));
+                    break;
                 }
-                let _ = self.env.insert(k, us);
-                break; // no need for `else` here
+                *left = begun;
+                self.add_def(k, effect, us, redef)?;
+                continue;
+            } else if t == Token::Var {
+                let k = ts
+                    .next()
+                    .ok_or(Error::VarName)
+                    .and_then(Word::<T>::try_from)
+                    .and_then(Word::into_name)?;
+                self.declare(k)?;
+                continue;
+            } else if t == Token::Const {
+                let k = ts
+                    .next()
+                    .ok_or(Error::ConstName)
+                    .and_then(Word::<T>::try_from)
+                    .and_then(Word::into_name)?;
+                self.bind(k)?;
+                continue;
+            } else if t == Token::Edition {
+                let e = ts.next().ok_or(Error::EditionName)?.to_string();
+                self.state.edition = e.parse().map_err(|_| Error::UnknownEdition(e))?;
+                continue;
             }
-            self.eval(&Word::try_from(t)?)?;
+            let w = self.resolve(Word::parse(t, ts)?);
+            self.eval(&w)?;
         }
         Ok(())
     }
-    /// Look for a definition in the environment.
-    #[must_use]
-    pub fn lookup(&self, s: &str) -> Option<String> {
-        self.env.get(s).map(|d| {
-            d.iter()
-                .map(ToString::to_string)
-                .collect::<Vec<_>>()
-                .join(" ")
-        })
-    }
-    /// `check` the input, then run it through `eval_inner`.
-    fn eval(&mut self, word: &Word) -> Result<(), Error> {
-        check(&self.env, &self.stack, word)?;
-        eval_inner(&self.env, &mut self.stack, word)
+    /// Parse words onto a definition's body, up to a `;` (returning whether there was one), &
+    /// keeping `left` at the number of tokens left when the word being read began.
+    fn body(
+        &self,
+        us: &mut Vec<Word<T>>,
+        ts: &mut vec::IntoIter<Token>,
+        left: &mut usize,
+    ) -> Result<bool, Error> {
+        loop {
+            *left = ts.len();
+            match ts.next() {
+                None => return Ok(false),
+                Some(Token::End) => return Ok(true),
+                Some(t) => us.push(self.resolve(Word::parse(t, ts)?)),
+            }
+        }
     }
-}
-
-/// Broken out because `eval_inner` is separate, too, and requires this.
-fn check(env: &IndexMap<LeanString, Vec<Word>>, stack: &[i64], word: &Word) -> Result<(), Error> {
-    let s = stack.len();
-    let r = match word {
-        Word::Num(_) | Word::Custom(_) => 0,
-        Word::Core(c) => match c {
-            Core::Drop | Core::Dup | Core::Print => 1,
-            Core::Swap | Core::Add | Core::Sub | Core::Mul | Core::Div | Core::Mod => 2,
-            Core::Zero => 3,
-        },
-    };
-    if s < r {
-        Err(Error::Small(word.to_string(), r, s))
-    } else if matches!(word, Word::Core(Core::Div | Core::Mod)) && stack[s - 2] == 0 {
-        Err(Error::NotNonzero(word.to_string()))
-    } else if *word == Word::Core(Core::Mod) && matches!(stack[s - 2..s], [-1, i64::MIN]) {
-        Err(Error::ModEdge)
-    } else if matches!(word, Word::Custom(_)) && !env.contains_key(word.unsafe_custom_inner()) {
-        Err(Error::Unknown(word.to_string()))
-    } else {
-        Ok(())
+    /// Replace the names of constants in a word (or quotation) with their values, & those of
+    /// defined words with their symbols.
+    fn resolve(&self, word: Word<T>) -> Word<T> {
+        match word {
+            Word::Custom(c) => self.resolve(Word::Unbound(c.into())),
+            Word::Unbound(c) => self.consts.get(&c).map_or_else(
+                || {
+                    let k = self.state.symbols.get(&c);
+                    k.map_or(Word::Unbound(c), Word::Custom)
+                },
+                |v| Word::Num(v.clone()),
+            ),
+            Word::Quote(ws) => Word::Quote(ws.into_iter().map(|w| self.resolve(w)).collect()),
+            w => w,
+        }
     }
-}
-
-/// Broken out to untangle mutability concerns.
-/// Full of `stack.pop().expect(…)` because this should _only_ be called from within `Machine::eval`.
-fn eval_inner(
-    env: &IndexMap<LeanString, Vec<Word>>,
-    stack: &mut Vec<i64>,
-    word: &Word,
-) -> Result<(), Error> {
-    match word {
-        Word::Core(Core::Drop) => {
-            stack.pop().expect("Internal error @ drop");
+    /// Fail unless the environment has room for a new definition (or constant) named `k`.
+    fn env_room(&self, k: &LeanString) -> Result<(), Error> {
+        if self.consts.contains_key(k) {
+            return Err(Error::AlreadyDefined(k.to_string()));
         }
-        Word::Core(Core::Swap) => {
-            let x = stack.pop().expect("Internal error @ swap 1");
-            let y = stack.pop().expect("Internal error @ swap 2");
-            stack.push(x);
-            stack.push(y);
+        match self.state.limits.env_size {
+            Some(n) if self.env.len() + self.consts.len() >= n && !self.is_defined(k) => {
+                Err(Error::EnvFull(n))
+            }
+            _ => Ok(()),
         }
-        Word::Core(Core::Dup) => {
-            let x = stack.pop().expect("Internal error @ dup");
-            stack.push(x);
-            stack.push(x);
+    }
+    /// Add (or replace) a definition, documented by a stack effect or not, if it has a body &
+    /// the environment has room for it.
+    fn add_def(
+        &mut self,
+        k: LeanString,
+        effect: Option<LeanString>,
+        us: Vec<Word<T>>,
+        redef: bool,
+    ) -> Result<(), Error> {
+        if us.is_empty() {
+            return Err(Error::DefBody);
         }
-        Word::Core(Core::Add) => {
-            let x = stack.pop().expect("Internal error @ add 1");
-            let y = stack.pop().expect("Internal error @ add 2");
-            stack.push(x.saturating_add(y));
+        let us = if self.inline > 0 {
+            self.inline(&k, us)
+        } else {
+            us
+        };
+        let us = if self.optimize {
+            Some(self.optimized(&us))
+                .filter(|vs| !vs.is_empty())
+                .unwrap_or(us)
+        } else {
+            us
+        };
+        self.env_room(&k)?;
+        if let Some(n) = self.state.limits.memory
+            && self.memory_usage() + name_size(&k) + words_size(&us) > n
+        {
+            return Err(Error::MemoryFull(n));
         }
-        Word::Core(Core::Sub) => {
-            let x = stack.pop().expect("Internal error @ sub 1");
-            let y = stack.pop().expect("Internal error @ sub 2");
-            stack.push(x.saturating_sub(y));
+        if !redef {
+            self.may_redefine(&k)?;
         }
-        Word::Core(Core::Mul) => {
-            let x = stack.pop().expect("Internal error @ mul 1");
-            let y = stack.pop().expect("Internal error @ mul 2");
-            stack.push(x.saturating_mul(y));
+        let symbols = &mut self.state.symbols;
+        let us = us.into_iter().map(|w| bind(symbols, w)).collect();
+        let k_symbol = symbols.intern(&k);
+        let _ = Arc::make_mut(&mut self.env).insert(k_symbol, Def::new(us));
+        Arc::make_mut(&mut self.state.memo).forget();
+        #[cfg(feature = "jit")]
+        self.state.jit.forget();
+        match effect {
+            Some(e) => self.effects.insert(k, e),
+            None => self.effects.shift_remove(&k),
+        };
+        self.state.stats.defs_added += 1;
+        self.make_room();
+        Ok(())
+    }
+    /// Splice the bodies of small definitions that words (or the quotations among them) call into
+    /// them, save for `k`, the definition they're the body of.
+    fn inline(&self, k: &str, ws: Vec<Word<T>>) -> Vec<Word<T>> {
+        let mut us = Vec::with_capacity(ws.len());
+        for w in ws {
+            match w {
+                Word::Custom(ref c) if c != k => match self.env.get(c) {
+                    Some(vs) if vs.len() <= self.inline && !refers(vs, c) && !exits(vs) => {
+                        us.extend(vs.iter().cloned());
+                    }
+                    _ => us.push(w),
+                },
+                Word::Quote(vs) => us.push(Word::Quote(self.inline(k, vs))),
+                w => us.push(w),
+            }
         }
-        Word::Core(Core::Div) => {
-            let x = stack.pop().expect("Internal error @ div 1");
-            let y = stack.pop().expect("Internal error @ div 2");
-            stack.push(x.saturating_div(y));
+        us
+    }
+    /// Optimize words (& the quotations among them), rewriting them as they're added.
+    fn optimized(&self, ws: &[Word<T>]) -> Vec<Word<T>> {
+        let mut us = Vec::with_capacity(ws.len());
+        for w in ws {
+            us.push(match w {
+                Word::Quote(vs) => Word::Quote(self.optimized(vs)),
+                w => w.clone(),
+            });
+            while self.peephole(&mut us) {}
         }
-        Word::Core(Core::Mod) => {
-            let x = stack.pop().expect("Internal error @ mod 1");
-            let y = stack.pop().expect("Internal error @ mod 2");
-            stack.push(x.rem_euclid(y));
+        fuse(us)
+    }
+    /// Rewrite the words at the end of `us` if they're a no-op or a constant to fold, returning
+    /// whether they were.
+    fn peephole(&self, us: &mut Vec<Word<T>>) -> bool {
+        let n = us.len();
+        match us.as_slice() {
+            [.., Word::Core(Core::Swap), Word::Core(Core::Swap)]
+            | [
+                ..,
+                Word::Core(Core::Dup) | Word::Num(_),
+                Word::Core(Core::Drop),
+            ] => {
+                us.truncate(n - 2);
+                true
+            }
+            [.., Word::Core(c)] => {
+                let Some(k) = pure_arity(*c).filter(|&k| k < n) else {
+                    return false;
+                };
+                let xs: Option<Vec<T>> = us[n - 1 - k..n - 1]
+                    .iter()
+                    .map(|w| match w {
+                        Word::Num(x) => Some(x.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                let Some(y) = xs.and_then(|xs| self.fold(xs, *c)) else {
+                    return false;
+                };
+                us.truncate(n - 1 - k);
+                us.push(Word::Num(y));
+                true
+            }
+            _ => false,
         }
-        Word::Core(Core::Zero) => {
-            let x = stack.pop().expect("Internal error @ zero? 1");
-            let y = stack.pop().expect("Internal error @ zero? 2");
-            let z = stack.pop().expect("Internal error @ zero? 3");
-            stack.push(if x == 0 { y } else { z });
+    }
+    /// The value `c` leaves given the stack `xs`, if it succeeds with just one.
+    fn fold(&self, xs: Vec<T>, c: Core) -> Option<T> {
+        let mut state = State {
+            stack: xs,
+            edition: self.state.edition,
+            ..State::default()
+        };
+        check(&self.env, &state, &Word::Core(c)).ok()?;
+        eval_core(&mut state, c).ok()?;
+        match &state.stack[..] {
+            [y] => Some(y.clone()),
+            _ => None,
         }
-        Word::Core(Core::Print) => println!("{}", stack.pop().expect("Internal error @ print")),
-        Word::Num(n) => stack.push(*n),
-        Word::Custom(c) => {
-            for w in &env[c] {
-                check(env, stack, w)?;
-                eval_inner(env, stack, w)?;
+    }
+    /// Roughly how many bytes the environment (definitions, constants, & stack effects), stack, &
+    /// values handles refer to (quotations, strings, ratios, & bignums, but not arrays, which the
+    /// heap size limits) take up.
+    #[must_use]
+    pub fn memory_usage(&self) -> usize {
+        self.env_usage() + tables_size(&self.state) + self.state.stack.len() * size_of::<T>()
+    }
+    /// Roughly how many bytes the environment takes up.
+    fn env_usage(&self) -> usize {
+        let defs = self.env.iter().map(|(k, ws)| name_size(k) + words_size(ws));
+        let consts = self.consts.keys().map(|k| name_size(k) + size_of::<T>());
+        let effects = self
+            .effects
+            .iter()
+            .map(|(k, e)| name_size(k) + name_size(e));
+        defs.chain(consts).chain(effects).sum()
+    }
+    /// Work out how many values the stack may hold within the memory limit (if there is one),
+    /// given the current size of the environment & of the values handles refer to.
+    fn make_room(&mut self) {
+        self.state.room = self.state.limits.memory.map(|n| {
+            n.saturating_sub(self.env_usage() + tables_size(&self.state)) / size_of::<T>()
+        });
+    }
+    /// Define (or redefine) `name` as `body`, just as `def` would.
+    ///
+    /// # Errors
+    /// If `name` isn't a valid name, `body` is empty, or the environment has no room.
+    pub fn define(&mut self, name: &str, body: Vec<Word<T>>) -> Result<(), Error> {
+        let k = Word::<T>::name(name)?;
+        let body = body.into_iter().map(|w| self.resolve(w)).collect();
+        self.add_def(k, None, body, false)
+    }
+    /// Apply the redefinition policy to a `def` (or `var`) of `k`, once its body's been read.
+    fn may_redefine(&self, k: &str) -> Result<(), Error> {
+        if self.is_defined(k) {
+            match &self.redefinition {
+                Redefinition::Allow => {}
+                Redefinition::Warn(warn) => warn(k),
+                Redefinition::Strict => return Err(Error::Redefined(k.into())),
             }
         }
+        Ok(())
     }
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
+    /// Remove the definition of `name`—unless another definition refers to it, or `force` is
+    /// given, in which case those definitions will fail with an unknown word when run.
+    ///
+    /// # Errors
+    /// If `name` isn't defined, or (without `force`) another definition refers to it.
+    pub fn undefine(&mut self, name: &str, force: bool) -> Result<(), Error> {
+        let Some(s) = self.defined(name) else {
+            return Err(Error::Unknown(name.into()));
+        };
+        if !force
+            && let Some(k) = self
+                .env
+                .iter()
+                .find(|&(k, ws)| *k != s && refers(ws, &s))
+                .map(|(k, _)| k)
+        {
+            return Err(Error::Referenced(name.into(), k.to_string()));
+        }
+        let _ = Arc::make_mut(&mut self.env).shift_remove(&s);
+        Arc::make_mut(&mut self.state.memo).forget();
+        #[cfg(feature = "jit")]
+        self.state.jit.forget();
+        self.effects.shift_remove(name);
+        Ok(())
+    }
+    /// Bind `k` to the value popped off the stack, if it's not already defined.
+    fn bind(&mut self, k: LeanString) -> Result<(), Error> {
+        self.env_room(&k)?;
+        if self.is_defined(&k) {
+            return Err(Error::AlreadyDefined(k.to_string()));
+        }
+        let v = self
+            .state
+            .stack
+            .pop()
+            .ok_or_else(|| Error::Small("const".into(), 1, 0))?;
+        self.consts.insert(k, v);
+        self.state.stats.defs_added += 1;
+        Ok(())
+    }
+    /// Define `k` as a fresh variable, if there's room for it.
+    fn declare(&mut self, k: LeanString) -> Result<(), Error> {
+        heap_room(&self.state, 1)?;
+        let h = i64::try_from(self.state.heap.count()).expect("Internal error @ var");
+        self.add_def(k, None, vec![Word::Num(T::from_i64(h))], false)?;
+        Arc::make_mut(&mut self.state.heap).alloc(1);
+        Ok(())
+    }
+    /// Render this machine as source which, evaluated by a fresh machine, rebuilds its edition,
+    /// definitions, quotations, and stack.
+    #[must_use]
+    pub fn to_source(&self) -> String {
+        let mut lines = vec![format!("#edition {}", self.state.edition)];
+        lines.extend(
+            self.env
+                .keys()
+                .map(|k| format!("def {k} {}", self.source(k))),
+        );
+        lines.extend(self.consts.iter().map(|(k, v)| format!("{v} const {k}")));
+        lines.extend(
+            self.state
+                .quotes
+                .iter()
+                .map(|q| format!("{} drop", Word::Quote(q.to_vec()))),
+        );
+        lines.extend(
+            self.state
+                .strings
+                .iter()
+                .map(|s| format!("{} drop", Word::<T>::Str(s.as_ref().into()))),
+        );
+        lines.extend(
+            self.state
+                .ratios
+                .iter()
+                .map(|&(n, d)| format!("{} drop", Word::<T>::Ratio(n, d))),
+        );
+        #[cfg(feature = "bignum")]
+        lines.extend(self.state.bigs.iter().map(|n| format!("{n}n drop")));
+        for (h, (len, cells)) in self.state.heap.iter().enumerate() {
+            lines.push(format!("{len} array drop"));
+            lines.extend(cells.map(|(i, v)| format!("{v} {i} {h} set")));
+        }
+        if !self.state.stack.is_empty() {
+            lines.push(unwords(&self.state.stack));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+    /// Look for a definition in the environment, led by its stack-effect comment if it has one.
+    #[must_use]
+    pub fn lookup(&self, s: &str) -> Option<String> {
+        self.defined(s)
+            .map(|k| self.source(&k))
+            .or_else(|| self.consts.get(s).map(ToString::to_string))
+    }
+    /// The source of a (known) definition following its name: its stack effect (if any) & body.
+    fn source(&self, k: &Symbol) -> String {
+        let effect = self.effects.get(k.as_str()).map(|e| format!("{e} "));
+        format!("{}{}", effect.unwrap_or_default(), unwords(&self.env[k]))
+    }
+    /// The names of the definitions in the environment, in the order they were first defined.
+    pub fn words(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.env.keys().map(Symbol::as_str)
+    }
+    /// The names of the constants bound by `const`, in the order they were bound.
+    pub fn constants(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.consts.keys().map(LeanString::as_str)
+    }
+    /// The body of a definition, if there is one.
+    #[must_use]
+    pub fn definition(&self, s: &str) -> Option<&[Word<T>]> {
+        self.defined(s).map(|k| &*self.env[&k])
+    }
+    /// Is there a definition of `k`?
+    fn is_defined(&self, k: &str) -> bool {
+        self.defined(k).is_some()
+    }
+    /// The symbol `k`'s interned as, if it's defined.
+    pub(crate) fn defined(&self, k: &str) -> Option<Symbol> {
+        self.state
+            .symbols
+            .get(k)
+            .filter(|k| self.env.contains_key(k))
+    }
+    /// `check` the input, then run it through `eval_inner`.
+    fn eval(&mut self, word: &Word<T>) -> Result<(), Error> {
+        check(&self.env, &self.state, word)?;
+        eval_inner(&self.env, &mut self.state, word)
+    }
+    /// Evaluate an already-parsed word, checked & limited just as if it had been read.
+    ///
+    /// # Errors
+    /// If evaluation goes wrong, or a limit is exceeded.
+    pub fn eval_word(&mut self, word: &Word<T>) -> Result<(), Error> {
+        self.eval_words(std::slice::from_ref(word))
+    }
+    /// Evaluate already-parsed words in turn, checked & limited just as if they'd been read
+    /// together.
+    ///
+    /// # Errors
+    /// If evaluation goes wrong, or a limit is exceeded.
+    pub fn eval_words(&mut self, words: &[Word<T>]) -> Result<(), Error> {
+        self.state.fuel = self.state.limits.fuel;
+        self.make_room();
+        words
+            .iter()
+            .try_for_each(|w| self.eval(&self.resolve(w.clone())))
+    }
+}
+
+/// Roughly how many bytes a name (or other string) takes up.
+const fn name_size(k: &str) -> usize {
+    size_of::<LeanString>() + k.len()
+}
+
+/// A word (or quotation) with the names of words undefined when it was read interned, as the
+/// body of a definition is, so it can call them once they're defined.
+fn bind<T>(symbols: &mut Symbols, w: Word<T>) -> Word<T> {
+    match w {
+        Word::Unbound(c) => Word::Custom(symbols.intern(&c)),
+        Word::Quote(ws) => Word::Quote(ws.into_iter().map(|w| bind(symbols, w)).collect()),
+        w => w,
+    }
+}
+
+/// Roughly how many bytes words (& the quotations among them) take up.
+fn words_size<T>(ws: &[Word<T>]) -> usize {
+    ws.iter()
+        .map(|w| {
+            size_of::<Word<T>>()
+                + match w {
+                    Word::Str(s) | Word::Unbound(s) | Word::Send(s) | Word::Recv(s) => s.len(),
+                    #[cfg(feature = "bignum")]
+                    Word::Big(n) => usize::try_from(n.bits() / 8).unwrap_or(usize::MAX),
+                    Word::Quote(ws) => words_size(ws),
+                    _ => 0,
+                }
+        })
+        .sum()
+}
+
+/// Roughly how many bytes the values handles refer to (& the names symbols stand for) take up,
+/// as interned.
+fn tables_size<T: Cell>(state: &State<T>) -> usize {
+    let names = state.symbols.names().map(name_size).sum::<usize>();
+    let quotes = state.quotes.iter().map(|ws| quote_size(ws)).sum::<usize>();
+    let strings = state.strings.iter().map(|s| string_size(s)).sum::<usize>();
+    let ratios = state.ratios.len() * size_of::<Ratio>();
+    #[cfg(feature = "bignum")]
+    let bigs = state.bigs.iter().map(big_size).sum::<usize>();
+    #[cfg(not(feature = "bignum"))]
+    let bigs = 0;
+    names + quotes + strings + ratios + bigs
+}
+
+/// Roughly how many bytes an interned quotation takes up.
+fn quote_size<T>(ws: &[Word<T>]) -> usize {
+    size_of::<Arc<[Word<T>]>>() + words_size(ws)
+}
+
+/// Roughly how many bytes an interned string takes up.
+const fn string_size(s: &str) -> usize {
+    size_of::<Arc<str>>() + s.len()
+}
+
+/// Roughly how many bytes an interned bignum takes up.
+#[cfg(feature = "bignum")]
+fn big_size(n: &BigInt) -> usize {
+    size_of::<BigInt>() + usize::try_from(n.bits() / 8).unwrap_or(usize::MAX)
+}
+
+/// How many values a core word that only computes a value from them takes, or `None` if it does
+/// anything else (or has another arity in some editions).
+pub const fn pure_arity(c: Core) -> Option<usize> {
+    match c {
+        Core::Neg
+        | Core::Pos
+        | Core::Not
+        | Core::Abs
+        | Core::Negate
+        | Core::Isqrt
+        | Core::Log2
+        | Core::Popcount
+        | Core::BNot => Some(1),
+        Core::Add
+        | Core::Sub
+        | Core::Mul
+        | Core::Div
+        | Core::Mod
+        | Core::And
+        | Core::Or
+        | Core::Xor
+        | Core::Min
+        | Core::Max
+        | Core::Pow
+        | Core::Gcd
+        | Core::Eq
+        | Core::Neq
+        | Core::Lt
+        | Core::Gt
+        | Core::Le
+        | Core::Ge
+        | Core::BAnd
+        | Core::BOr
+        | Core::BXor
+        | Core::Shl
+        | Core::Shr => Some(2),
+        _ => None,
+    }
+}
+
+/// Replace common pairs of core words with superinstructions.
+fn fuse<T>(ws: Vec<Word<T>>) -> Vec<Word<T>> {
+    let mut us: Vec<Word<T>> = Vec::with_capacity(ws.len());
+    for w in ws {
+        if let (Some(Word::Core(a)), Word::Core(b)) = (us.last(), &w)
+            && let Some(f) = Fused::of(*a, *b)
+        {
+            us.pop();
+            us.push(Word::Fused(f));
+        } else {
+            us.push(w);
+        }
+    }
+    us
+}
+
+/// Do words (or the quotations among them) `exit`?
+fn exits<T>(ws: &[Word<T>]) -> bool {
+    ws.iter().any(|w| match w {
+        Word::Core(Core::Exit) => true,
+        Word::Quote(ws) => exits(ws),
+        _ => false,
+    })
+}
+
+/// Do words (or the quotations among them) refer to the custom word `k`?
+fn refers<T>(ws: &[Word<T>], k: &Symbol) -> bool {
+    ws.iter().any(|w| match w {
+        Word::Custom(c) => c == k,
+        #[cfg(feature = "concurrency")]
+        Word::Spawn(c) => k == c.as_str(),
+        Word::Quote(ws) => refers(ws, k),
+        _ => false,
+    })
+}
+
+/// Broken out because `eval_inner` is separate, too, and requires this.
+fn check<T: Cell>(env: &Arc<Env<T>>, state: &State<T>, word: &Word<T>) -> Result<(), Error> {
+    let stack = &state.stack;
+    let s = stack.len();
+    let r = match word {
+        #[cfg(feature = "concurrency")]
+        Word::Spawn(_) => stack
+            .last()
+            .and_then(handle)
+            .map_or(1, |k| k.saturating_add(1)),
+        Word::Core(Core::Pick | Core::Roll) => stack
+            .last()
+            .and_then(handle)
+            .map_or(1, |k| k.saturating_add(2)),
+        _ => arity(state.edition, word),
+    };
+    if s < r {
+        Err(Error::Small(word.to_string(), r, s))
+    } else if matches!(word, Word::Core(Core::Div | Core::Mod)) && stack[s - 2] == T::ZERO
+        || *word == Word::Core(Core::Log2) && stack[s - 1] == T::ZERO
+    {
+        Err(Error::NotNonzero(word.to_string()))
+    } else if *word == Word::Core(Core::Mod)
+        && stack[s - 1].checked_rem_euclid(&stack[s - 2]).is_none()
+    {
+        Err(Error::ModEdge)
+    } else if *word == Word::Core(Core::Times) && stack[s - 2] < T::ZERO
+        || matches!(
+            word,
+            Word::Core(
+                Core::Pow
+                    | Core::Pick
+                    | Core::Roll
+                    | Core::Isqrt
+                    | Core::Log2
+                    | Core::Iota
+                    | Core::PrintWidth
+                    | Core::PrintZeroes
+            )
+        ) && stack[s - 1] < T::ZERO
+    {
+        Err(Error::Negative(word.to_string()))
+    } else if matches!(word, Word::Core(Core::PrintWidth | Core::PrintZeroes))
+        && handle(&stack[s - 1]).is_none_or(|w| w > MAX_WIDTH)
+    {
+        Err(Error::TooWide(word.to_string(), MAX_WIDTH))
+    } else if generated(stack, word) > MAX_GENERATED {
+        Err(Error::TooMany(word.to_string(), MAX_GENERATED))
+    } else if let Some(n) = state.limits.stack_depth
+        && s - r + generated(stack, word) > n
+    {
+        Err(Error::StackOverflow(n))
+    } else if let Some(n) = state.room
+        && s - r + generated(stack, word) > n
+    {
+        Err(Error::MemoryFull(state.limits.memory.unwrap_or_default()))
+    } else if *word == Word::Core(Core::ClearToMarker) && state.marks.is_empty() {
+        Err(Error::NoMarker)
+    } else if *word == Word::Core(Core::Last) && state.last.is_none() {
+        Err(Error::NoLast)
+    } else if *word == Word::Core(Core::Emit) && code_point(&stack[s - 1]).is_none() {
+        Err(Error::NotChar(stack[s - 1].as_i64()))
+    } else if let Some(q) = unknown(stack, quotes_taken(word), state.quotes.len()) {
+        Err(Error::UnknownQuote(q))
+    } else if let Some(t) = unknown(stack, strings_taken(word), state.strings.len()) {
+        Err(Error::UnknownString(t))
+    } else if let Some(r) = unknown(stack, ratios_taken(word), state.ratios.len()) {
+        Err(Error::UnknownRatio(r))
+    } else if let Some(a) = unknown(stack, arrays_taken(word), state.heap.count()) {
+        Err(Error::UnknownArray(a))
+    } else if !state.limits.capabilities.allow(word) {
+        Err(Error::CapabilityDenied(word.to_string()))
+    } else if state.edition.checked_arithmetic() && overflows(stack, word) {
+        Err(Error::Overflow(word.to_string()))
+    } else if matches!(word, Word::Custom(_) | Word::Unbound(_))
+        && !word
+            .symbol(&state.symbols)
+            .is_some_and(|k| env.contains_key(&k))
+    {
+        Err(Error::Unknown(word.to_string()))
+    } else if let Word::Recv(c) = word
+        && state.channels.is_empty(c)
+    {
+        Err(Error::ChannelEmpty(c.to_string()))
+    } else {
+        check_ratios(state, word)?;
+        check_arrays(state, word)?;
+        #[cfg(feature = "concurrency")]
+        check_workers(env, state, word)?;
+        #[cfg(feature = "bignum")]
+        check_bigs(state, word)?;
+        Ok(())
+    }
+}
+
+/// The most values `iota` or `range` may push at once, even if the stack's size is unlimited.
+const MAX_GENERATED: usize = 1 << 32;
+
+/// How many values this word pushes (at least, saturating), if it's `iota` or `range`, given a
+/// stack known to be large enough.
+fn generated<T: Cell>(stack: &[T], word: &Word<T>) -> usize {
+    let s = stack.len();
+    match word {
+        Word::Core(Core::Iota) => handle(&stack[s - 1]).unwrap_or_default(),
+        Word::Core(Core::Range) if stack[s - 1] > stack[s - 2] => stack[s - 1]
+            .checked_sub(&stack[s - 2])
+            .as_ref()
+            .and_then(handle)
+            .unwrap_or(usize::MAX),
+        _ => 0,
+    }
+}
+
+/// The parts of `check` specific to `spawn` and `join`, given a stack known to be large enough.
+#[cfg(feature = "concurrency")]
+fn check_workers<T: Cell>(
+    env: &Arc<Env<T>>,
+    state: &State<T>,
+    word: &Word<T>,
+) -> Result<(), Error> {
+    let top = state.stack.last().cloned().unwrap_or_default();
+    match word {
+        Word::Spawn(_) if top < T::ZERO => Err(Error::Negative(word.to_string())),
+        Word::Spawn(c)
+            if !word
+                .symbol(&state.symbols)
+                .is_some_and(|k| env.contains_key(&k)) =>
+        {
+            Err(Error::Unknown(c.to_string()))
+        }
+        Word::Spawn(_) if state.workers.is_full() => Err(Error::WorkerLimit(state.workers.max())),
+        Word::Join if !top.to_i64().is_some_and(|id| state.workers.contains(id)) => {
+            Err(Error::UnknownTask(top.as_i64()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The first of the top `taken` items on the stack which isn't a handle into a table of `len`
+/// entries, if any.
+fn unknown<T: Cell>(stack: &[T], taken: usize, len: usize) -> Option<i64> {
+    stack[stack.len() - taken..]
+        .iter()
+        .find(|&h| handle(h).is_none_or(|i| i >= len))
+        .map(Cell::as_i64)
+}
+
+/// The index a cell stands for, as a handle (or count), if it's a valid one.
+fn handle<T: Cell>(h: &T) -> Option<usize> {
+    h.to_i64().and_then(|h| usize::try_from(h).ok())
+}
+
+/// How many quotation handles does this word take from the top of the stack?
+const fn quotes_taken<T: Cell>(word: &Word<T>) -> usize {
+    match word {
+        Word::Core(Core::Call | Core::If | Core::Times | Core::Catch) => 1,
+        Word::Core(Core::IfElse | Core::While) => 2,
+        _ => 0,
+    }
+}
+
+/// How many items this word needs on the stack (for `spawn`, besides those it hands over; for
+/// `pick` & `roll`, besides those below the depth they're given).
+// It's a flat table, with a line per core word.
+#[allow(clippy::too_many_lines)]
+const fn arity<T: Cell>(edition: Edition, word: &Word<T>) -> usize {
+    match word {
+        Word::Num(_)
+        | Word::Float(_)
+        | Word::Ratio(..)
+        | Word::Str(_)
+        | Word::Custom(_)
+        | Word::Unbound(_)
+        | Word::Recv(_)
+        | Word::Quote(_) => 0,
+        #[cfg(feature = "bignum")]
+        Word::Big(_) => 0,
+        Word::Send(_) => 1,
+        Word::Fused(f) => f.arity(),
+        #[cfg(feature = "concurrency")]
+        Word::Join | Word::Spawn(_) => 1,
+        Word::Core(c) => match c {
+            Core::Mark
+            | Core::ClearToMarker
+            | Core::Last
+            | Core::Read
+            | Core::Exit
+            | Core::True
+            | Core::False
+            | Core::Depth
+            | Core::Clear
+            | Core::ShowStack
+            | Core::Sum
+            | Core::Product
+            | Core::Reverse => 0,
+            Core::Zero if edition.zero_predicate() => 1,
+            Core::Drop
+            | Core::Dup
+            | Core::Print
+            | Core::Peek
+            | Core::PrintBare
+            | Core::HexPrint
+            | Core::BinPrint
+            | Core::Neg
+            | Core::Pos
+            | Core::Call
+            | Core::Throw
+            | Core::Catch
+            | Core::FToI
+            | Core::IToF
+            | Core::FPrint
+            | Core::Length
+            | Core::SPrint
+            | Core::Not
+            | Core::BNot
+            | Core::Abs
+            | Core::Negate
+            | Core::Isqrt
+            | Core::Log2
+            | Core::Popcount
+            | Core::Pick
+            | Core::Roll
+            | Core::Iota
+            | Core::Num
+            | Core::Den
+            | Core::Floor
+            | Core::IToR
+            | Core::RPrint
+            | Core::Emit
+            | Core::Array
+            | Core::Len
+            | Core::Fetch => 1,
+            #[cfg(feature = "bignum")]
+            Core::Big | Core::BToI | Core::BPrint => 1,
+            #[cfg(feature = "bignum")]
+            Core::BAdd | Core::BSub | Core::BMul | Core::BDiv | Core::BMod => 2,
+            Core::Swap
+            | Core::TwoDrop
+            | Core::TwoDup
+            | Core::PrintWidth
+            | Core::PrintZeroes
+            | Core::Over
+            | Core::Nip
+            | Core::Tuck
+            | Core::Range
+            | Core::Add
+            | Core::Sub
+            | Core::Mul
+            | Core::Div
+            | Core::Mod
+            | Core::If
+            | Core::Times
+            | Core::While
+            | Core::FAdd
+            | Core::FSub
+            | Core::FMul
+            | Core::FDiv
+            | Core::Concat
+            | Core::And
+            | Core::Or
+            | Core::Xor
+            | Core::Eq
+            | Core::Neq
+            | Core::Lt
+            | Core::Gt
+            | Core::Le
+            | Core::Ge
+            | Core::Min
+            | Core::Max
+            | Core::Pow
+            | Core::Gcd
+            | Core::BAnd
+            | Core::BOr
+            | Core::BXor
+            | Core::Shl
+            | Core::Shr
+            | Core::RAdd
+            | Core::RSub
+            | Core::RMul
+            | Core::RDiv
+            | Core::Get
+            | Core::Store => 2,
+            Core::Zero | Core::Select | Core::IfElse | Core::Set | Core::Rot => 3,
+            Core::TwoSwap | Core::TwoOver => 4,
+        },
+    }
+}
+
+/// The parts of `check` specific to bignums, given a stack known to be large enough.
+#[cfg(feature = "bignum")]
+fn check_bigs<T: Cell>(state: &State<T>, word: &Word<T>) -> Result<(), Error> {
+    let (stack, s) = (&state.stack, state.stack.len());
+    let taken = match word {
+        Word::Core(Core::BToI | Core::BPrint) => 1,
+        Word::Core(Core::BAdd | Core::BSub | Core::BMul | Core::BDiv | Core::BMod) => 2,
+        _ => 0,
+    };
+    if let Some(h) = unknown(stack, taken, state.bigs.len()) {
+        return Err(Error::UnknownBig(h));
+    }
+    let big = |i: usize| &state.bigs[handle(&stack[s - i]).unwrap_or_default()];
+    match word {
+        Word::Core(Core::BDiv | Core::BMod) if *big(2) == BigInt::ZERO => {
+            Err(Error::NotNonzero(word.to_string()))
+        }
+        Word::Core(Core::BToI) if T::from_big(big(1)).is_none() => {
+            Err(Error::Overflow(word.to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The parts of `check` specific to ratios (& ratio literals), given a stack of ratio handles
+/// known to be valid.
+fn check_ratios<T: Cell>(state: &State<T>, word: &Word<T>) -> Result<(), Error> {
+    let (stack, s) = (&state.stack, state.stack.len());
+    match word {
+        Word::Ratio(_, 0) => Err(Error::NotNonzero(word.to_string())),
+        Word::Ratio(n, d) if ratio::normalize(i128::from(*n), i128::from(*d)).is_none() => {
+            Err(Error::Overflow(word.to_string()))
+        }
+        Word::Core(Core::IToR) if stack[s - 1].to_i64().is_none() => {
+            Err(Error::Overflow(word.to_string()))
+        }
+        Word::Core(c @ (Core::RAdd | Core::RSub | Core::RMul | Core::RDiv)) => {
+            let (x, y) = (ratio(state, &stack[s - 1]), ratio(state, &stack[s - 2]));
+            if *c == Core::RDiv && y.0 == 0 {
+                Err(Error::NotNonzero(word.to_string()))
+            } else if ratio::op(*c, x, y).is_none() {
+                Err(Error::Overflow(word.to_string()))
+            } else {
+                Ok(())
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The parts of `check` specific to arrays, given a stack of array handles known to be valid.
+fn check_arrays<T: Cell>(state: &State<T>, word: &Word<T>) -> Result<(), Error> {
+    let (stack, s) = (&state.stack, state.stack.len());
+    match word {
+        Word::Core(Core::Array) => handle(&stack[s - 1])
+            .ok_or_else(|| Error::Negative(word.to_string()))
+            .and_then(|n| heap_room(state, n)),
+        Word::Core(c @ (Core::Get | Core::Set | Core::Fetch | Core::Store)) => {
+            let len = state.heap.len(handle(&stack[s - 1]).unwrap_or_default());
+            let i = if matches!(c, Core::Get | Core::Set) {
+                stack[s - 2].clone()
+            } else {
+                T::ZERO
+            };
+            if handle(&i).is_some_and(|i| i < len) {
+                Ok(())
+            } else {
+                Err(Error::OutOfBounds(i.as_i64(), len))
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Fail unless the heap has room for `n` more array elements.
+fn heap_room<T: Cell>(state: &State<T>, n: usize) -> Result<(), Error> {
+    match state.limits.heap_size {
+        Some(max) if n > max.saturating_sub(state.heap.used()) => Err(Error::HeapFull(max)),
+        _ => Ok(()),
+    }
+}
+
+/// How many array handles does this word take from the top of the stack?
+const fn arrays_taken<T: Cell>(word: &Word<T>) -> usize {
+    match word {
+        Word::Core(Core::Get | Core::Set | Core::Len | Core::Fetch | Core::Store) => 1,
+        _ => 0,
+    }
+}
+
+/// How many ratio handles does this word take from the top of the stack?
+const fn ratios_taken<T: Cell>(word: &Word<T>) -> usize {
+    match word {
+        Word::Core(Core::Num | Core::Den | Core::Floor | Core::RPrint) => 1,
+        Word::Core(Core::RAdd | Core::RSub | Core::RMul | Core::RDiv) => 2,
+        _ => 0,
+    }
+}
+
+/// How many string handles does this word take from the top of the stack?
+const fn strings_taken<T: Cell>(word: &Word<T>) -> usize {
+    match word {
+        Word::Core(Core::Length | Core::SPrint) => 1,
+        Word::Core(Core::Concat) => 2,
+        _ => 0,
+    }
+}
+
+/// Would this arithmetic word overflow, given a stack already known to be large enough?
+fn overflows<T: Cell>(stack: &[T], word: &Word<T>) -> bool {
+    let s = stack.len();
+    let op = match word {
+        Word::Core(c @ (Core::Abs | Core::Negate | Core::Pow | Core::Gcd)) => {
+            return math(stack, *c).is_none();
+        }
+        Word::Core(c @ (Core::Sum | Core::Product)) => return fold(stack, *c).is_none(),
+        Word::Core(Core::Add) => T::checked_add,
+        Word::Core(Core::Sub) => T::checked_sub,
+        Word::Core(Core::Mul) => T::checked_mul,
+        Word::Core(Core::Div) => T::checked_div,
+        Word::Fused(Fused::DupAdd) => return stack[s - 1].checked_add(&stack[s - 1]).is_none(),
+        Word::Fused(Fused::DupMul) => return stack[s - 1].checked_mul(&stack[s - 1]).is_none(),
+        _ => return false,
+    };
+    op(&stack[s - 1], &stack[s - 2]).is_none()
+}
+
+/// The whole stack folded by `sum` or `product`, if the result fits.
+fn fold<T: Cell>(stack: &[T], c: Core) -> Option<T> {
+    let op = if c == Core::Sum {
+        T::checked_add
+    } else {
+        T::checked_mul
+    };
+    let unit = T::from_i64(i64::from(c == Core::Product));
+    stack.iter().rev().try_fold(unit, |acc, y| op(&acc, y))
+}
+
+/// Evaluate a word that's passed `check`, along with any words it runs in turn.
+///
+/// Rather than recursing, nested bodies (of custom words & quotations) are kept on an explicit
+/// stack of frames; a body's last word replaces its frame, so tail calls run in constant space.
+fn eval_inner<T: Cell>(
+    env: &Arc<Env<T>>,
+    state: &mut State<T>,
+    word: &Word<T>,
+) -> Result<(), Error> {
+    let mut frames = Vec::new();
+    let mut r = step(env, state, word, &mut frames);
+    loop {
+        if let Err(e) = r {
+            recover(state, &mut frames, e)?;
+        }
+        if frames.is_empty() {
+            return Ok(());
+        }
+        r = advance(env, state, &mut frames);
+    }
+}
+
+/// Unwind to the innermost `catch`, restoring the stack it saved & pushing the error's code; if
+/// there's no `catch`, or the error can't be caught, give up with it.
+fn recover<T: Cell>(
+    state: &mut State<T>,
+    frames: &mut Vec<Frame<T>>,
+    e: Error,
+) -> Result<(), Error> {
+    if e.catchable() {
+        while let Some(frame) = frames.pop() {
+            if let Frame::Catch(stack, marks) = frame {
+                state.stack = stack;
+                state.marks = marks;
+                state.stack.push(T::from_i64(e.code()));
+                return Ok(());
+            }
+        }
+    }
+    Err(e)
+}
+
+/// Evaluate the next bit of the innermost frame.
+fn advance<'a, T: Cell>(
+    env: &'a Arc<Env<T>>,
+    state: &mut State<T>,
+    frames: &mut Vec<Frame<'a, T>>,
+) -> Result<(), Error> {
+    if let Some(frame) = frames.last_mut() {
+        match frame {
+            Frame::Words(body, next) => {
+                let i = *next;
+                *next += 1;
+                let body = body.clone();
+                if i + 1 >= body.len() {
+                    if let Body::Def(..) = body {
+                        *frame = Frame::Return;
+                    } else {
+                        frames.pop();
+                    }
+                }
+                if let Some(w) = body.get(i) {
+                    if !matches!(body, Body::Def(_, n) if i < n) {
+                        check(env, state, w)?;
+                    }
+                    step(env, state, w, frames)?;
+                }
+            }
+            Frame::Times(_, 0) | Frame::Return => {
+                frames.pop();
+            }
+            Frame::Memo(..) => {
+                if let Some(Frame::Memo(c, xs, o)) = frames.pop() {
+                    let s = state.stack.len();
+                    Arc::make_mut(&mut state.memo).record(c, xs, &state.stack[s - o..]);
+                }
+            }
+            Frame::Times(q, n) => {
+                *n -= 1;
+                burn(state)?;
+                let q = Body::Quote(Arc::clone(q));
+                push(state, frames, Frame::Words(q, 0))?;
+            }
+            Frame::While(cond, _, tested @ false) => {
+                *tested = true;
+                burn(state)?;
+                let cond = Body::Quote(Arc::clone(cond));
+                push(state, frames, Frame::Words(cond, 0))?;
+            }
+            Frame::While(_, body, tested) => match state.stack.pop() {
+                None => return Err(Error::Small(Core::While.to_string(), 1, 0)),
+                Some(x) if x == T::ZERO => {
+                    frames.pop();
+                }
+                Some(_) => {
+                    *tested = false;
+                    let body = Body::Quote(Arc::clone(body));
+                    push(state, frames, Frame::Words(body, 0))?;
+                }
+            },
+            Frame::Catch(..) => {
+                frames.pop();
+                state.stack.push(T::ZERO);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A body of words being evaluated.
+#[derive(Clone)]
+enum Body<'a, T> {
+    /// A definition's, & how many of its first words needn't be checked before they're evaluated.
+    Def(&'a [Word<T>], usize),
+    Quote(Arc<[Word<T>]>),
+}
+
+impl<T> std::ops::Deref for Body<'_, T> {
+    type Target = [Word<T>];
+    fn deref(&self) -> &[Word<T>] {
+        match self {
+            Self::Def(ws, _) => ws,
+            Self::Quote(ws) => ws,
+        }
+    }
+}
+
+/// Evaluation still to be done, innermost last.
+enum Frame<'a, T> {
+    /// A body, and the index of the next word in it to evaluate.
+    Words(Body<'a, T>, usize),
+    /// A quotation to evaluate this many more times.
+    Times(Arc<[Word<T>]>, i64),
+    /// A condition & a body quotation, and whether the condition's just been evaluated.
+    While(Arc<[Word<T>]>, Arc<[Word<T>]>, bool),
+    /// The stack & marks to restore should the words above this fail.
+    Catch(Vec<T>, Vec<usize>),
+    /// A custom word's finished body, left for `exit` to return from until whatever replaced it
+    /// by a tail call finishes.
+    Return,
+    /// A call to a pure definition, the values it took, & how many it leaves, to memoize once
+    /// the frames above this finish.
+    Memo(Symbol, Box<[T]>, usize),
+}
+
+/// Use up a unit of the evaluation budget (counted, even if there's no limit): for each word, &
+/// each loop iteration (so even loops with empty bodies run out). This is also when
+/// cancellation's noticed.
+fn burn<T: Cell>(state: &mut State<T>) -> Result<(), Error> {
+    if state.cancel.take() {
+        return Err(Error::Cancelled);
+    }
+    match state.fuel {
+        Some(0) => return Err(Error::BudgetExceeded(state.limits.fuel.unwrap_or_default())),
+        Some(ref mut f) => *f -= 1,
+        None => {}
+    }
+    state.stats.fuel_consumed += 1;
+    Ok(())
+}
+
+/// Push a frame, one level more deeply nested.
+fn push<'a, T: Cell>(
+    state: &State<T>,
+    frames: &mut Vec<Frame<'a, T>>,
+    frame: Frame<'a, T>,
+) -> Result<(), Error> {
+    match state.limits.recursion_depth {
+        Some(n) if frames.len() >= n => Err(Error::RecursionLimit(n)),
+        _ => {
+            frames.push(frame);
+            Ok(())
+        }
+    }
+}
+
+/// Evaluate a single word that's passed `check`, pushing frames for any words it runs in turn.
+/// Full of `stack.pop().expect(…)` because `check` has already ensured the stack is large enough.
+fn step<'a, T: Cell>(
+    env: &'a Arc<Env<T>>,
+    state: &mut State<T>,
+    word: &Word<T>,
+    frames: &mut Vec<Frame<'a, T>>,
+) -> Result<(), Error> {
+    pause(env, state, word, frames)?;
+    burn(state)?;
+    state.stats.words_executed += 1;
+    let depth = state.trace.is_some().then(|| nesting(frames));
+    let stack = &mut state.stack;
+    match word {
+        Word::Core(c @ (Core::Call | Core::If | Core::IfElse | Core::Times | Core::While)) => {
+            if let Some(frame) = control(state, *c) {
+                push(state, frames, frame)?;
+            }
+        }
+        Word::Core(Core::Throw) => {
+            let x = stack.pop().expect("Internal error @ throw");
+            return Err(Error::Thrown(x.as_i64()));
+        }
+        Word::Core(Core::Exit) => {
+            while let Some(frame) = frames.pop() {
+                if matches!(frame, Frame::Words(Body::Def(..), _) | Frame::Return) {
+                    break;
+                }
+            }
+        }
+        Word::Core(Core::Catch) => {
+            let q = Body::Quote(quote(state, "catch"));
+            let saved = Frame::Catch(state.stack.clone(), state.marks.clone());
+            push(state, frames, saved)?;
+            push(state, frames, Frame::Words(q, 0))?;
+        }
+        Word::Core(c) => eval_core(state, *c)?,
+        Word::Num(n) => stack.push(n.clone()),
+        Word::Float(x) => stack.push(cell(*x)),
+        Word::Str(s) => {
+            let t = intern(state, s)?;
+            state.stack.push(t);
+        }
+        Word::Ratio(n, d) => {
+            let r = ratio::normalize(i128::from(*n), i128::from(*d))
+                .ok_or_else(|| Error::Overflow(word.to_string()))?;
+            let h = intern_ratio(state, r)?;
+            state.stack.push(h);
+        }
+        #[cfg(feature = "bignum")]
+        Word::Big(n) => {
+            let h = intern_big(state, n.clone())?;
+            state.stack.push(h);
+        }
+        Word::Send(c) => state
+            .channels
+            .send(c, stack.pop().expect("Internal error @ send")),
+        Word::Recv(c) => stack.push(
+            state
+                .channels
+                .recv(c)
+                .ok_or_else(|| Error::ChannelEmpty(c.to_string()))?,
+        ),
+        #[cfg(feature = "concurrency")]
+        Word::Spawn(_) => spawn(
+            env,
+            state,
+            word.symbol(&state.symbols).expect("Internal error @ spawn"),
+        ),
+        #[cfg(feature = "concurrency")]
+        Word::Join => {
+            let id = stack.pop().expect("Internal error @ join");
+            let vs = state.workers.join(id.as_i64())?;
+            state.stack.extend(vs);
+        }
+        Word::Quote(ws) => {
+            let q = intern_quote(state, ws)?;
+            state.stack.push(q);
+        }
+        Word::Fused(f) => {
+            let s = stack.len();
+            match f {
+                Fused::DupAdd => stack[s - 1] = stack[s - 1].saturating_add(&stack[s - 1]),
+                Fused::DupMul => stack[s - 1] = stack[s - 1].saturating_mul(&stack[s - 1]),
+                Fused::SwapDrop => drop(stack.swap_remove(s - 2)),
+            }
+        }
+        Word::Custom(_) | Word::Unbound(_) => {
+            if matches!(frames.last(), Some(Frame::Return)) {
+                frames.pop();
+            }
+            call(env, state, frames, word)?;
+        }
+    }
+    if let Some(depth) = depth {
+        trace(state, word, depth)?;
+    }
+    let s = state.stack.len();
+    state.stats.max_stack_depth = state.stats.max_stack_depth.max(s);
+    while state.marks.last().is_some_and(|&m| m > s) {
+        state.marks.pop();
+    }
+    match (state.limits.stack_depth, state.room) {
+        (Some(n), _) if s > n => Err(Error::StackOverflow(n)),
+        (_, Some(n)) if s > n => Err(Error::MemoryFull(state.limits.memory.unwrap_or_default())),
+        _ => Ok(()),
+    }
+}
+
+/// How many custom words' bodies are being evaluated.
+fn nesting<T>(frames: &[Frame<T>]) -> usize {
+    frames
+        .iter()
+        .filter(|f| matches!(f, Frame::Words(Body::Def(..), _) | Frame::Return))
+        .count()
+}
+
+/// Pause before a word, if the debugger says to, failing if it's then told to give up.
+fn pause<T: Cell>(
+    env: &Env<T>,
+    state: &mut State<T>,
+    word: &Word<T>,
+    frames: &[Frame<T>],
+) -> Result<(), Error> {
+    let Some(debugger) = &mut state.debugger else {
+        return Ok(());
+    };
+    let w = word.to_string();
+    if !debugger.pauses_at(&w) {
+        return Ok(());
+    }
+    let p = Pause {
+        word: w,
+        depth: nesting(frames),
+        stack: state.stack.clone(),
+        definitions: env
+            .iter()
+            .map(|(k, d)| (k.to_string(), unwords(d)))
+            .collect(),
+    };
+    match debugger.pause(p) {
+        Resume::Abort => Err(Error::Cancelled),
+        Resume::Step | Resume::Continue => Ok(()),
+    }
+}
+
+/// Write a word just executed, & the stack after it (top first, as `Display` shows it), to the
+/// trace, indented by `depth`.
+fn trace<T: Cell>(state: &State<T>, word: &Word<T>, depth: usize) -> Result<(), Error> {
+    let mut stack = String::new();
+    for x in state.stack.iter().rev() {
+        stack.push(' ');
+        stack.push_str(&x.to_string());
+    }
+    let indent = 2 * depth;
+    state.trace.as_ref().map_or(Ok(()), |t| {
+        t.print(format_args!("{:indent$}{word}  [{stack} ]\n", ""))
+    })
+}
+
+/// Call a custom word (`check`ed to be defined): run its native code, if it's been compiled & will
+/// finish, push what it left before, if it's memoized & was called with the same values, or begin
+/// evaluating its body.
+fn call<'a, T: Cell>(
+    env: &'a Arc<Env<T>>,
+    state: &mut State<T>,
+    frames: &mut Vec<Frame<'a, T>>,
+    word: &Word<T>,
+) -> Result<(), Error> {
+    let c = word.symbol(&state.symbols).expect("Internal error @ call");
+    #[cfg(feature = "jit")]
+    if compiled(env, state, frames.len(), &c) {
+        return Ok(());
+    }
+    if let Some((i, o)) = Arc::make_mut(&mut state.memo).effect(env, &c)
+        && let Some(from) = state.stack.len().checked_sub(i)
+    {
+        if let Some(vs) =
+            Arc::make_mut(&mut state.memo).recall(state.edition, &c, &state.stack[from..])
+        {
+            state.stack.truncate(from);
+            state.stack.extend_from_slice(vs);
+            return Ok(());
+        }
+        let memo = Frame::Memo(c.clone(), state.stack[from..].into(), o);
+        push(state, frames, memo)?;
+    }
+    let def = &env[&c];
+    let n = def.unchecked(state.edition, state.stack.len());
+    push(state, frames, Frame::Words(Body::Def(def, n), 0))
+}
+
+/// Run a custom word's native code, if it's been compiled & will finish as its body would given
+/// how many frames are nested, accounting for it as if the body had been evaluated. Return
+/// whether it ran.
+#[cfg(feature = "jit")]
+fn compiled<T: Cell>(env: &Env<T>, state: &mut State<T>, nested: usize, c: &Symbol) -> bool {
+    if state.trace.is_some() || state.debugger.is_some() || state.memo.capacity() > 0 {
+        return false;
+    }
+    let Some(f) = state.jit.call(env, state.edition, c) else {
+        return false;
+    };
+    let s = state.stack.len();
+    let within = |limit: Option<usize>, n: usize| limit.is_none_or(|limit| n <= limit);
+    if s < f.inputs
+        || !within(state.limits.stack_depth, s + f.rise)
+        || !within(state.room, s + f.rise)
+        || !within(state.limits.recursion_depth, nested + f.depth)
+        || state.fuel.is_some_and(|fuel| fuel < f.words)
+    {
+        return false;
+    }
+    let from = s - f.inputs;
+    let mut io = state.stack[from..]
+        .iter()
+        .map(Cell::as_i64)
+        .collect::<Vec<_>>();
+    io.resize(f.inputs.max(f.outputs), 0);
+    if !f.run(&mut io, state.cancel.flag()) {
+        return false;
+    }
+    state.stack.truncate(from);
+    state
+        .stack
+        .extend(io[..f.outputs].iter().map(|&x| T::from_i64(x)));
+    if let Some(fuel) = &mut state.fuel {
+        *fuel -= f.words;
+    }
+    state.stats.fuel_consumed += f.words;
+    state.stats.words_executed += f.words;
+    state.stats.max_stack_depth = state.stats.max_stack_depth.max(s + f.rise);
+    while state.marks.last().is_some_and(|&m| m > s - f.fall) {
+        state.marks.pop();
+    }
+    true
+}
+
+/// The core words.
+/// Full of `stack.pop().expect(…)` because `check` has already ensured the stack is large enough.
+fn eval_core<T: Cell>(state: &mut State<T>, c: Core) -> Result<(), Error> {
+    let stack = &mut state.stack;
+    match c {
+        Core::Drop => {
+            stack.pop().expect("Internal error @ drop");
+        }
+        Core::Swap => {
+            let x = stack.pop().expect("Internal error @ swap 1");
+            let y = stack.pop().expect("Internal error @ swap 2");
+            stack.push(x);
+            stack.push(y);
+        }
+        Core::Dup => {
+            let x = stack.last().expect("Internal error @ dup").clone();
+            stack.push(x);
+        }
+        Core::Add | Core::Sub | Core::Mul | Core::Div => {
+            let x = stack.pop().expect("Internal error @ arithmetic 1");
+            let y = stack.pop().expect("Internal error @ arithmetic 2");
+            stack.push(match c {
+                Core::Add => x.saturating_add(&y),
+                Core::Sub => x.saturating_sub(&y),
+                Core::Mul => x.saturating_mul(&y),
+                _ => x.saturating_div(&y),
+            });
+        }
+        Core::Mod => {
+            let x = stack.pop().expect("Internal error @ mod 1");
+            let y = stack.pop().expect("Internal error @ mod 2");
+            stack.push(x.rem_euclid(&y));
+        }
+        Core::Zero if state.edition.zero_predicate() => {
+            let x = stack.pop().expect("Internal error @ zero?");
+            stack.push(truth(x == T::ZERO));
+        }
+        Core::Neg | Core::Pos => {
+            let x = stack.pop().expect("Internal error @ sign test");
+            let (neg, pos) = (x < T::ZERO, x > T::ZERO);
+            stack.push(truth(if c == Core::Neg { neg } else { pos }));
+        }
+        Core::Zero | Core::Select => {
+            let x = stack.pop().expect("Internal error @ select 1");
+            let y = stack.pop().expect("Internal error @ select 2");
+            let z = stack.pop().expect("Internal error @ select 3");
+            stack.push(if x == T::ZERO { y } else { z });
+        }
+        Core::Last => stack.push(state.last.clone().expect("Internal error @ last")),
+        Core::Read => stack.push(T::from_i64(state.input.read()?)),
+        Core::Mark => state.marks.push(stack.len()),
+        Core::ClearToMarker => {
+            let m = state.marks.pop().expect("Internal error @ clear-to-marker");
+            stack.truncate(m);
+        }
+        Core::Call
+        | Core::If
+        | Core::IfElse
+        | Core::Times
+        | Core::While
+        | Core::Throw
+        | Core::Catch
+        | Core::Exit => {
+            unreachable!("`{c}` needs the environment, so `eval_inner` handles it")
+        }
+        _ => return eval_family(state, c),
+    }
+    Ok(())
+}
+
+/// The rest of the core words, each family of which has its own function.
+fn eval_family<T: Cell>(state: &mut State<T>, c: Core) -> Result<(), Error> {
+    match c {
+        Core::FAdd
+        | Core::FSub
+        | Core::FMul
+        | Core::FDiv
+        | Core::FToI
+        | Core::IToF
+        | Core::FPrint => eval_float(state, c)?,
+        Core::Print
+        | Core::Peek
+        | Core::PrintBare
+        | Core::PrintWidth
+        | Core::PrintZeroes
+        | Core::HexPrint
+        | Core::BinPrint
+        | Core::Emit => eval_print(state, c)?,
+        Core::Concat | Core::Length | Core::SPrint => eval_string(state, c)?,
+        Core::RAdd
+        | Core::RSub
+        | Core::RMul
+        | Core::RDiv
+        | Core::Num
+        | Core::Den
+        | Core::Floor
+        | Core::IToR
+        | Core::RPrint => eval_ratio(state, c)?,
+        #[cfg(feature = "bignum")]
+        Core::Big
+        | Core::BAdd
+        | Core::BSub
+        | Core::BMul
+        | Core::BDiv
+        | Core::BMod
+        | Core::BToI
+        | Core::BPrint => eval_big(state, c)?,
+        Core::Array | Core::Get | Core::Set | Core::Len | Core::Fetch | Core::Store => {
+            eval_array(state, c);
+        }
+        Core::True | Core::False | Core::Not | Core::And | Core::Or | Core::Xor => {
+            eval_logic(&mut state.stack, c);
+        }
+        Core::Eq | Core::Neq | Core::Lt | Core::Gt | Core::Le | Core::Ge => {
+            eval_compare(&mut state.stack, c);
+        }
+        Core::BAnd | Core::BOr | Core::BXor | Core::BNot | Core::Shl | Core::Shr => {
+            eval_bits(&mut state.stack, c);
+        }
+        Core::Abs
+        | Core::Negate
+        | Core::Min
+        | Core::Max
+        | Core::Pow
+        | Core::Gcd
+        | Core::Isqrt
+        | Core::Log2
+        | Core::Popcount => eval_math(&mut state.stack, c),
+        Core::Over
+        | Core::Rot
+        | Core::Nip
+        | Core::Tuck
+        | Core::Pick
+        | Core::Roll
+        | Core::TwoDrop
+        | Core::TwoSwap
+        | Core::TwoDup
+        | Core::TwoOver => eval_shuffle(&mut state.stack, c),
+        Core::Depth => {
+            let d = i64::try_from(state.stack.len()).unwrap_or(i64::MAX);
+            state.stack.push(T::from_i64(d));
+        }
+        Core::Clear => {
+            state.stack.clear();
+            state.marks.clear();
+        }
+        Core::ShowStack => {
+            let (n, xs) = (state.stack.len(), unwords(&state.stack));
+            state.output.print(format_args!("<{n}> {xs}\n"))?;
+        }
+        Core::Sum | Core::Product | Core::Reverse => eval_whole(&mut state.stack, c),
+        Core::Iota | Core::Range => {
+            let n = generated(&state.stack, &Word::Core(c));
+            state.stack.pop().expect("Internal error @ generate");
+            let lo = if c == Core::Range {
+                state.stack.pop().expect("Internal error @ range")
+            } else {
+                T::ZERO
+            };
+            let one = T::from_i64(1);
+            let xs = std::iter::successors(Some(lo), |x| Some(x.saturating_add(&one)));
+            state.stack.extend(xs.take(n));
+        }
+        _ => unreachable!("`eval_core` handles `{c}`"),
+    }
+    Ok(())
+}
+
+/// The core words printing integers (or characters), each leaving what it printed as `last`.
+/// Full of `stack.pop().expect(…)` because `check` has already ensured the stack is large enough.
+fn eval_print<T: Cell>(state: &mut State<T>, c: Core) -> Result<(), Error> {
+    let (stack, out) = (&mut state.stack, &state.output);
+    let x = if c == Core::Peek {
+        stack.last().cloned()
+    } else {
+        stack.pop()
+    }
+    .expect("Internal error @ print");
+    match c {
+        Core::Print | Core::Peek => out.print(format_args!("{x}\n"))?,
+        Core::PrintBare => out.print(format_args!("{x}"))?,
+        Core::HexPrint => out.print(format_args!("{}\n", Wide::<16>(x.as_i128())))?,
+        Core::BinPrint => out.print(format_args!("{}\n", Wide::<2>(x.as_i128())))?,
+        Core::PrintWidth | Core::PrintZeroes => {
+            let n = stack.pop().expect("Internal error @ print-w");
+            let pad = if c == Core::PrintWidth {
+                Pad::Space
+            } else {
+                Pad::Zero
+            };
+            let width = handle(&x).expect("Internal error @ print-w width");
+            out.print(format_args!(
+                "{}",
+                Padded {
+                    n: n.clone(),
+                    width,
+                    pad
+                }
+            ))?;
+            state.last = Some(n);
+            return Ok(());
+        }
+        Core::Emit => {
+            let ch = code_point(&x).expect("Internal error @ emit");
+            out.print(format_args!("{ch}"))?;
+        }
+        _ => unreachable!("`{c}` isn't a printing word"),
+    }
+    state.last = Some(x);
+    Ok(())
+}
+
+/// The core words on floats, which are kept on the stack as their bit patterns.
+/// Full of `stack.pop().expect(…)` because `check` has already ensured the stack is large enough.
+fn eval_float<T: Cell>(state: &mut State<T>, c: Core) -> Result<(), Error> {
+    let stack = &mut state.stack;
+    match c {
+        Core::FAdd | Core::FSub | Core::FMul | Core::FDiv => {
+            let x = float(&stack.pop().expect("Internal error @ float op 1"));
+            let y = float(&stack.pop().expect("Internal error @ float op 2"));
+            stack.push(cell(match c {
+                Core::FAdd => x + y,
+                Core::FSub => x - y,
+                Core::FMul => x * y,
+                _ => x / y,
+            }));
+        }
+        Core::FToI => {
+            let x = float(&stack.pop().expect("Internal error @ f>i"));
+            stack.push(T::from_f64(x));
+        }
+        Core::IToF => {
+            let x = stack.pop().expect("Internal error @ i>f");
+            stack.push(cell(x.to_f64()));
+        }
+        Core::FPrint => {
+            let x = stack.pop().expect("Internal error @ fprint");
+            state.output.print(format_args!("{:?}\n", float(&x)))?;
+            state.last = Some(x);
+        }
+        _ => unreachable!("`{c}` isn't a float word"),
+    }
+    Ok(())
+}
+
+/// The logical core words, taking 0 as false & anything else as true.
+/// Full of `stack.pop().expect(…)` because `check` has already ensured the stack is large enough.
+fn eval_logic<T: Cell>(stack: &mut Vec<T>, c: Core) {
+    let mut pop = || stack.pop().expect("Internal error @ logic") != T::ZERO;
+    let b = match c {
+        Core::True => true,
+        Core::False => false,
+        Core::Not => !pop(),
+        Core::And => pop() & pop(),
+        Core::Or => pop() | pop(),
+        _ => pop() != pop(),
+    };
+    stack.push(truth(b));
+}
+
+/// The core words comparing the first two elements of the stack.
+fn eval_compare<T: Cell>(stack: &mut Vec<T>, c: Core) {
+    let x = stack.pop().expect("Internal error @ comparison 1");
+    let y = stack.pop().expect("Internal error @ comparison 2");
+    stack.push(truth(match c {
+        Core::Eq => x == y,
+        Core::Neq => x != y,
+        Core::Lt => x < y,
+        Core::Gt => x > y,
+        Core::Le => x <= y,
+        _ => x >= y,
+    }));
+}
+
+/// The words consuming (or rearranging) the whole stack, saturating where need be.
+fn eval_whole<T: Cell>(stack: &mut Vec<T>, c: Core) {
+    let (unit, op): (T, fn(&T, &T) -> T) = match c {
+        Core::Reverse => return stack.reverse(),
+        Core::Sum => (T::ZERO, T::saturating_add),
+        _ => (T::from_i64(1), T::saturating_mul),
+    };
+    let r = stack.drain(..).rev().fold(unit, |acc, y| op(&acc, &y));
+    stack.push(r);
+}
+
+/// The stack shuffling words beyond `drop`, `swap`, & `dup`.
+fn eval_shuffle<T: Cell>(stack: &mut Vec<T>, c: Core) {
+    let s = stack.len();
+    match c {
+        Core::Over => stack.push(stack[s - 2].clone()),
+        Core::Rot => stack[s - 3..].rotate_left(1),
+        Core::Nip => drop(stack.remove(s - 2)),
+        Core::Tuck => stack.insert(s - 2, stack[s - 1].clone()),
+        Core::TwoDrop => stack.truncate(s - 2),
+        Core::TwoSwap => stack[s - 4..].rotate_left(2),
+        Core::TwoDup => stack.extend_from_within(s - 2..),
+        Core::TwoOver => stack.extend_from_within(s - 4..s - 2),
+        _ => {
+            let n = stack.pop().expect("Internal error @ shuffle");
+            let i = s - 2 - handle(&n).expect("Internal error @ shuffle depth");
+            if c == Core::Pick {
+                stack.push(stack[i].clone());
+            } else {
+                stack[i..].rotate_left(1);
+            }
+        }
+    }
+}
+
+/// The extended arithmetic words, which saturate where the result doesn't fit.
+fn eval_math<T: Cell>(stack: &mut Vec<T>, c: Core) {
+    let r = math(stack, c).unwrap_or_else(|| {
+        let x = stack.last().expect("Internal error @ math");
+        match c {
+            Core::Pow => stack[stack.len() - 2].saturating_pow(exponent(x)),
+            Core::Gcd => T::ZERO.saturating_sub(x.min(&stack[stack.len() - 2])),
+            _ => T::ZERO.saturating_sub(x),
+        }
+    });
+    let taken = if matches!(
+        c,
+        Core::Abs | Core::Negate | Core::Isqrt | Core::Log2 | Core::Popcount
+    ) {
+        1
+    } else {
+        2
+    };
+    stack.truncate(stack.len() - taken);
+    stack.push(r);
+}
+
+/// The result of an extended arithmetic word on the stack, if it fits.
+fn math<T: Cell>(stack: &[T], c: Core) -> Option<T> {
+    let s = stack.len();
+    let x = &stack[s - 1];
+    let negate = |x: &T| T::ZERO.checked_sub(x);
+    match c {
+        Core::Abs if *x < T::ZERO => negate(x),
+        Core::Abs => Some(x.clone()),
+        Core::Negate => negate(x),
+        Core::Min => Some(x.min(&stack[s - 2]).clone()),
+        Core::Max => Some(x.max(&stack[s - 2]).clone()),
+        Core::Pow => stack[s - 2].checked_pow(exponent(x)),
+        Core::Isqrt => Some(x.isqrt()),
+        Core::Log2 => Some(T::from_i64(x.ilog2().into())),
+        Core::Popcount => Some(T::from_i64(x.count_ones().into())),
+        _ => {
+            let (mut gcd, mut rem) = (x.clone(), stack[s - 2].clone());
+            while rem != T::ZERO {
+                // Only `MIN` by `-1` overflows, & its remainder is 0.
+                let next = gcd.checked_rem_euclid(&rem).unwrap_or(T::ZERO);
+                (gcd, rem) = (rem, next);
+            }
+            if gcd < T::ZERO {
+                negate(&gcd)
+            } else {
+                Some(gcd)
+            }
+        }
+    }
+}
+
+/// A (nonnegative) exponent as a `u32`, clamped to the nearest one with the same parity if need
+/// be, which gives the same result as the exponent itself (or saturates anyway).
+fn exponent<T: Cell>(e: &T) -> u32 {
+    let even = e.clone() & T::from_i64(1) == T::ZERO;
+    e.to_i64()
+        .and_then(|e| u32::try_from(e).ok())
+        .unwrap_or_else(|| u32::MAX - u32::from(even))
+}
+
+/// The bitwise core words.
+fn eval_bits<T: Cell>(stack: &mut Vec<T>, c: Core) {
+    let x = stack.pop().expect("Internal error @ bitwise op");
+    if c == Core::BNot {
+        stack.push(!x);
+        return;
+    }
+    let y = stack.pop().expect("Internal error @ bitwise op 2");
+    // Masking the shift amount's low bits keeps shifts in range, even for negative amounts.
+    let shift = u32::try_from(x.as_i64() & i64::from(T::BITS - 1)).unwrap_or_default();
+    stack.push(match c {
+        Core::BAnd => x & y,
+        Core::BOr => x | y,
+        Core::BXor => x ^ y,
+        Core::Shl => y.wrapping_shl(shift),
+        _ => y.wrapping_shr(shift),
+    });
+}
+
+/// The core words on strings, which are kept on the stack as handles.
+/// Full of `stack.pop().expect(…)` because `check` has already ensured the stack is large enough.
+fn eval_string<T: Cell>(state: &mut State<T>, c: Core) -> Result<(), Error> {
+    let x = state.stack.pop().expect("Internal error @ string op");
+    let s = string(state, &x);
+    match c {
+        Core::Concat => {
+            let y = state.stack.pop().expect("Internal error @ concat");
+            let y = string(state, &y);
+            room_after(state, string_size(&y) + s.len())?;
+            let joined = intern(state, &format!("{y}{s}"))?;
+            state.stack.push(joined);
+        }
+        Core::Length => state.stack.push(T::from_i64(
+            s.chars().count().try_into().unwrap_or(i64::MAX),
+        )),
+        Core::SPrint => {
+            state.output.print(format_args!("{s}\n"))?;
+            state.last = Some(x);
+        }
+        _ => unreachable!("`{c}` isn't a string word"),
+    }
+    Ok(())
+}
+
+/// The core words on ratios, which are kept on the stack as handles.
+/// Full of `stack.pop().expect(…)` because `check` has already ensured the stack is large enough.
+fn eval_ratio<T: Cell>(state: &mut State<T>, c: Core) -> Result<(), Error> {
+    let x = state.stack.pop().expect("Internal error @ ratio op");
+    let r = match c {
+        Core::IToR => (x.to_i64().expect("Internal error @ i>r"), 1),
+        Core::Num | Core::Den | Core::Floor => {
+            let (n, d) = ratio(state, &x);
+            state.stack.push(T::from_i64(match c {
+                Core::Num => n,
+                Core::Den => d,
+                _ => n.div_euclid(d),
+            }));
+            return Ok(());
+        }
+        Core::RPrint => {
+            let (n, d) = ratio(state, &x);
+            state.output.print(format_args!("{n}/{d}\n"))?;
+            state.last = Some(x);
+            return Ok(());
+        }
+        _ => {
+            let y = state.stack.pop().expect("Internal error @ ratio op 2");
+            let r = ratio::op(c, ratio(state, &x), ratio(state, &y));
+            r.expect("Internal error @ ratio op overflow")
+        }
+    };
+    let h = intern_ratio(state, r)?;
+    state.stack.push(h);
+    Ok(())
+}
+
+/// The core words on arrays, which are kept on the stack as handles.
+fn eval_array<T: Cell>(state: &mut State<T>, c: Core) {
+    let x = state.stack.pop().expect("Internal error @ array op");
+    let x = handle(&x).expect("Internal error @ array handle");
+    let r = match c {
+        Core::Array => {
+            let h = Arc::make_mut(&mut state.heap).alloc(x);
+            T::from_i64(i64::try_from(h).expect("Internal error @ array"))
+        }
+        Core::Len => T::from_i64(i64::try_from(state.heap.len(x)).unwrap_or(i64::MAX)),
+        Core::Fetch => state.heap.get(x, 0),
+        Core::Store => {
+            let v = state.stack.pop().expect("Internal error @ store");
+            Arc::make_mut(&mut state.heap).set(x, 0, v);
+            return;
+        }
+        _ => {
+            let i = state.stack.pop().expect("Internal error @ array index");
+            let i = handle(&i).expect("Internal error @ array index handle");
+            if c == Core::Get {
+                state.heap.get(x, i)
+            } else {
+                let v = state.stack.pop().expect("Internal error @ array set");
+                Arc::make_mut(&mut state.heap).set(x, i, v);
+                return;
+            }
+        }
+    };
+    state.stack.push(r);
+}
+
+/// The ratio with a handle `check` has vouched for.
+fn ratio<T: Cell>(state: &State<T>, h: &T) -> Ratio {
+    state.ratios[handle(h).expect("Internal error @ ratio handle")]
+}
+
+/// Intern a ratio already in lowest terms, returning its handle, if there's room for it.
+fn intern_ratio<T: Cell>(state: &mut State<T>, r: Ratio) -> Result<T, Error> {
+    let i = if let Some(i) = state.ratios.get_index_of(&r) {
+        i
+    } else {
+        claim(state, size_of::<Ratio>())?;
+        Arc::make_mut(&mut state.ratios).insert_full(r).0
+    };
+    Ok(T::from_i64(
+        i64::try_from(i).expect("Internal error @ ratio handle"),
+    ))
+}
+
+/// The core words on bignums, which are kept on the stack as handles.
+/// Full of `stack.pop().expect(…)` because `check` has already ensured the stack is large enough.
+#[cfg(feature = "bignum")]
+fn eval_big<T: Cell>(state: &mut State<T>, c: Core) -> Result<(), Error> {
+    let x = state.stack.pop().expect("Internal error @ bignum op");
+    let n = match c {
+        Core::Big => x.to_big(),
+        Core::BToI => {
+            let n = T::from_big(big(state, &x)).expect("Internal error @ b>i");
+            state.stack.push(n);
+            return Ok(());
+        }
+        Core::BPrint => {
+            let n = big(state, &x);
+            state.output.print(format_args!("{n}\n"))?;
+            state.last = Some(x);
+            return Ok(());
+        }
+        _ => {
+            let y = state.stack.pop().expect("Internal error @ bignum op 2");
+            let (x, y) = (big(state, &x), big(state, &y));
+            match c {
+                Core::BAdd => x + y,
+                Core::BSub => x - y,
+                Core::BMul => x * y,
+                Core::BDiv => x / y,
+                _ => {
+                    let r = x % y;
+                    if r >= BigInt::ZERO {
+                        r
+                    } else if *y > BigInt::ZERO {
+                        r + y
+                    } else {
+                        r - y
+                    }
+                }
+            }
+        }
+    };
+    let h = intern_big(state, n)?;
+    state.stack.push(h);
+    Ok(())
+}
+
+/// The bignum with a handle `check` has vouched for.
+#[cfg(feature = "bignum")]
+fn big<'a, T: Cell>(state: &'a State<T>, h: &T) -> &'a BigInt {
+    &state.bigs[handle(h).expect("Internal error @ bignum handle")]
+}
+
+/// Intern a bignum, returning its handle, if there's room for it.
+#[cfg(feature = "bignum")]
+fn intern_big<T: Cell>(state: &mut State<T>, n: BigInt) -> Result<T, Error> {
+    let i = if let Some(i) = state.bigs.get_index_of(&n) {
+        i
+    } else {
+        claim(state, big_size(&n))?;
+        Arc::make_mut(&mut state.bigs).insert_full(n).0
+    };
+    Ok(T::from_i64(
+        i64::try_from(i).expect("Internal error @ bignum handle"),
+    ))
+}
+
+/// The string with a handle `check` has vouched for.
+fn string<T: Cell>(state: &State<T>, t: &T) -> Arc<str> {
+    Arc::clone(&state.strings[handle(t).expect("Internal error @ string handle")])
+}
+
+/// Intern a quotation, returning its handle, if there's room for it.
+fn intern_quote<T: Cell>(state: &mut State<T>, ws: &[Word<T>]) -> Result<T, Error> {
+    let i = if let Some(i) = state.quotes.get_index_of(ws) {
+        i
+    } else {
+        claim(state, quote_size(ws))?;
+        Arc::make_mut(&mut state.quotes).insert_full(ws.into()).0
+    };
+    Ok(T::from_i64(
+        i64::try_from(i).expect("Internal error @ quote handle"),
+    ))
+}
+
+/// Intern a string, returning its handle, if there's room for it.
+fn intern<T: Cell>(state: &mut State<T>, s: &str) -> Result<T, Error> {
+    let i = if let Some(i) = state.strings.get_index_of(s) {
+        i
+    } else {
+        claim(state, string_size(s))?;
+        Arc::make_mut(&mut state.strings).insert_full(s.into()).0
+    };
+    Ok(T::from_i64(
+        i64::try_from(i).expect("Internal error @ string handle"),
+    ))
+}
+
+/// How many values the stack may hold within the memory limit (if there is one) once `bytes`
+/// more are interned, failing if that's fewer than it holds.
+fn room_after<T: Cell>(state: &State<T>, bytes: usize) -> Result<Option<usize>, Error> {
+    state
+        .room
+        .map(|room| {
+            room.checked_sub(bytes.div_ceil(size_of::<T>()))
+                .filter(|&left| left >= state.stack.len())
+                .ok_or_else(|| Error::MemoryFull(state.limits.memory.unwrap_or_default()))
+        })
+        .transpose()
+}
+
+/// Take the room for `bytes` more interned out of what the memory limit (if any) leaves the
+/// stack, failing if there isn't enough.
+fn claim<T: Cell>(state: &mut State<T>, bytes: usize) -> Result<(), Error> {
+    state.room = room_after(state, bytes)?;
+    Ok(())
+}
+
+/// The character with this code point, if there is one.
+fn code_point<T: Cell>(n: &T) -> Option<char> {
+    n.to_i64()
+        .and_then(|n| u32::try_from(n).ok())
+        .and_then(char::from_u32)
+}
+
+/// The stack cell holding a truth value: 1 for true, & 0 for false.
+fn truth<T: Cell>(b: bool) -> T {
+    T::from_i64(b.into())
+}
+
+/// A float, from the stack cell holding its bit pattern.
+fn float<T: Cell>(n: &T) -> f64 {
+    f64::from_bits(n.as_i64().cast_unsigned())
+}
+
+/// The stack cell holding a float's bit pattern.
+fn cell<T: Cell>(x: f64) -> T {
+    T::from_i64(x.to_bits().cast_signed())
+}
+
+/// Start a worker evaluating the custom word `c` on the top items of the stack, given a stack
+/// `check`ed for it, & push its task id.
+#[cfg(feature = "concurrency")]
+fn spawn<T: Cell>(env: &Arc<Env<T>>, state: &mut State<T>, c: Symbol) {
+    let stack = &mut state.stack;
+    let k = stack.pop().expect("Internal error @ spawn");
+    let k = handle(&k).expect("Internal error @ spawn count");
+    let mut worker = State {
+        stack: stack.split_off(stack.len() - k),
+        edition: state.edition,
+        channels: state.channels.clone(),
+        cancel: state.cancel.clone(),
+        output: state.output.clone(),
+        input: state.input.clone(),
+        limits: state.limits,
+        fuel: state.limits.fuel,
+        room: state.room,
+        quotes: state.quotes.clone(),
+        strings: state.strings.clone(),
+        ratios: state.ratios.clone(),
+        #[cfg(feature = "bignum")]
+        bigs: state.bigs.clone(),
+        heap: state.heap.clone(),
+        symbols: state.symbols.clone(),
+        ..State::default()
+    };
+    let (env, w) = (Arc::clone(env), Word::Custom(c));
+    let id = state.workers.spawn(move || {
+        check(&env, &worker, &w)?;
+        eval_inner(&env, &mut worker, &w)?;
+        Ok(worker.stack)
+    });
+    state.stack.push(T::from_i64(id));
+}
+
+/// Pop the operands of a core word that executes quotations, given a stack `check`ed for it,
+/// returning what's to be evaluated (if anything).
+fn control<'a, T: Cell>(state: &mut State<T>, c: Core) -> Option<Frame<'a, T>> {
+    let q = quote(state, "quotation");
+    match c {
+        Core::If if state.stack.pop().expect("Internal error @ if") == T::ZERO => None,
+        Core::IfElse => {
+            let t = quote(state, "if-else");
+            let cond = state
+                .stack
+                .pop()
+                .expect("Internal error @ if-else condition");
+            Some(Frame::Words(
+                Body::Quote(if cond == T::ZERO { q } else { t }),
+                0,
+            ))
+        }
+        Core::Times => {
+            let n = state.stack.pop().expect("Internal error @ times");
+            Some(Frame::Times(q, n.to_i64().unwrap_or(i64::MAX)))
+        }
+        Core::While => Some(Frame::While(quote(state, "while"), q, false)),
+        _ => Some(Frame::Words(Body::Quote(q), 0)),
+    }
+}
+
+/// Pop a quotation handle `check` has vouched for, returning the quotation's body.
+fn quote<T: Cell>(state: &mut State<T>, at: &str) -> Arc<[Word<T>]> {
+    let q = state.stack.pop().as_ref().map(handle);
+    let q = q.unwrap_or_else(|| panic!("Internal error @ {at}"));
+    Arc::clone(&state.quotes[q.unwrap_or_else(|| panic!("Internal error @ {at} handle"))])
+}
+
+#[cfg(test)]
+mod tests {
     use super::{super::word::tests::word, *};
     use itertools::Itertools;
     use proptest::prelude::*;
@@ -181,13 +3097,7 @@ mod tests {
 
     #[test]
     fn def_errs() {
-        for s in [
-            "def",
-            "def name",
-            "def def drop",
-            "def drop body",
-            "def name name",
-        ] {
+        for s in ["def", "def name ;", "def def drop", "def drop body"] {
             assert!(Machine::default().read_eval(s).is_err());
         }
     }
@@ -197,10 +3107,1717 @@ mod tests {
             assert!(Machine::default().read_eval(s).is_err());
         }
     }
+    #[test]
+    fn editions() {
+        let max = "9223372036854775807 1 add";
+        let mut m = Machine::default();
+        assert!(m.read_eval(max).is_ok());
+        assert_eq!(m.state.stack, [i64::MAX]);
+        assert_eq!(
+            Machine::with_edition(Edition::Edition2026).read_eval(max),
+            Err(Error::Overflow("add".into()))
+        );
+        let mut m = Machine::default();
+        assert!(m.read_eval("#edition 2026").is_ok());
+        assert_eq!(m.edition(), Edition::Edition2026);
+        assert!(m.read_eval(max).is_err());
+        assert_eq!(m.read_eval("2 64 pow"), Err(Error::Overflow("pow".into())));
+        assert_eq!(m.read_eval("3 -1 pow"), Err(Error::Negative("pow".into())));
+        assert_eq!(
+            m.read_eval("-4 isqrt"),
+            Err(Error::Negative("isqrt".into()))
+        );
+        assert_eq!(m.read_eval("0 log2"), Err(Error::NotNonzero("log2".into())));
+        for (s, e) in [
+            ("#edition", Error::EditionName),
+            ("#edition 1999", Error::UnknownEdition("1999".into())),
+            ("def e #edition 2026", Error::EditionReserved),
+        ] {
+            assert_eq!(Machine::default().read_eval(s), Err(e));
+        }
+    }
+
+    #[test]
+    fn channels() {
+        let mut m1 = Machine::default();
+        let mut m2 = Machine::default();
+        m2.connect(m1.channels());
+        assert!(m1.read_eval("1 2 send c send c").is_ok());
+        assert_eq!(m2.channels().len("c"), 2);
+        assert!(m2.read_eval("recv c recv c").is_ok());
+        assert_eq!(m2.state.stack, [2, 1]);
+        assert_eq!(m2.read_eval("recv c"), Err(Error::ChannelEmpty("c".into())));
+        m1.channels().send("d", 3);
+        assert!(m2.read_eval("def fetch recv d").is_ok());
+        assert!(m2.read_eval("fetch").is_ok());
+        assert_eq!(m2.state.stack, [2, 1, 3]);
+        for s in ["send", "recv", "send 1", "recv dup"] {
+            assert!(Machine::default().read_eval(s).is_err());
+        }
+    }
+    #[cfg(feature = "concurrency")]
+    #[test]
+    fn spawn_join() {
+        let mut m = Machine::default();
+        m.set_max_workers(2);
+        assert!(m.read_eval("def sq dup mul").is_ok());
+        assert!(
+            m.read_eval("3 1 spawn sq 4 1 spawn sq join swap join")
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [16, 9]);
+        assert!(m.read_eval("def one 1").is_ok());
+        assert!(m.read_eval("0 spawn one 0 spawn one").is_ok());
+        assert_eq!(m.read_eval("0 spawn one"), Err(Error::WorkerLimit(2)));
+        assert!(m.read_eval("drop join swap join").is_ok());
+        assert_eq!(m.state.stack, [16, 9, 1, 1]);
+        assert_eq!(
+            m.read_eval("0 spawn sq join"),
+            Err(Error::Small("dup".into(), 1, 0))
+        );
+        for (s, e) in [
+            ("spawn", Error::SpawnName),
+            ("-1 spawn sq", Error::Negative("spawn sq".into())),
+            ("0 spawn nope", Error::Unknown("nope".into())),
+            ("7 join", Error::UnknownTask(7)),
+        ] {
+            assert_eq!(m.read_eval(s), Err(e));
+        }
+    }
+    #[test]
+    fn limits() {
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert_eq!(
+            m.read_eval("1 print"),
+            Err(Error::CapabilityDenied("print".into()))
+        );
+        m.set_limits(Limits {
+            fuel: Some(4),
+            stack_depth: Some(3),
+            recursion_depth: Some(2),
+            env_size: Some(3),
+            heap_size: None,
+            memory: None,
+            capabilities: Capabilities::ALL,
+        });
+        assert_eq!(m.read_eval("1 drop 1 drop"), Ok(()));
+        assert_eq!(
+            m.read_eval("1 drop 1 drop 1"),
+            Err(Error::BudgetExceeded(4))
+        );
+        m.state.stack.clear();
+        assert_eq!(m.read_eval("1 2 3 4"), Err(Error::StackOverflow(3)));
+        for s in ["def a 1", "def b a 1", "def c b 1", "def c b 1"] {
+            assert_eq!(m.read_eval(s), Ok(()));
+        }
+        assert_eq!(m.read_eval("def d c"), Err(Error::EnvFull(3)));
+        m.state.stack.clear();
+        assert_eq!(m.read_eval("b"), Ok(()));
+        assert_eq!(m.read_eval("c"), Err(Error::RecursionLimit(2)));
+    }
+    #[test]
+    fn capabilities() {
+        let mut m = Machine::default();
+        m.set_capabilities(Capabilities::NONE);
+        for s in ["1 print", "1.5 fprint", "\"s\" sprint", "65 emit", ".s"] {
+            let w = s.rsplit(' ').next().unwrap_or_default();
+            assert_eq!(m.read_eval(s), Err(Error::CapabilityDenied(w.into())));
+            m.clear_stack();
+        }
+        assert_eq!(m.read_eval("def p print ; 1 [ p ] catch"), Ok(()));
+        assert_eq!(
+            m.stack(),
+            [1, Error::CapabilityDenied("print".into()).code()]
+        );
+        assert_eq!(m.limits().fuel, None);
+        m.set_capabilities(Capabilities {
+            print: true,
+            ..Capabilities::NONE
+        });
+        assert!(m.capabilities().print && !m.capabilities().communicate);
+        assert_eq!(Profile::Strict.limits().capabilities, Capabilities::NONE);
+        let mut m = Machine::with_profile(Profile::Strict);
+        let mut denied = vec![("1 send c", "send c"), ("recv c", "recv c")];
+        if cfg!(feature = "concurrency") {
+            assert_eq!(m.read_eval("def w 1"), Ok(()));
+            denied.extend([("0 spawn w", "spawn w"), ("0 join", "join")]);
+        }
+        for (s, w) in denied {
+            assert_eq!(m.read_eval(s), Err(Error::CapabilityDenied(w.into())));
+        }
+    }
+    #[test]
+    fn memory() {
+        let mut m = Machine::default();
+        assert_eq!(m.memory_usage(), 0);
+        assert!(m.read_eval("1 2").is_ok());
+        assert_eq!(m.memory_usage(), 16);
+        assert!(m.read_eval("def sq dup mul ;").is_ok());
+        let env = m.memory_usage() - 16;
+        assert!(env > 2 * size_of::<Word>());
+        let limit = env + 4 * size_of::<i64>();
+        m.set_limits(Limits {
+            memory: Some(limit),
+            ..Limits::default()
+        });
+        assert_eq!(m.read_eval("3 4 5"), Err(Error::MemoryFull(limit)));
+        m.clear_stack();
+        assert_eq!(m.read_eval("1 2 3"), Ok(()));
+        assert_eq!(m.push(4), Ok(()));
+        assert_eq!(m.push(5), Err(Error::MemoryFull(limit)));
+        assert_eq!(
+            m.read_eval("def cube dup sq mul ;"),
+            Err(Error::MemoryFull(limit))
+        );
+        m.clear_stack();
+        assert_eq!(m.read_eval("1 dup dup sq"), Ok(()));
+        assert_eq!(m.read_eval("dup dup"), Err(Error::MemoryFull(limit)));
+        let mut m = Machine::default();
+        assert_eq!(m.read_eval("\"abc\" [ 1 ] drop"), Ok(()));
+        assert!(m.memory_usage() > 3 + 2 * size_of::<i64>());
+        let mut m = Machine::with_profile(Profile::Strict);
+        let s = "\"aaaaaaaaaaaaaaaa\" 24 [ dup concat ] times length";
+        assert_eq!(m.read_eval(s), Err(Error::MemoryFull(1 << 20)));
+        assert!(m.memory_usage() <= 1 << 20);
+    }
+    #[test]
+    fn cancel() {
+        let mut m = Machine::default();
+        let token = m.cancel_token().clone();
+        token.cancel();
+        assert_eq!(m.read_eval("1 2"), Err(Error::Cancelled));
+        assert!(!token.is_cancelled());
+        assert_eq!(m.read_eval("1 2"), Ok(()));
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            token.cancel();
+        });
+        let s = "def forever 1 drop forever ; [ forever ] catch";
+        assert_eq!(m.read_eval(s), Err(Error::Cancelled));
+        assert!(canceller.join().is_ok());
+        let token = CancelToken::new();
+        m.set_cancel_token(&token);
+        token.cancel();
+        token.reset();
+        assert_eq!(m.read_eval("3"), Ok(()));
+        m.cancel_token().cancel();
+        assert!(token.is_cancelled());
+    }
+    /// Somewhere to write to, then read back what was written.
+    #[derive(Clone, Default)]
+    struct Sink(Arc<std::sync::Mutex<Vec<u8>>>);
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().map_or(Ok(0), |mut v| v.write(buf))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    impl Sink {
+        fn written(&self) -> String {
+            let bytes = self.0.lock().map(|v| v.clone()).unwrap_or_default();
+            String::from_utf8(bytes).unwrap_or_default()
+        }
+    }
+    #[test]
+    fn output() {
+        struct Broken;
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("broken"))
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let (sink, mut m) = (Sink::default(), Machine::default());
+        m.set_output(sink.clone());
+        let s = r#"3 print 104 emit 105 emit 10 emit 1 2 .s "yes" sprint 5 print- 32 emit -6 print- 10 emit -255 hexprint 5 binprint 7 3 print-w -7 3 print-0w 10 emit 8 . peek drop"#;
+        assert_eq!(m.read_eval(s), Ok(()));
+        m.clone().reset();
+        let mut n = Machine::default();
+        n.share_output(m.output());
+        assert_eq!(n.read_eval("4 print"), Ok(()));
+        assert_eq!(
+            sink.written(),
+            "3\nhi\n<2> 1 2\nyes\n5 -6\n-#ff\n0b101\n  7-07\n8\n8\n4\n"
+        );
+        let e = Error::TooWide("print-0w".into(), 1024);
+        assert_eq!(n.read_eval("1 1025 print-0w"), Err(e));
+        let e = Error::Negative("print-w".into());
+        assert_eq!(n.read_eval("-1 print-w"), Err(e));
+        m.set_output(Broken);
+        let e = Error::Io("output: broken".into());
+        assert_eq!(m.read_eval("print"), Err(e));
+        assert_eq!(m.state.stack, [1]);
+    }
+    #[test]
+    fn trace() {
+        let (sink, mut m) = (Sink::default(), Machine::default());
+        assert_eq!(
+            m.read_eval("def sq dup mul ; def quad sq sq 1 add ; 2"),
+            Ok(())
+        );
+        m.set_trace(Some(&Output::new(sink.clone())));
+        assert!(m.trace().is_some());
+        assert_eq!(m.read_eval("quad [ 3 ] call"), Ok(()));
+        m.set_trace(None);
+        assert_eq!(m.read_eval("drop"), Ok(()));
+        let traced = [
+            "quad  [ 2 ]",
+            "  sq  [ 2 ]",
+            "    dup  [ 2 2 ]",
+            "    mul  [ 4 ]",
+            "  sq  [ 4 ]",
+            "    dup  [ 4 4 ]",
+            "    mul  [ 16 ]",
+            "  1  [ 1 16 ]",
+            "  add  [ 17 ]",
+            "[ 3 ]  [ 0 17 ]",
+            "call  [ 17 ]",
+            "3  [ 3 17 ]",
+        ];
+        assert_eq!(sink.written().lines().collect::<Vec<_>>(), traced);
+    }
+    #[test]
+    fn debugger() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut resumes = [Resume::Step, Resume::Step, Resume::Continue, Resume::Abort].into_iter();
+        let mut d = Debugger::new(move |p: Pause<i64>| {
+            let _ = tx.send((p.word, p.depth, p.stack, p.definitions.len()));
+            resumes.next().unwrap_or(Resume::Continue)
+        });
+        d.set_breakpoint("sq");
+        let mut m = Machine::default();
+        assert_eq!(m.read_eval("def sq dup mul ; def quad sq sq ;"), Ok(()));
+        m.set_debugger(Some(d));
+        assert_eq!(m.debugger().map(|d| d.breakpoints().count()), Some(1));
+        assert_eq!(m.read_eval("3 quad"), Err(Error::Cancelled));
+        assert_eq!(m.state.stack, [9]);
+        let pauses: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            pauses,
+            [
+                ("sq".into(), 1, vec![3], 2),
+                ("dup".into(), 2, vec![3], 2),
+                ("mul".into(), 2, vec![3, 3], 2),
+                ("sq".into(), 1, vec![9], 2),
+            ]
+        );
+        if let Some(d) = &mut m.state.debugger {
+            assert!(d.remove_breakpoint("sq"));
+            assert!(!d.remove_breakpoint("sq"));
+        }
+        assert_eq!(m.read_eval("quad"), Ok(()));
+        assert_eq!(m.state.stack, [6561]);
+        m.set_debugger(None);
+        assert!(m.debugger().is_none());
+    }
+    #[test]
+    fn input() {
+        let mut m = Machine::default();
+        m.set_input_values([3, 4]);
+        assert_eq!(m.read_eval("read read add"), Ok(()));
+        assert_eq!(m.state.stack, [7]);
+        assert_eq!(m.read_eval("read"), Err(Error::EndOfInput));
+        assert_eq!(m.read_eval("[ read ] catch"), Ok(()));
+        assert_eq!(m.state.stack, [7, -63]);
+        m.set_input(&b"5\n-6 x"[..]);
+        let mut n = Machine::<u64>::new();
+        n.share_input(m.input());
+        assert_eq!(m.read_eval("read"), Ok(()));
+        assert_eq!(n.read_eval("read"), Ok(()));
+        assert_eq!(n.state.stack, [u64::MAX - 5]);
+        assert!(matches!(m.read_eval("read"), Err(Error::Parsing(_))));
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert_eq!(
+            m.read_eval("read"),
+            Err(Error::CapabilityDenied("read".into()))
+        );
+    }
+    #[test]
+    fn atomic() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("1 2 3 array dup 7 0 rot set def open").is_ok());
+        let before = m.clone();
+        let s = "1 ; dup 9 0 rot set 4 mul #edition 2026 def sq dup mul ; \"s\" print nope";
+        assert_eq!(m.read_eval_atomic(s), Err(Error::Unknown("nope".into())));
+        assert_eq!(m, before);
+        assert_eq!(
+            (m.edition(), m.defining(), m.last(), m.to_source()),
+            (before.edition(), Some("open"), None, before.to_source())
+        );
+        assert_eq!(m.read_eval_atomic("1 ; open 0 rot get"), Ok(()));
+        assert_eq!(m.stack(), [1, 2, 1, 7]);
+        let mut m = Machine::builder()
+            .memo_capacity(100)
+            .build()
+            .expect("builds");
+        assert_eq!(m.read_eval("def f 1 ;"), Ok(()));
+        let r = m.read_eval_atomic("redef f 2 ; f drop nope");
+        assert_eq!(r, Err(Error::Unknown("nope".into())));
+        assert_eq!(m.read_eval("f"), Ok(()));
+        assert_eq!(
+            (m.stack(), m.lookup("f").as_deref()),
+            ([1].as_slice(), Some("1"))
+        );
+    }
+    #[test]
+    fn unbound() {
+        let mut m = Machine::default();
+        assert_eq!(
+            m.read_eval("1 unbound_never"),
+            Err(Error::Unknown("unbound_never".into()))
+        );
+        assert_eq!(m.state.symbols.get("unbound_never"), None);
+        let mut other = Machine::default();
+        assert_eq!(other.read_eval("def unbound_elsewhere 1 ;"), Ok(()));
+        assert!(other.state.symbols.get("unbound_elsewhere").is_some());
+        assert_eq!(m.state.symbols.get("unbound_elsewhere"), None);
+        assert!(
+            m.read_eval(
+                "[ unbound_later ] def unbound_later unbound_sq ; def unbound_sq dup mul ; call"
+            )
+            .is_ok()
+        );
+        assert_eq!(m.stack(), [1]);
+        assert_eq!(m.read_eval("3 unbound_later"), Ok(()));
+        assert_eq!(m.stack(), [1, 9]);
+    }
+    #[test]
+    fn redefinition() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("def x 1 ; def x 2 ;").is_ok());
+        m.set_redefinition(Redefinition::Strict);
+        assert_eq!(
+            m.read_eval("def y 3 ; def y 4 ;"),
+            Err(Error::Redefined("y".into()))
+        );
+        assert_eq!(
+            m.define("x", vec![Word::Num(5)]),
+            Err(Error::Redefined("x".into()))
+        );
+        assert_eq!(m.read_eval("var x"), Err(Error::Redefined("x".into())));
+        assert_eq!(m.read_eval("redef y 5 ; x y"), Ok(()));
+        assert_eq!(m.stack(), [2, 5]);
+        assert_eq!(m.read_eval("redef y"), Ok(()));
+        assert_eq!(m.read_eval("6 ; def y"), Ok(()));
+        assert_eq!(m.read_eval("7 ;"), Err(Error::Redefined("y".into())));
+        assert_eq!(m.read_eval("clear y"), Ok(()));
+        assert_eq!(m.stack(), [6]);
+        let warned = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let w = Arc::clone(&warned);
+        m.set_redefinition(Redefinition::Warn(Arc::new(move |k| {
+            w.lock().expect("unpoisoned").push(k.to_owned());
+        })));
+        assert!(
+            m.read_eval("def x 6 ; def z 7 ; redef y 8 ; def z 9 ;")
+                .is_ok()
+        );
+        assert_eq!(*warned.lock().expect("unpoisoned"), ["x", "z"]);
+        assert_eq!(m.read_eval("def x ;"), Err(Error::DefBody));
+        assert!(m.read_eval("def x 1 ] ;").is_err());
+        assert_eq!(*warned.lock().expect("unpoisoned"), ["x", "z"]);
+        assert_eq!(m.read_eval("redef"), Err(Error::DefName));
+        assert_eq!(m.read_eval("def redef 1 ;"), Err(Error::RedefReserved));
+    }
+    #[test]
+    fn inline() {
+        let defs = "def sq dup mul ; def cube dup sq mul ; def q [ sq ] ; def three 1 2 3 ; \
+                    def six three add add ; def r 1 r ; def f r ; def e 1 exit ; def g e 2 ;";
+        let mut m = Machine::default();
+        assert!(m.read_eval(defs).is_ok());
+        assert_eq!(m.lookup("cube").unwrap_or_default(), "dup sq mul");
+        m = Machine::builder()
+            .inline_threshold(2)
+            .build()
+            .expect("builds");
+        assert!(m.read_eval(defs).is_ok());
+        for (k, source) in [
+            ("cube", "dup dup mul mul"),
+            ("q", "[ dup mul ]"),
+            ("six", "three add add"),
+            ("f", "r"),
+            ("g", "e 2"),
+        ] {
+            assert_eq!(m.lookup(k).unwrap_or_default(), source);
+        }
+        assert_eq!(m.read_eval("3 cube"), Ok(()));
+        assert_eq!(m.stats().words_executed, 6);
+        assert_eq!(m.read_eval("def sq 0 ; 2 cube"), Ok(()));
+        assert_eq!(m.stack(), [27, 8]);
+        assert_eq!(m.undefine("sq", false), Ok(()));
+    }
+
+    #[test]
+    fn fork() {
+        let mut m = Machine::default();
+        assert_eq!(m.read_eval("def sq dup mul ; 3 4"), Ok(()));
+        m.channels().send("c", 1);
+        let mut f = m.fork();
+        assert!(Arc::ptr_eq(&m.env, &f.env));
+        assert_eq!(f.read_eval("def cube dup sq mul ; cube recv c add"), Ok(()));
+        assert_eq!(f.stack(), [3, 65]);
+        assert!(std::ptr::eq(m.env[0].as_ptr(), f.env[0].as_ptr()));
+        assert_eq!((m.stack(), m.definition("cube")), ([3, 4].as_slice(), None));
+        assert_eq!(m.channels().len("c"), 1);
+        assert_eq!(m.read_eval(r#"2 array "s" 1/2"#), Ok(()));
+        let mut f = m.clone();
+        assert!(Arc::ptr_eq(&m.state.heap, &f.state.heap));
+        assert!(Arc::ptr_eq(&m.state.strings, &f.state.strings));
+        assert_eq!(f.read_eval("drop drop 1 0 set \"t\""), Ok(()));
+        assert!(!Arc::ptr_eq(&m.state.heap, &f.state.heap));
+        assert_eq!((m.state.heap.get(0, 0), m.state.strings.len()), (0, 1));
+        assert!(Arc::ptr_eq(&m.state.ratios, &f.state.ratios));
+    }
+
+    #[test]
+    fn memo() {
+        let defs = "def fib dup 2 gt? [ drop 1 ] [ dup 1 swap sub fib swap 2 swap sub fib add ] \
+                    if-else ; def quad dup mul dup mul ; def p dup print ; def d depth ; \
+                    def odd dup [ 1 swap sub even ] if ; def even dup [ 1 swap sub odd ] if ; \
+                    def loop loop ; def grow 1 grow ; def maybe [ 1 ] [ 1 2 ] if-else ;";
+        let mut m = Machine::builder()
+            .memo_capacity(64)
+            .build()
+            .expect("builds");
+        assert_eq!(m.read_eval(defs), Ok(()));
+        for (k, e) in [
+            ("fib", Some((1, 1))),
+            ("quad", Some((1, 1))),
+            ("p", None),
+            ("d", None),
+            ("odd", Some((1, 1))),
+            ("even", Some((1, 1))),
+            ("loop", None),
+            ("grow", None),
+            ("maybe", None),
+        ] {
+            assert_eq!(
+                Arc::make_mut(&mut m.state.memo).effect(&m.env, &m.state.symbols.intern(k)),
+                e,
+                "{k}"
+            );
+        }
+        assert_eq!(m.read_eval("20 fib"), Ok(()));
+        assert_eq!((m.stack(), m.memo_len()), ([10946].as_slice(), 21));
+        let executed = m.stats().words_executed;
+        assert_eq!(m.read_eval("drop 20 fib 1 p"), Ok(()));
+        assert_eq!(m.stats().words_executed - executed, 7);
+        assert_eq!(m.read_eval("clear 5 odd"), Ok(()));
+        assert_eq!(m.stack(), [0]);
+        assert_eq!(m.read_eval("def fib drop 0 ; clear 20 fib"), Ok(()));
+        assert_eq!((m.stack(), m.memo_len()), ([0].as_slice(), 1));
+        m.clear_memo();
+        m.set_memo_capacity(0);
+        assert_eq!(m.read_eval("20 fib"), Ok(()));
+        assert_eq!(m.memo_len(), 0);
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn jit() {
+        let defs = "def sq dup mul ; def f over over add rot rot sub mul ; def g 3 f sq 7 mod ; \
+                    def h 5 swap div ; def k 1 2 3 rot 2swap 2over 2dup tuck nip zero? ; \
+                    def c lt? swap 3 ge? and not 1.5 drop ; def m 10 min -3 max abs neg ; \
+                    def b 3 shl -1 shr bnot 255 band 7 bor 5 bxor popcount ; \
+                    def s dup add dup mul swap drop ; def deep 1 2 3 4 5 6 7 2drop 2drop 2drop ; \
+                    def p dup print ; def q [ 1 ] call ; def r 1 r ; def t g h m ;";
+        let limits = Limits {
+            stack_depth: Some(8),
+            recursion_depth: Some(3),
+            ..Limits::default()
+        };
+        for edition in [Edition::Edition2025, Edition::Edition2026] {
+            for (limits, optimize) in [(Limits::default(), false), (limits, false), (limits, true)]
+            {
+                let [mut m, mut n] = [1, 0].map(|threshold| {
+                    let mut m = Machine::builder()
+                        .edition(edition)
+                        .limits(limits)
+                        .optimize(optimize)
+                        .jit_threshold(threshold)
+                        .build()
+                        .expect("builds");
+                    assert_eq!(m.read_eval(defs), Ok(()));
+                    m
+                });
+                for s in [
+                    "4 9 g",
+                    "-8 h 0 h",
+                    "1 2 k",
+                    "4 2 c 1 5 c",
+                    "-50 m 50 m",
+                    "9 b -9 b",
+                    "3 s 9223372036854775807 s",
+                    "deep",
+                    "2 p q",
+                    "4 9 t",
+                    "-9223372036854775808 -1 h",
+                    "1 2 3 4 5 t",
+                ] {
+                    let s = format!("clear {s}");
+                    assert_eq!(m.read_eval(&s), n.read_eval(&s), "{s}");
+                    assert_eq!(m.stack(), n.stack(), "{s}");
+                    let (jitted, interpreted) = (m.stats(), n.stats());
+                    assert_eq!(
+                        (
+                            jitted.words_executed,
+                            jitted.fuel_consumed,
+                            jitted.max_stack_depth
+                        ),
+                        (
+                            interpreted.words_executed,
+                            interpreted.fuel_consumed,
+                            interpreted.max_stack_depth
+                        ),
+                        "{s}"
+                    );
+                    assert_eq!(
+                        m.read_eval_limited(&s, 20),
+                        n.read_eval_limited(&s, 20),
+                        "{s}"
+                    );
+                }
+                for (k, compiled) in [("t", true), ("p", false), ("q", false), ("r", false)] {
+                    let c = m
+                        .state
+                        .jit
+                        .call(&m.env, edition, &m.state.symbols.intern(k));
+                    assert_eq!(c.is_some(), compiled, "{k}");
+                }
+            }
+        }
+        let mut m = Machine::builder().jit_threshold(2).build().expect("builds");
+        assert_eq!(m.read_eval("def one 1 ; one one one"), Ok(()));
+        assert!(
+            m.state
+                .jit
+                .call(&m.env, m.edition(), &m.state.symbols.intern("one"))
+                .is_some()
+        );
+        assert_eq!(m.read_eval("def one 2 ; clear one"), Ok(()));
+        assert_eq!(m.stack(), [2]);
+        let r = m.read_eval_atomic("redef one 3 ; one one one drop drop drop nope");
+        assert_eq!(r, Err(Error::Unknown("nope".into())));
+        assert_eq!(m.read_eval("clear one one"), Ok(()));
+        assert_eq!(m.stack(), [2, 2]);
+        m.set_jit_threshold(0);
+        assert_eq!(m.jit_threshold(), 0);
+        assert!(
+            m.state
+                .jit
+                .call(&m.env, m.edition(), &m.state.symbols.intern("one"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn hoisted() {
+        let defs = "def f swap over add ; def g dup mul 0 1 div ;";
+        for edition in [Edition::Edition2025, Edition::Edition2026] {
+            let mut m = Machine::builder().edition(edition).build().expect("builds");
+            assert_eq!(m.read_eval(defs), Ok(()));
+            assert_eq!(m.read_eval("1 2 f"), Ok(()));
+            assert_eq!(m.stack(), [2, 3]);
+            assert_eq!(
+                m.read_eval("clear 1 f"),
+                Err(Error::Small("swap".into(), 2, 1))
+            );
+            let r = m.read_eval(&format!("clear {} g", i64::MAX));
+            let e = if edition.checked_arithmetic() {
+                Error::Overflow("mul".into())
+            } else {
+                Error::NotNonzero("div".into())
+            };
+            assert_eq!(r, Err(e));
+        }
+    }
+    #[test]
+    fn optimize() {
+        let mut m = Machine::default();
+        m.set_optimize(true);
+        let s = "def z 0 0 add 0 0 add add ; def c 2 3 add 4 mul neg? ; def s swap swap dup drop 1 ; \
+                 def q [ 1 2 sub ] 3 drop call ; def d 0 1 div ; def p dup drop ;";
+        assert_eq!(m.read_eval(s), Ok(()));
+        for (k, source) in [
+            ("z", "0"),
+            ("c", "0"),
+            ("s", "1"),
+            ("q", "[ 1 ] call"),
+            ("d", "0 1 div"),
+            ("p", "dup drop"),
+        ] {
+            assert_eq!(m.lookup(k).unwrap_or_default(), source);
+        }
+        assert_eq!(
+            m.read_eval("#edition 2026 def o 9223372036854775807 1 add ;"),
+            Ok(())
+        );
+        assert_eq!(
+            m.lookup("o").unwrap_or_default(),
+            "9223372036854775807 1 add"
+        );
+        assert_eq!(
+            m.read_eval("#edition 2025 def o 9223372036854775807 1 add ;"),
+            Ok(())
+        );
+        assert_eq!(m.lookup("o").unwrap_or_default(), "9223372036854775807");
+    }
+    #[test]
+    fn fused() {
+        let mut m = Machine::builder().optimize(true).build().expect("builds");
+        let s = "def f dup mul swap drop dup add ; def g [ dup add ] call ; 2 3 f 4 g";
+        assert_eq!(m.read_eval(s), Ok(()));
+        assert_eq!(m.stack(), [18, 8]);
+        assert_eq!(
+            m.definition("f"),
+            Some(&[Fused::DupMul, Fused::SwapDrop, Fused::DupAdd].map(Word::Fused)[..])
+        );
+        assert_eq!(
+            m.lookup("f").unwrap_or_default(),
+            "dup mul swap drop dup add"
+        );
+        assert_eq!(
+            m.definition("g"),
+            Some(
+                &[
+                    Word::Quote(vec![Word::Fused(Fused::DupAdd)]),
+                    Word::Core(Core::Call)
+                ][..]
+            )
+        );
+        assert!(m.to_rust("f").is_ok());
+        let mut restored = Machine::new();
+        assert_eq!(restored.restore(&m.snapshot()), Ok(()));
+        assert_eq!(restored.definition("f"), m.definition("f"));
+        m.clear_stack();
+        assert_eq!(m.read_eval("f"), Err(Error::Small("dup mul".into(), 1, 0)));
+        assert_eq!(
+            m.read_eval("#edition 2026 4611686018427387904 g"),
+            Err(Error::Overflow("dup add".into()))
+        );
+    }
+    #[test]
+    fn budget() {
+        let mut m = Machine::default();
+        assert_eq!(m.read_eval_limited("def f 1 f ;", 0), Ok(()));
+        assert_eq!(
+            m.read_eval_limited("f", 1000),
+            Err(Error::BudgetExceeded(1000))
+        );
+        assert_eq!(m.len(), 500);
+        assert_eq!(m.limits().fuel, None);
+        m.clear_stack();
+        assert_eq!(m.read_eval_limited("1 drop", 2), Ok(()));
+        m.set_limits(Limits {
+            fuel: Some(4),
+            ..Limits::default()
+        });
+        assert_eq!(
+            m.read_eval_limited("1 2 3 4 5", 8),
+            Err(Error::BudgetExceeded(4))
+        );
+        assert_eq!(m.limits().fuel, Some(4));
+    }
+    #[test]
+    fn predicates() {
+        for (e, s, r) in [
+            (Edition::Edition2025, "3 2 0 zero?", 2),
+            (Edition::Edition2025, "3 2 1 zero?", 3),
+            (Edition::Edition2026, "0 zero?", 1),
+            (Edition::Edition2026, "5 zero?", 0),
+            (Edition::Edition2026, "3 2 0 select", 2),
+            (Edition::Edition2025, "-5 neg?", 1),
+            (Edition::Edition2025, "0 neg?", 0),
+            (Edition::Edition2025, "5 pos?", 1),
+            (Edition::Edition2025, "0 pos?", 0),
+        ] {
+            let mut m = Machine::with_edition(e);
+            assert!(m.read_eval(s).is_ok());
+            assert_eq!(m.state.stack, [r]);
+        }
+    }
+    #[test]
+    fn display() {
+        let mut m = Machine::default();
+        for s in ["def a 1", "def b 2", "def c 3"] {
+            assert!(m.read_eval(s).is_ok());
+        }
+        assert!(m.read_eval("1 2 3 4").is_ok());
+        let s = format!("{m:.2}");
+        assert!(s.ends_with("env: a b … and 1 more\nstack: [ 4 3 … and 2 more ]"));
+        assert!(format!("{m}").ends_with("env: a b c\nstack: [ 4 3 2 1 ]"));
+        let s = format!("{m:#.1}");
+        assert!(s.ends_with("env:\n  a\n  … and 2 more\nstack: [\n  4\n  … and 3 more\n]"));
+    }
+    #[test]
+    fn to_source() {
+        let mut m = Machine::with_edition(Edition::Edition2026);
+        for s in [
+            "def sq dup mul",
+            "def out send c",
+            "-9223372036854775808 #ff",
+        ] {
+            assert!(m.read_eval(s).is_ok());
+        }
+        let s = m.to_source();
+        assert_eq!(
+            s,
+            "#edition 2026\ndef sq dup mul\ndef out send c\n-9223372036854775808 255\n"
+        );
+        let mut m2 = Machine::default();
+        assert!(m2.read_eval_stream(s.as_bytes()).is_ok());
+        assert_eq!(m2.to_string(), m.to_string());
+    }
+    #[test]
+    fn last() {
+        let mut m = Machine::default();
+        assert_eq!(m.read_eval("_"), Err(Error::NoLast));
+        assert!(m.read_eval("3 4 mul print _ last add").is_ok());
+        assert_eq!(m.last(), Some(12));
+        assert_eq!(m.state.stack, [24]);
+    }
+    #[test]
+    fn markers() {
+        let mut m = Machine::default();
+        assert_eq!(m.read_eval("clear-to-marker"), Err(Error::NoMarker));
+        assert!(m.read_eval("1 mark 2 mark 3 4 clear-to-marker").is_ok());
+        assert_eq!(m.state.stack, [1, 2]);
+        assert!(m.read_eval("5 clear-to-marker").is_ok());
+        assert_eq!(m.state.stack, [1]);
+        assert!(m.read_eval("mark drop 2 3").is_ok());
+        assert_eq!(m.read_eval("clear-to-marker"), Err(Error::NoMarker));
+    }
+    #[test]
+    fn stats() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("def sq dup mul").is_ok());
+        assert!(m.read_eval("1 2 3 sq").is_ok());
+        assert_eq!(
+            *m.stats(),
+            Stats {
+                words_executed: 6,
+                max_stack_depth: 4,
+                defs_added: 1,
+                fuel_consumed: 6,
+            }
+        );
+        let (result, eval) = m.read_eval_stats("drop def cube dup sq mul ; cube 1 nope");
+        assert_eq!(result, Err(Error::Unknown("nope".into())));
+        assert_eq!(
+            (eval.words_executed, eval.max_stack_depth, eval.defs_added),
+            (8, 4, 1)
+        );
+        assert!(eval.duration > std::time::Duration::ZERO);
+        assert_eq!(
+            *m.stats(),
+            Stats {
+                words_executed: 14,
+                max_stack_depth: 4,
+                defs_added: 2,
+                fuel_consumed: 14,
+            }
+        );
+    }
+
+    #[test]
+    fn quotes() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("def sq [ dup mul ] call").is_ok());
+        assert!(m.read_eval("3 sq [ 1 [ 2 ] ] dup call call").is_ok());
+        assert_eq!(m.state.stack, [9, 1, 1, 2]);
+        assert_eq!(m.state.quotes.len(), 3);
+        assert_eq!(m.read_eval("7 call"), Err(Error::UnknownQuote(7)));
+        assert_eq!(m.read_eval("[ dup"), Err(Error::Unclosed));
+        assert_eq!(m.read_eval("]"), Err(Error::Unopened));
+        assert!(m.read_eval("[ 4 ]").is_ok());
+        let mut m2 = Machine::default();
+        assert!(m2.read_eval_stream(m.to_source().as_bytes()).is_ok());
+        assert!(m2.read_eval("call").is_ok());
+        assert_eq!(m2.state.stack, [9, 1, 1, 2, 7, 4]);
+    }
+
+    #[test]
+    fn shuffles() {
+        let mut m = Machine::default();
+        for (s, xs) in [
+            ("1 2 over", &[1, 2, 1][..]),
+            ("1 2 3 rot", &[2, 3, 1]),
+            ("1 2 nip", &[2]),
+            ("1 2 tuck", &[2, 1, 2]),
+            ("1 2 3 0 pick", &[1, 2, 3, 3]),
+            ("1 2 3 2 pick", &[1, 2, 3, 1]),
+            ("1 2 3 0 roll", &[1, 2, 3]),
+            ("1 2 3 2 roll", &[2, 3, 1]),
+            ("1 2 3 2drop", &[1]),
+            ("1 2 3 4 2swap", &[3, 4, 1, 2]),
+            ("1 2 2dup", &[1, 2, 1, 2]),
+            ("1 2 3 4 2over", &[1, 2, 3, 4, 1, 2]),
+        ] {
+            assert!(m.read_eval(s).is_ok());
+            assert_eq!(m.state.stack, xs);
+            m.state.stack.clear();
+        }
+        for (s, e) in [
+            ("1 2 3 pick", Error::Small("pick".into(), 5, 3)),
+            ("1 -1 roll", Error::Negative("roll".into())),
+            ("1 2 3 2over", Error::Small("2over".into(), 4, 3)),
+        ] {
+            assert_eq!(m.read_eval(s), Err(e));
+            m.state.stack.clear();
+        }
+    }
+
+    #[test]
+    fn stack_access() {
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert!(m.is_empty());
+        assert_eq!((m.push(3), m.push(4)), (Ok(()), Ok(())));
+        assert!(m.read_eval("mul 1").is_ok());
+        assert_eq!((m.stack(), m.len(), m.peek()), (&[12, 1][..], 2, Some(1)));
+        assert_eq!((m.pop(), m.pop(), m.pop()), (Some(1), Some(12), None));
+        let n = m.limits().stack_depth.expect("limited");
+        let r = (0..=n).try_for_each(|_| m.push(0));
+        assert_eq!((r, m.len()), (Err(Error::StackOverflow(n)), n));
+    }
+
+    #[test]
+    fn define() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("2 const two").is_ok());
+        let body = vec![Word::Unbound("two".into()), Word::Core(Core::Mul)];
+        assert_eq!(m.define("double", body), Ok(()));
+        assert_eq!(m.lookup("double"), Some("2 mul".into()));
+        for (k, e) in [
+            ("def", Error::DefReserved),
+            ("dup", Error::CoreNotName("dup".into())),
+            ("two", Error::AlreadyDefined("two".into())),
+            ("", Error::DefName),
+        ] {
+            assert_eq!(m.define(k, vec![Word::Num(1)]), Err(e));
+        }
+        assert_eq!(m.define("empty", vec![]), Err(Error::DefBody));
+    }
+
+    #[test]
+    fn eval_words() {
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert!(m.read_eval("3 const three").is_ok());
+        assert_eq!(
+            m.define("sq", vec![Word::Core(Core::Dup), Word::Core(Core::Mul)]),
+            Ok(())
+        );
+        let ws = [Word::Unbound("three".into()), Word::Unbound("sq".into())];
+        assert_eq!(m.eval_words(&ws), Ok(()));
+        assert_eq!(m.eval_word(&Word::Quote(vec![Word::Num(1)])), Ok(()));
+        assert_eq!(m.len(), 2);
+        assert_eq!(m.eval_word(&Word::Core(Core::Call)), Ok(()));
+        assert_eq!(m.stack(), [9, 1]);
+        assert_eq!(
+            m.eval_word(&Word::Core(Core::Print)),
+            Err(Error::CapabilityDenied("print".into()))
+        );
+        assert_eq!(
+            m.eval_word(&Word::Unbound("nope".into())),
+            Err(Error::Unknown("nope".into()))
+        );
+    }
+
+    #[test]
+    fn inspection() {
+        let mut m = Machine::default();
+        assert!(
+            m.read_eval("def sq dup mul ; def quad sq sq ; def sq dup dup mul")
+                .is_ok()
+        );
+        assert_eq!(m.words().collect::<Vec<_>>(), ["sq", "quad"]);
+        assert_eq!(
+            m.definition("quad"),
+            Some(&[Word::Unbound("sq".into()), Word::Unbound("sq".into())][..])
+        );
+        assert_eq!(m.definition("dup"), None);
+    }
+
+    #[test]
+    fn undefine() {
+        let mut m = Machine::default();
+        assert!(
+            m.read_eval("def sq dup mul ; def quad [ sq ] call sq ; def f f")
+                .is_ok()
+        );
+        assert_eq!(
+            m.undefine("sq", false),
+            Err(Error::Referenced("sq".into(), "quad".into()))
+        );
+        assert_eq!(m.undefine("f", false), Ok(()));
+        assert_eq!(m.undefine("f", false), Err(Error::Unknown("f".into())));
+        assert_eq!(m.undefine("sq", true), Ok(()));
+        assert_eq!(m.words().collect::<Vec<_>>(), ["quad"]);
+        assert_eq!(m.read_eval("2 quad"), Err(Error::Unknown("sq".into())));
+    }
+
+    #[test]
+    fn reset() {
+        let mut m = Machine::with_profile(Profile::Standard);
+        m.set_edition(Edition::Edition2026);
+        let s = "def sq dup mul ; 2 const two var v [ 1 ] \"s\" mark 3 sq print def open";
+        assert!(m.read_eval(s).is_ok());
+        m.clear_stack();
+        assert!(m.is_empty());
+        m.clear_env();
+        assert_eq!(
+            (m.words().len(), m.lookup("two"), m.defining()),
+            (0, None, None)
+        );
+        assert_eq!(m.read_eval("clear-to-marker"), Err(Error::NoMarker));
+        assert!(m.read_eval("[ 2 ] 1 array 4 print").is_ok());
+        m.reset();
+        let fresh = Machine::with_profile(Profile::Standard);
+        assert_eq!(m.to_source(), fresh.to_source().replace("2025", "2026"));
+        assert_eq!((m.limits(), m.last()), (fresh.limits(), None));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let mut m = Machine::with_edition(Edition::Edition2026);
+        let s = "def sq ( n -- n ) dup mul ; def quad sq sq ; 2 const two [ two sq ] \"s\" 1/2 3 array def open";
+        assert!(m.read_eval(s).is_ok());
+        let json = serde_json::to_string(&m).expect("serializes");
+        assert!(json.contains(r#"{"Core":"dup"}"#));
+        let mut m2 = serde_json::from_str::<Machine>(&json).expect("deserializes");
+        assert_eq!(
+            (&m2, m2.edition(), m2.defining()),
+            (&m, m.edition(), Some("open"))
+        );
+        assert_eq!(m2.to_source(), m.to_source());
+        let s = "1 ; open drop len swap drop swap length rot call";
+        assert!(m2.read_eval(s).is_ok());
+        assert_eq!(m2.stack(), [3, 1, 4]);
+        assert_eq!(m2.read_eval("clear 3 quad"), Ok(()));
+        assert_eq!(m2.stack(), [81]);
+        assert!(serde_json::from_str::<Machine>(&json.replace("dup", "dupe")).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json() {
+        let mut m = Machine::with_edition(Edition::Edition2026);
+        assert!(
+            m.read_eval("def sq ( n -- n ) dup mul ; 2 const two 3 4")
+                .is_ok()
+        );
+        let json: serde_json::Value = serde_json::from_str(&m.to_json()).expect("parses");
+        assert_eq!(json["edition"], "2026");
+        assert_eq!(json["core"][0], "drop");
+        assert_eq!(
+            json["env"],
+            serde_json::json!({ "sq": "( n -- n ) dup mul", "two": "2" })
+        );
+        assert_eq!(json["stack"], serde_json::json!([3, 4]));
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut m = Machine::with_edition(Edition::Edition2026);
+        let s = "def sq ( n -- n ) dup mul ; 2 const two mark [ two sq ] \"s\" -1/2 1.5 \
+                 3 array dup 7 1 rot set 9 print def open";
+        assert!(m.read_eval(s).is_ok());
+        let bytes = m.snapshot();
+        let mut m2 = Machine::with_profile(Profile::Standard);
+        assert_eq!(m2.restore(&bytes), Ok(()));
+        assert_eq!(
+            (&m2, m2.edition(), m2.defining()),
+            (&m, m.edition(), Some("open"))
+        );
+        assert_eq!((m2.to_source(), m2.last()), (m.to_source(), Some(9)));
+        assert_eq!(m2.limits(), &Profile::Standard.limits());
+        let s = "1 ; open drop 1 swap get swap f>i add swap rprint swap length rot call";
+        assert!(m2.read_eval(s).is_ok());
+        assert_eq!(m2.stack(), [8, 1, 4]);
+        assert!(m2.read_eval("clear-to-marker").is_ok());
+        assert!(m2.is_empty());
+        assert!(m.read_eval("1 ; redef open").is_ok());
+        m2.set_redefinition(Redefinition::Strict);
+        assert_eq!(m2.restore(&m.snapshot()), Ok(()));
+        assert_eq!(m2.read_eval("2 ; clear open"), Ok(()));
+        assert_eq!(m2.stack(), [2]);
+        let mut small = Machine::with_profile(Profile::Strict);
+        let mut big = Machine::default();
+        assert!(big.read_eval("100000 array").is_ok());
+        assert!(matches!(
+            small.restore(&big.snapshot()),
+            Err(Error::HeapFull(_))
+        ));
+        assert_eq!(small, Machine::default());
+        assert_eq!(
+            Machine::<u64>::new().restore(&bytes),
+            Err(Error::SnapshotCell)
+        );
+    }
+
+    #[test]
+    fn snapshots() {
+        use std::hash::{BuildHasher, RandomState};
+        let mut m = Machine::default();
+        assert!(m.read_eval("def sq dup mul ; 3").is_ok());
+        let snapshot = m.clone();
+        assert_eq!(m, snapshot);
+        assert!(m.read_eval("sq").is_ok());
+        assert_ne!(m, snapshot);
+        assert_eq!(snapshot.stack(), [3]);
+        let mut other = Machine::default();
+        assert!(
+            other
+                .read_eval("def sq dup mul ; 3 \"ignored\" drop")
+                .is_ok()
+        );
+        assert_eq!(other, snapshot);
+        assert!(other.read_eval("def cube dup sq mul ;").is_ok());
+        assert_ne!(other, snapshot);
+        let hasher = RandomState::new();
+        assert_eq!(hasher.hash_one(&other), hasher.hash_one(other.clone()));
+        assert_ne!(hasher.hash_one(&m), hasher.hash_one(&snapshot));
+    }
+
+    #[test]
+    fn effects() {
+        let mut m = Machine::default();
+        for s in [
+            "def sq ( n -- n*n ) dup mul",
+            "def cube ( n -- n*n*n )",
+            "dup sq mul ;",
+            "def two ( -- n ) ( a -- b ) 2",
+            "( x -- y ) 3 cube",
+        ] {
+            assert!(m.read_eval(s).is_ok(), "{s}");
+        }
+        assert_eq!(m.lookup("sq"), Some("( n -- n*n ) dup mul".into()));
+        assert_eq!(m.lookup("cube"), Some("( n -- n*n*n ) dup sq mul".into()));
+        assert_eq!(m.lookup("two"), Some("( -- n ) 2".into()));
+        assert_eq!(m.state.stack, [27]);
+        let mut m2 = Machine::default();
+        assert!(m2.read_eval_stream(m.to_source().as_bytes()).is_ok());
+        assert_eq!(m2.lookup("sq"), m.lookup("sq"));
+        assert!(m.read_eval("def sq dup mul").is_ok());
+        assert_eq!(m.lookup("sq"), Some("dup mul".into()));
+    }
+
+    #[test]
+    fn consts() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("6 7 mul const answer").is_ok());
+        assert!(m.read_eval("def twice answer answer add").is_ok());
+        assert_eq!(m.lookup("twice"), Some("42 42 add".into()));
+        assert!(m.read_eval("twice [ answer ] call").is_ok());
+        assert_eq!(m.state.stack, [84, 42]);
+        for (s, e) in [
+            ("1 const answer", Error::AlreadyDefined("answer".into())),
+            ("def answer 1", Error::AlreadyDefined("answer".into())),
+            ("1 const twice", Error::AlreadyDefined("twice".into())),
+            ("const", Error::ConstName),
+            ("def c const", Error::ConstReserved),
+        ] {
+            assert_eq!(m.read_eval(s), Err(e));
+        }
+        m.state.stack.clear();
+        assert_eq!(
+            m.read_eval("const none"),
+            Err(Error::Small("const".into(), 1, 0))
+        );
+        let mut m2 = Machine::default();
+        assert!(m2.read_eval_stream(m.to_source().as_bytes()).is_ok());
+        assert_eq!(m2.lookup("answer"), Some("42".into()));
+        assert!(m2.state.stack.is_empty());
+    }
+
+    #[test]
+    fn open_defs() {
+        let mut m = Machine::default();
+        for s in ["def cube", "dup dup", "", "mul mul ; 3 cube"] {
+            assert!(m.read_eval(s).is_ok());
+        }
+        assert_eq!(m.defining(), None);
+        assert_eq!(m.lookup("cube"), Some("dup dup mul mul".into()));
+        assert_eq!(m.state.stack, [27]);
+        assert!(m.read_eval("def bad").is_ok());
+        assert_eq!(m.defining(), Some("bad"));
+        assert_eq!(m.read_eval("1 ]"), Err(Error::Unopened));
+        assert_eq!(m.defining(), None);
+        assert_eq!(m.read_eval(";"), Err(Error::Unbegun));
+        assert!(m.read_eval("def empty").is_ok());
+        assert_eq!(m.read_eval(";"), Err(Error::DefBody));
+    }
+
+    #[test]
+    fn spans() {
+        let mut m = Machine::default();
+        for (s, span) in [
+            ("1 2 [ 3 ] nope", 10..14),
+            ("1 [ 2", 2..5),
+            ("def f 1 nope ; f", 15..16),
+            ("#1_0000_0000_0000_0000", 0..22),
+        ] {
+            let e = m.read_eval_spanned(s).map_err(|e| match e {
+                Error::Span(span, _) => span,
+                e => panic!("{e} isn't spanned"),
+            });
+            assert_eq!(e, Err(span), "{s}");
+        }
+    }
+
+    #[test]
+    fn ended_defs() {
+        let mut m = Machine::default();
+        assert!(
+            m.read_eval("def sq dup mul ; def inc 1 add ; 5 sq inc")
+                .is_ok()
+        );
+        assert_eq!(m.lookup("sq"), Some("dup mul".into()));
+        assert_eq!(m.state.stack, [26]);
+        assert!(m.read_eval("def four 2 sq ;").is_ok());
+        assert_eq!(m.defining(), None);
+        assert_eq!(m.read_eval("def none ; 1"), Err(Error::DefBody));
+        assert_eq!(m.read_eval("1 ; 2"), Err(Error::Unbegun));
+    }
+
+    #[test]
+    fn generators() {
+        let mut m = Machine::default();
+        for (s, xs) in [
+            ("4 iota", &[0, 1, 2, 3][..]),
+            ("0 iota", &[]),
+            ("-2 1 range", &[-2, -1, 0]),
+            ("3 1 range", &[]),
+        ] {
+            assert!(m.read_eval(s).is_ok());
+            assert_eq!(m.state.stack, xs);
+            m.state.stack.clear();
+        }
+        for (s, e) in [
+            ("-1 iota", Error::Negative("iota".into())),
+            (
+                "0 9223372036854775807 range",
+                Error::TooMany("range".into(), 1 << 32),
+            ),
+            (
+                "-9223372036854775807 9223372036854775807 range",
+                Error::TooMany("range".into(), 1 << 32),
+            ),
+        ] {
+            assert_eq!(m.read_eval(s), Err(e));
+            m.state.stack.clear();
+        }
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert!(m.read_eval("1000 iota 23 iota").is_ok());
+        assert_eq!(m.read_eval("2 iota"), Err(Error::StackOverflow(1024)));
+        assert_eq!(m.state.stack.len(), 1024);
+    }
+
+    #[test]
+    fn introspection() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("depth 5 6 depth .s").is_ok());
+        assert_eq!(m.state.stack, [0, 5, 6, 3]);
+        assert!(m.read_eval("mark 7 clear depth").is_ok());
+        assert_eq!(m.state.stack, [0]);
+        assert_eq!(m.read_eval("clear-to-marker"), Err(Error::NoMarker));
+        for (s, xs) in [
+            ("clear sum", &[0][..]),
+            ("clear product", &[1]),
+            ("clear 1 2 3 4 sum", &[10]),
+            ("clear 1 2 3 4 product", &[24]),
+            ("clear 1 2 3 reverse", &[3, 2, 1]),
+            ("clear 9223372036854775807 1 sum", &[i64::MAX]),
+        ] {
+            assert!(m.read_eval(s).is_ok());
+            assert_eq!(m.state.stack, xs);
+        }
+        assert!(m.read_eval("#edition 2026").is_ok());
+        assert_eq!(
+            m.read_eval("2 9223372036854775807 product"),
+            Err(Error::Overflow("product".into()))
+        );
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert_eq!(m.read_eval(".s"), Err(Error::CapabilityDenied(".s".into())));
+    }
+
+    #[test]
+    fn conditionals() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("def magnitude dup neg? [ -1 mul ] if").is_ok());
+        assert!(
+            m.read_eval("def sign dup [ neg? [ -1 ] [ 1 ] if-else ] [ drop 0 ] if-else")
+                .is_ok()
+        );
+        assert!(
+            m.read_eval("-3 magnitude 4 magnitude -5 sign 0 sign 6 sign")
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [3, 4, -1, 0, 1]);
+        let mut m = Machine::default();
+        assert_eq!(m.read_eval("1 2 if"), Err(Error::UnknownQuote(2)));
+        assert_eq!(m.read_eval("[ ] 7 if-else"), Err(Error::UnknownQuote(7)));
+    }
+
+    #[test]
+    fn loops() {
+        let mut m = Machine::with_profile(Profile::Standard);
+        assert!(m.read_eval("1 10 [ 2 mul ] times").is_ok());
+        assert_eq!(m.state.stack, [1024]);
+        assert!(m.read_eval("drop 4 [ dup ] [ 1 swap sub ] while").is_ok());
+        assert_eq!(m.state.stack, [0]);
+        assert!(
+            m.read_eval("def countdown [ dup pos? ] [ dup print 1 swap sub ] while")
+                .is_ok()
+        );
+        assert!(m.read_eval("drop 3 countdown").is_ok());
+        assert_eq!(m.last(), Some(1));
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert_eq!(
+            m.read_eval("[ ] [ ] while"),
+            Err(Error::Small("while".into(), 1, 0))
+        );
+        assert_eq!(
+            m.read_eval("-1 [ ] times"),
+            Err(Error::Negative("times".into()))
+        );
+        assert_eq!(
+            m.read_eval("drop drop 1 [ drop drop ] times"),
+            Err(Error::Small("drop".into(), 1, 0))
+        );
+        assert!(matches!(
+            m.read_eval("[ 1 ] [ ] while"),
+            Err(Error::BudgetExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn recursion() {
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert!(
+            m.read_eval("def total dup [ dup 1 swap sub total add ] if")
+                .is_ok()
+        );
+        assert!(m.read_eval("10 total").is_ok());
+        assert_eq!(m.state.stack, [55]);
+        assert_eq!(m.read_eval("100 total"), Err(Error::RecursionLimit(64)));
+        assert!(m.read_eval("def forever forever").is_ok());
+        assert!(matches!(
+            m.read_eval("forever"),
+            Err(Error::BudgetExceeded(_))
+        ));
+    }
+    #[test]
+    fn tail_calls() {
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert!(m.read_eval("def down dup [ 1 swap sub down ] if").is_ok());
+        assert!(m.read_eval("100000 down").is_ok());
+        assert_eq!(m.state.stack, [0]);
+        assert!(
+            m.read_eval("def count [ dup pos? ] [ 1 swap sub ] while")
+                .is_ok()
+        );
+        assert!(
+            m.read_eval("drop 1000 count drop 10000 [ 1 count drop ] times")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("def w0 1").is_ok());
+        for i in 1..100_000 {
+            assert!(m.read_eval(&format!("def w{i} w{} 1", i - 1)).is_ok());
+        }
+        assert!(m.read_eval("w99999").is_ok());
+        assert_eq!(m.state.stack.len(), 100_000);
+        m.state.stack.clear();
+        m.set_limits(Profile::Standard.limits());
+        assert_eq!(m.read_eval("w99999"), Err(Error::RecursionLimit(1 << 10)));
+        for s in ["[ 1 ] [ ] while", "9223372036854775807 [ ] times"] {
+            let mut m = Machine::with_profile(Profile::Strict);
+            assert_eq!(m.read_eval(s), Err(Error::BudgetExceeded(1 << 20)));
+        }
+    }
+
+    #[test]
+    fn cells() {
+        let mut m = Machine::<i128>::new();
+        assert!(
+            m.read_eval("9223372036854775807 dup mul [ 0 1 div ] catch")
+                .is_ok()
+        );
+        assert_eq!(
+            m.state.stack,
+            [85_070_591_730_234_615_847_396_907_784_232_501_249, -11]
+        );
+        let mut m = Machine::<u64>::new();
+        assert!(
+            m.read_eval("1 2 sub 2 1 sub -1 1 add 2.5 f>i [ 7 throw ] catch")
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [1, 0, u64::MAX, 2, 7]);
+        assert!(m.read_eval("#edition 2026").is_ok());
+        assert_eq!(m.read_eval("2 1 sub"), Err(Error::Overflow("sub".into())));
+        assert_eq!(m.read_eval("5 neg?"), Ok(()));
+        assert_eq!(m.state.stack.last(), Some(&0));
+    }
+
+    #[test]
+    fn floats() {
+        let mut m = Machine::default();
+        for (s, x) in [
+            ("0.5 1.5 fadd", 2.0),
+            ("2.0 1.0 fdiv", 0.5),
+            ("1.5e-3 1000.0 fmul", 1.5),
+            ("1.0 -2.5e1 fsub", -26.0),
+            ("3 i>f", 3.0),
+        ] {
+            assert!(m.read_eval(s).is_ok());
+            assert_eq!(m.state.stack.pop().as_ref().map(float), Some(x));
+        }
+        assert!(m.read_eval("7 i>f 2 i>f swap fdiv f>i -1e300 f>i").is_ok());
+        assert_eq!(m.state.stack, [3, i64::MIN]);
+        assert_ne!(Word::<i64>::Float(0.0), Word::Float(-0.0));
+    }
+
+    #[test]
+    fn strings() {
+        let mut m = Machine::default();
+        assert!(
+            m.read_eval(r#""héllo" " \"world\"\n" concat dup length"#)
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [2, 14]);
+        assert_eq!(
+            m.state.strings.last().map(AsRef::as_ref),
+            Some("héllo \"world\"\n")
+        );
+        assert_eq!(m.read_eval("7 length"), Err(Error::UnknownString(7)));
+        let sink = Sink::default();
+        m.set_output(sink.clone());
+        assert_eq!(
+            m.read_eval(r#"print print sprint "hi" peek sprint 2 print"#),
+            Ok(())
+        );
+        assert_eq!(sink.written(), "7\n14\nhéllo \"world\"\n\n3\nhi\n2\n");
+        // Numbers print as numbers, whatever strings there are.
+        assert_eq!(
+            m.read_eval(r#""hi" drop -9223372036854775808 print"#),
+            Ok(())
+        );
+        assert!(sink.written().ends_with("\n-9223372036854775808\n"));
+        assert!(m.read_eval(r#"def greet "hi" swap concat"#).is_ok());
+        assert_eq!(m.lookup("greet").as_deref(), Some(r#""hi" swap concat"#));
+        let mut m2 = Machine::default();
+        assert!(m2.read_eval_stream(m.to_source().as_bytes()).is_ok());
+        assert_eq!(m2.state.strings, m.state.strings);
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert_eq!(
+            m.read_eval(r#""x" sprint"#),
+            Err(Error::CapabilityDenied("sprint".into()))
+        );
+    }
+
+    #[test]
+    fn chars() {
+        let mut m = Machine::default();
+        assert!(m.read_eval(r"'a' 'é' '\n' '\''").is_ok());
+        assert_eq!(m.state.stack, [97, 233, 10, 39]);
+        assert!(m.read_eval("emit").is_ok());
+        assert_eq!(m.state.last, Some(39));
+        assert_eq!(m.read_eval("55296 emit"), Err(Error::NotChar(55296)));
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert_eq!(
+            m.read_eval("'x' emit"),
+            Err(Error::CapabilityDenied("emit".into()))
+        );
+    }
+
+    #[test]
+    fn ratios() {
+        let mut m = Machine::default();
+        for (s, xs) in [
+            ("1/3 1/6 radd dup num swap den", [1, 2]),
+            ("3/2 2 i>r rdiv dup num swap den", [4, 3]),
+            ("-7/2 floor -4/6 num", [-4, -2]),
+        ] {
+            assert!(m.read_eval(s).is_ok());
+            assert_eq!(m.state.stack, xs);
+            m.state.stack.clear();
+        }
+        for (s, e) in [
+            ("0/5 1/2 rdiv", Error::NotNonzero("rdiv".into())),
+            ("1/0", Error::NotNonzero("1/0".into())),
+            (
+                "9223372036854775807/1 dup radd",
+                Error::Overflow("radd".into()),
+            ),
+            ("99 num", Error::UnknownRatio(99)),
+        ] {
+            assert_eq!(m.read_eval(s), Err(e));
+            m.state.stack.clear();
+        }
+        let w = Word::Ratio(i64::MIN, -1);
+        assert_eq!(m.eval_word(&w), Err(Error::Overflow(w.to_string())));
+        assert_eq!(
+            m.eval_word(&Word::Ratio(1, 0)),
+            Err(Error::NotNonzero("1/0".into()))
+        );
+        assert!(m.define("half", vec![Word::Ratio(2, 0)]).is_ok());
+        assert_eq!(m.read_eval("half"), Err(Error::NotNonzero("2/0".into())));
+        assert!(m.undefine("half", false).is_ok());
+        let mut m2 = Machine::default();
+        assert!(m2.read_eval_stream(m.to_source().as_bytes()).is_ok());
+        assert_eq!(m2.state.ratios, m.state.ratios);
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert_eq!(
+            m.read_eval("1/2 rprint"),
+            Err(Error::CapabilityDenied("rprint".into()))
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let mut m = Machine::default();
+        assert!(
+            m.read_eval("3 array 7 1 0 set 1 0 get 0 0 get 0 len")
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [0, 7, 0, 3]);
+        m.state.stack.clear();
+        for (s, e) in [
+            ("-1 array", Error::Negative("array".into())),
+            ("3 0 get", Error::OutOfBounds(3, 3)),
+            ("1 -1 0 set", Error::OutOfBounds(-1, 3)),
+            ("99 len", Error::UnknownArray(99)),
+        ] {
+            assert_eq!(m.read_eval(s), Err(e));
+            m.state.stack.clear();
+        }
+        let mut m2 = Machine::default();
+        assert!(m2.read_eval_stream(m.to_source().as_bytes()).is_ok());
+        assert_eq!(m2.state.heap, m.state.heap);
+        let mut m = Machine::default();
+        assert!(
+            m.read_eval("1000000000000 array 999999999999 over get swap len")
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [0, 1_000_000_000_000]);
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert!(m.read_eval("65536 array drop").is_ok());
+        assert_eq!(m.read_eval("1 array"), Err(Error::HeapFull(1 << 16)));
+    }
+
+    #[test]
+    fn vars() {
+        let mut m = Machine::default();
+        assert!(
+            m.read_eval("var n var m 5 n ! n @ 1 add n ! n @ m @")
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [6, 0]);
+        for (s, e) in [
+            ("var", Error::VarName),
+            ("var var", Error::VarReserved),
+            ("var 1", Error::NumNotName("1".into())),
+            ("def v var x", Error::VarReserved),
+            ("0 array @", Error::OutOfBounds(0, 0)),
+        ] {
+            assert_eq!(m.read_eval(s), Err(e));
+        }
+        let mut m2 = Machine::default();
+        assert!(m2.read_eval_stream(m.to_source().as_bytes()).is_ok());
+        assert!(m2.read_eval("n @").is_ok());
+        assert_eq!(m2.state.stack.last(), Some(&6));
+    }
+
+    #[cfg(feature = "bignum")]
+    #[test]
+    fn bignums() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("18446744073709551616n dup bmul").is_ok());
+        let h = m.state.stack.pop().expect("a bignum");
+        assert_eq!(
+            big(&m.state, &h).to_string(),
+            "340282366920938463463374607431768211456"
+        );
+        assert!(
+            m.read_eval("2 big -7 big bmod b>i 3 big -7 big bdiv b>i")
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [1, -2]);
+        m.state.stack.clear();
+        for (s, e) in [
+            ("0 big 1 big bdiv", Error::NotNonzero("bdiv".into())),
+            ("18446744073709551616n b>i", Error::Overflow("b>i".into())),
+            ("99 bprint", Error::UnknownBig(99)),
+        ] {
+            assert_eq!(m.read_eval(s), Err(e));
+            m.state.stack.clear();
+        }
+        let mut m2 = Machine::default();
+        assert!(m2.read_eval_stream(m.to_source().as_bytes()).is_ok());
+        assert_eq!(m2.state.bigs, m.state.bigs);
+    }
+
+    #[cfg(feature = "bignum")]
+    #[test]
+    fn big_cells() {
+        let mut m = Machine::<BigInt>::new();
+        assert!(
+            m.read_eval("99999999999999999999999 1 add 2 100 pow 2 1 sub")
+                .is_ok()
+        );
+        let xs = [
+            "100000000000000000000000",
+            "1267650600228229401496703205376",
+            "-1",
+        ];
+        assert_eq!(m.state.stack, xs.map(|x| x.parse::<BigInt>().unwrap()));
+        let mut n = Machine::<BigInt>::new();
+        assert!(n.restore(&m.snapshot()).is_ok());
+        assert_eq!(n.state.stack, m.state.stack);
+        assert!(matches!(
+            Machine::<i64>::new().read_eval("99999999999999999999999"),
+            Err(Error::Parsing(_))
+        ));
+    }
+
+    #[test]
+    fn logic() {
+        let mut m = Machine::default();
+        for (s, x) in [
+            ("true false and", 0),
+            ("true 7 and", 1),
+            ("false -3 or", 1),
+            ("false false or", 0),
+            ("5 not", 0),
+            ("0 not", 1),
+            ("2 true xor", 0),
+            ("2 false xor", 1),
+            ("3 pos? 4 neg? not and", 1),
+            ("2 2 eq?", 1),
+            ("2 3 neq?", 1),
+            ("2 3 lt?", 0),
+            ("2 3 gt?", 1),
+            ("3 3 le?", 1),
+            ("-1 3 ge?", 1),
+            ("-3 abs -2 neg min", 2),
+            ("-3 7 max", 7),
+            ("-2 3 pow", -8),
+            ("1 -9223372036854775807 sub abs", i64::MAX),
+            ("1 -9223372036854775807 sub neg", i64::MAX),
+            ("1 10000000000 pow", 1),
+            ("10 100 pow", i64::MAX),
+            ("-2 63 pow", i64::MIN),
+            ("-2 65 pow", i64::MIN),
+            ("-18 12 gcd", 6),
+            ("99 isqrt", 9),
+            ("1024 log2 1023 log2 sub", -1),
+            ("-1 popcount 255 popcount sub", -56),
+            ("0 0 gcd", 0),
+            ("0 1 -9223372036854775807 sub gcd", i64::MAX),
+            ("12 10 band", 8),
+            ("12 10 bor", 14),
+            ("12 10 bxor", 6),
+            ("0 bnot", -1),
+            ("1 65 shl", 2),
+            ("-16 2 shr", -4),
+        ] {
+            assert!(m.read_eval(s).is_ok());
+            assert_eq!(m.state.stack.pop(), Some(x));
+        }
+        assert!(m.read_eval("0 1 2 true select true [ 9 ] if").is_ok());
+        assert_eq!(m.state.stack, [0, 1, 9]);
+    }
+
+    #[test]
+    fn exceptions() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("1 2 [ 3 drop ] catch").is_ok());
+        assert_eq!(m.state.stack, [1, 2, 0]);
+        assert!(m.read_eval("[ drop drop 4 7 throw 5 ] catch").is_ok());
+        assert_eq!(m.state.stack, [1, 2, 0, 7]);
+        assert!(
+            m.read_eval("drop drop drop drop 1 [ 0 swap div ] catch")
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [1, Error::NotNonzero("div".into()).code()]);
+        assert!(
+            m.read_eval("drop drop [ drop ] catch [ 9 throw ] catch")
+                .is_ok()
+        );
+        assert_eq!(m.state.stack, [Error::Small("drop".into(), 1, 0).code(), 9]);
+        assert_eq!(m.read_eval("3 throw"), Err(Error::Thrown(3)));
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert!(m.read_eval("def forever [ forever ] call").is_ok());
+        assert!(matches!(
+            m.read_eval("[ forever ] catch"),
+            Err(Error::BudgetExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn exits() {
+        let mut m = Machine::default();
+        assert!(m.read_eval("def f 1 exit 2").is_ok());
+        assert!(m.read_eval("def g f 3 dup [ exit ] if 4").is_ok());
+        assert!(m.read_eval("def h 0 g 5 [ 6 exit ] call").is_ok());
+        assert!(m.read_eval("h 7").is_ok());
+        assert_eq!(m.state.stack, [0, 1, 3, 5, 6, 7]);
+        assert!(
+            m.read_eval("def loop 10 [ dup [ exit ] if ] times 8")
+                .is_ok()
+        );
+        assert!(m.read_eval("loop [ 9 exit 10 ] call 11 exit 12").is_ok());
+        assert_eq!(m.state.stack, [0, 1, 3, 5, 6, 7, 9, 11, 12]);
+    }
+
+    /// Words whose `check` implies they'll evaluate successfully: those executing quotations can
+    /// fail partway through (or never finish), `throw` always fails, & `read` fails at the end of
+    /// input (which, in tests, is standard input).
+    fn uncalled() -> impl Strategy<Value = Word> {
+        word().prop_filter(
+            "quotations may not finish, throw always fails, & read needs input",
+            |w| {
+                !matches!(
+                    w,
+                    Word::Core(
+                        Core::Call
+                            | Core::If
+                            | Core::IfElse
+                            | Core::Times
+                            | Core::While
+                            | Core::Throw
+                            | Core::Catch
+                            | Core::Read
+                    )
+                )
+            },
+        )
+    }
+
+    /// Words that don't `exit`, which in a definition would skip the rest of it.
+    fn unexited() -> impl Strategy<Value = Word> {
+        word().prop_filter("exit ends a definition early", |w| {
+            !w.to_string().split(' ').any(|t| t == "exit")
+        })
+    }
+
+    /// Evaluate words in turn, without reading them, within the machine's budget.
+    fn eval(m: &mut Machine, ws: &[Word]) -> Result<(), Error> {
+        m.state.fuel = m.state.limits.fuel;
+        ws.iter().try_for_each(|w| m.eval(w))
+    }
 
     fn fib_machine(n: i64) -> Result<i64, Error> {
         if n >= 93 {
-            Err(Error::Bad)
+            Err(Error::Overflow(format!("fib_{n}")))
         } else {
             let mut m = Machine::default();
             (0..=n)
@@ -210,7 +4827,10 @@ mod tests {
             m.read_eval("def fib_1 1")?;
             m.read_eval("def fib_0 1")?;
             m.read_eval(&format!("fib_{n}"))?;
-            m.stack.pop().ok_or(Error::Bad)
+            m.state
+                .stack
+                .pop()
+                .ok_or_else(|| Error::Small(format!("fib_{n}"), 1, 0))
         }
     }
 
@@ -227,28 +4847,33 @@ mod tests {
             }
         }
         #[test]
-        fn check_implies_eval(ws in prop::collection::vec(word(), 0..64)) {
+        fn check_implies_eval(ws in prop::collection::vec(uncalled(), 0..64)) {
             let mut m = Machine::default();
             for w in ws {
-                prop_assert_eq!(check(&m.env, &m.stack, &w).is_ok(), m.eval(&w).is_ok());
+                prop_assert_eq!(check(&m.env, &m.state, &w).is_ok(), m.eval(&w).is_ok());
             }
         }
         #[test]
-        fn check_implies_read_eval(ws in prop::collection::vec(word(), 0..64)) {
+        fn check_implies_read_eval(ws in prop::collection::vec(uncalled(), 0..64)) {
             let mut m = Machine::default();
             for w in ws {
-                prop_assert_eq!(check(&m.env, &m.stack, &w).is_ok(), m.read_eval(&w.to_string()).is_ok());
+                prop_assert_eq!(check(&m.env, &m.state, &w).is_ok(), m.read_eval(&w.to_string()).is_ok());
             }
         }
         #[test]
-        fn def_adds_to_env(ws in prop::collection::vec(r"\S+", 0..64), n in r"custom_name_\S+") {
+        fn def_adds_to_env(
+            ws in prop::collection::vec(r"\S+".prop_filter("ends a definition", |w| w != ";"), 0..64),
+            n in r"custom_name_\S+",
+        ) {
             let mut m = Machine::default();
             let d = ws.join(" ");
             let s = format!("def {n} {d}");
             let r = m.read_eval(&s);
             prop_assert!(
                 (ws.is_empty()
-                    || ws.contains(&n)
+                    || ws
+                        .iter()
+                        .any(|w| ["[", "]", "\\", "("].contains(&&**w) || w.contains('"'))
                     || n.parse::<i64>().is_ok()
                     || [
                         "def", "pop", "swap", "dup", "add", "sub", "mul", "div", "mod", "zero?", "print",
@@ -256,15 +4881,16 @@ mod tests {
                     .contains(&&*n))
                     || (r.is_ok()
                         && m.lookup(&n).is_some()
-                        && m.env.contains_key(&LeanString::from(n.clone()))
+                        && m.is_defined(&n)
                         && m.to_string().contains(&n))
             );
-            prop_assert!(m.stack.is_empty());
+            prop_assert!(m.state.stack.is_empty());
         }
         #[test]
-        fn custom_ok(ws in prop::collection::vec(word(), 1..64), n in r"custom_word_\S+") {
-            let mut m1 = Machine::default();
-            let r1 = ws.iter().map(|w| m1.eval(w)).collect::<Result<Vec<()>, _>>();
+        fn custom_ok(ws in prop::collection::vec(unexited(), 1..64), n in r"custom_word_\S+") {
+            // Quotations might loop, so budget for that.
+            let mut m1 = Machine::with_profile(Profile::Strict);
+            let r1 = eval(&mut m1, &ws);
             let s = format!(
                 "def {n} {}",
                 ws.iter()
@@ -272,9 +4898,9 @@ mod tests {
                     .collect::<Vec<_>>()
                     .join(" ")
             );
-            let mut m2 = Machine::default();
+            let mut m2 = Machine::with_profile(Profile::Strict);
             prop_assert!(m2.read_eval(&s).is_ok());
-            prop_assert_eq!(m2.eval(&Word::Custom(n.into())).is_ok(), r1.is_ok());
+            prop_assert_eq!(eval(&mut m2, &[Word::Unbound(n.into())]).is_ok(), r1.is_ok());
         }
         #[test]
         fn fib(n in 0..16i64) {
@@ -5,14 +5,17 @@
 #![deny(missing_docs)]
 #![deny(unsafe_code)]
 use clap::{Parser, Subcommand, ValueEnum};
-use documented::DocumentedFields;
-use drsm::{Core, Machine};
-use rustyline::{Config, DefaultEditor, EditMode, error::ReadlineError};
+use drsm::{
+    Edition, Machine, Profile, Stats, paths,
+    repl::{Repl, Settings},
+};
+use rustyline::{EditMode, error::ReadlineError};
 use std::{
-    fmt,
-    fs::File,
-    io::{self, BufRead, BufReader},
-    path::PathBuf,
+    fmt, fs, io,
+    num::NonZero,
+    path::{Path, PathBuf},
+    str,
+    time::{Duration, Instant},
 };
 
 #[derive(Parser, Debug)]
@@ -26,11 +29,130 @@ struct Args {
 enum Command {
     #[command(about = "run an interactive REPL")]
     Repl {
-        #[arg(short, long, default_value_t = Mode::Vi)]
-        mode: Mode,
+        #[command(flatten)]
+        settings: SettingsArgs,
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "the configuration file [default: config.toml in the config directory]"
+        )]
+        config: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "a file to run at startup [default: ~/.drsmrc, if there is one]"
+        )]
+        rc: Option<PathBuf>,
+        #[arg(long, conflicts_with = "rc", help = "don't run a file at startup")]
+        no_rc: bool,
+        #[command(flatten)]
+        machine: MachineArgs,
     },
     #[command(about = "execute the commands in a file")]
-    Run { file: PathBuf },
+    Run {
+        #[arg(help = "the file to run, or - for standard input")]
+        file: PathBuf,
+        #[command(flatten)]
+        machine: MachineArgs,
+        #[arg(
+            long,
+            value_enum,
+            help = "print a resource report to stderr after running"
+        )]
+        report: Option<Report>,
+        #[arg(
+            long,
+            value_name = "N",
+            help = "save the machine's state after every N statements"
+        )]
+        checkpoint_every: Option<NonZero<usize>>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "where to save checkpoints [default: in the state directory]"
+        )]
+        state: Option<PathBuf>,
+        #[arg(long, help = "resume from the last checkpoint")]
+        resume: bool,
+    },
+    #[command(about = "translate a definition from a file into Rust or WebAssembly text")]
+    #[command(group(clap::ArgGroup::new("target").required(true).args(["rust", "wat"])))]
+    Export {
+        file: PathBuf,
+        word: String,
+        #[arg(long, help = "emit a Rust function")]
+        rust: bool,
+        #[arg(long, help = "emit a WebAssembly text module")]
+        wat: bool,
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+struct MachineArgs {
+    #[arg(short, long, default_value_t = Edition::default())]
+    edition: Edition,
+    #[arg(
+        short,
+        long,
+        default_value_t = Profile::default(),
+        help = "sandbox profile bundling resource limits"
+    )]
+    profile: Profile,
+}
+
+impl MachineArgs {
+    fn build(&self) -> Machine {
+        let mut m = Machine::with_edition(self.edition);
+        m.set_limits(self.profile.limits());
+        m
+    }
+}
+
+/// The REPL's settings given on the command line, overriding the configuration file's.
+#[derive(Debug, clap::Args)]
+struct SettingsArgs {
+    #[arg(short, long, help = "key bindings [default: vi]")]
+    mode: Option<Mode>,
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        value_name = "BOOL",
+        help = "show the top of the stack after each line (toggled by `?autoprint`)"
+    )]
+    autoprint: Option<bool>,
+    #[arg(long, help = "what to prompt for lines with [default: \">  \"]")]
+    prompt: Option<String>,
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        value_name = "BOOL",
+        help = "color input as it's typed [default: true]"
+    )]
+    color: Option<bool>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "the history file [default: history.txt in the state directory]"
+    )]
+    history: Option<PathBuf>,
+}
+
+impl SettingsArgs {
+    fn settings(&self) -> Settings {
+        Settings {
+            edit_mode: self.mode.map(Into::into),
+            prompt: self.prompt.clone(),
+            color: self.color,
+            autoprint: self.autoprint,
+            history: self.history.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
@@ -57,6 +179,31 @@ impl From<Mode> for EditMode {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Report {
+    Text,
+    Json,
+}
+
+impl Report {
+    fn render(self, stats: &Stats, elapsed: Duration) -> String {
+        match self {
+            Self::Text => format!(
+                "words executed:    {}\nmax stack depth:   {}\ndefinitions added: {}\nfuel consumed:     {}\nelapsed:           {elapsed:?}",
+                stats.words_executed, stats.max_stack_depth, stats.defs_added, stats.fuel_consumed
+            ),
+            Self::Json => format!(
+                r#"{{"words_executed":{},"max_stack_depth":{},"defs_added":{},"fuel_consumed":{},"elapsed_secs":{}}}"#,
+                stats.words_executed,
+                stats.max_stack_depth,
+                stats.defs_added,
+                stats.fuel_consumed,
+                elapsed.as_secs_f64()
+            ),
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 enum Error {
     #[error("{0}")]
@@ -67,22 +214,101 @@ enum Error {
     Readline(#[from] ReadlineError),
 }
 
-static REPL_COMMANDS: &str = "
-Commands:
-    ?           =>  show these commands.
-    ?show       =>  show machine's environment & stack.
-    ?lookup <w> =>  look up word <w> in the environment.
-    ?quit       =>  quit the REPL.
-";
+/// Save the machine to `path`, recording that the run should resume from line `next`.
+///
+/// The file is written alongside & then renamed over the old one, so a crash mid-write can't lose
+/// the previous checkpoint.
+fn checkpoint(m: &Machine, next: usize, path: &Path) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut saved = format!("{next}\n").into_bytes();
+    saved.extend(m.snapshot());
+    fs::write(&tmp, saved)?;
+    fs::rename(tmp, path)
+}
 
-fn main() -> Result<(), Error> {
-    let args = Args::parse();
-    match args.command {
-        Command::Repl { mode } => {
-            let mut r =
-                DefaultEditor::with_config(Config::builder().edit_mode(mode.into()).build())?;
-            println!(
-                r"
+/// Run `file`, saving a checkpoint to `state` after every `every` statements (if given), &
+/// removing it once the run finishes; with `resume`, pick up from the checkpoint.
+fn run_checkpointed(
+    m: &mut Machine,
+    file: &Path,
+    every: Option<NonZero<usize>>,
+    state: &Path,
+    resume: bool,
+) -> Result<(), Error> {
+    let from = if resume { restore(m, state)? } else { 0 };
+    let every = every.map_or(0, NonZero::get);
+    let mut count = 0;
+    m.read_eval_path_resumable(file, from, |m, next| {
+        count += 1;
+        if every > 0 && count % every == 0 {
+            checkpoint(m, next, state)
+                .map_err(|e| drsm::Error::Io(format!("{}: {e}", state.display())))?;
+        }
+        Ok(())
+    })?;
+    if state.exists() {
+        fs::remove_file(state)?;
+    }
+    Ok(())
+}
+
+/// Restore the machine from a checkpoint at `path`, returning the line from which to resume.
+fn restore(m: &mut Machine, path: &Path) -> Result<usize, Error> {
+    let saved = fs::read(path)?;
+    let (next, snapshot) = saved
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or((&saved[..], &[][..]), |i| (&saved[..i], &saved[i + 1..]));
+    let next = str::from_utf8(next)
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: not a checkpoint", path.display()),
+            )
+        })?;
+    m.restore(snapshot)?;
+    Ok(next)
+}
+
+/// Run the rc file given, or else the user's (if there is one), reporting anything that goes wrong
+/// rather than failing.
+fn run_rc(m: &mut Machine, rc: Option<PathBuf>) {
+    let Some(rc) = rc.or_else(|| paths::rc_file().ok().filter(|f| f.exists())) else {
+        return;
+    };
+    if let Err(e) = m.read_eval_path(&rc) {
+        eprintln!("{e}");
+    }
+}
+
+/// Run an interactive REPL on the given machine, set up by the configuration file (if there is
+/// one) but for the settings given on the command line.
+fn repl(args: &SettingsArgs, config: Option<PathBuf>, machine: Machine) -> Result<(), Error> {
+    let file = match config.map_or_else(paths::config_file, Ok) {
+        Ok(f) => Settings::load(&f)?,
+        Err(e) => {
+            eprintln!("No configuration file: {e}.");
+            Settings::default()
+        }
+    };
+    let mut settings = file.overridden_by(args.settings());
+    let mode = *settings.edit_mode.get_or_insert(EditMode::Vi);
+    let mode = if mode == EditMode::Vi {
+        Mode::Vi
+    } else {
+        Mode::Emacs
+    };
+    if settings.history.is_none() {
+        match paths::history_file() {
+            Ok(h) => settings.history = Some(h),
+            Err(e) => eprintln!("History won't be saved: {e}."),
+        }
+    }
+    let mut repl = Repl::new(machine).settings(&settings);
+    println!(
+        r"
     ____  ____  _____ __  ___
    / __ \/ __ \/ ___//  |/  /
   / / / / /_/ /\__ \/ /|_/ /
@@ -90,57 +316,73 @@ fn main() -> Result<(), Error> {
 /_____/_/ |_|/____/_/  /_/
 
 Dylan's Rusty Stack Machine
+{}
+Line-editing is enabled, with {mode}-style key bindings (chosen at startup via the `-m/--mode` option or the configuration file, or with `?mode`).
+",
+        repl.help()
+    );
+    Ok(repl.run()?)
+}
 
-{REPL_COMMANDS}
-
-Line-editing is enabled, with {mode}-style key bindings (chosen at startup via the `-m/--mode` option).
-"
-            );
-            if r.load_history("history.txt").is_err() {
-                eprintln!("No previous history.");
+fn main() -> Result<(), Error> {
+    let args = Args::parse();
+    match args.command {
+        Command::Repl {
+            settings,
+            config,
+            rc,
+            no_rc,
+            machine,
+        } => {
+            let mut m = machine.build();
+            if !no_rc {
+                run_rc(&mut m, rc);
             }
-            let mut m = Machine::default();
-            loop {
-                match r.readline(">  ") {
-                    Ok(l) if l == "?" => println!("{REPL_COMMANDS}"),
-                    Ok(l) if l == "?show" => println!("{m}"),
-                    Ok(l) if l == "?quit" => {
-                        println!("Bye!");
-                        break;
-                    }
-                    Ok(l) if l.starts_with("?lookup") => {
-                        if let Some(w) = l.split_ascii_whitespace().nth(1) {
-                            match (Core::get_field_docs(w), m.lookup(w)) {
-                                (Ok(d), _) => println!("`{w}` is a core word: {d}"),
-                                (_, Some(d)) => println!("`{w}` is defined as `{d}`"),
-                                (_, None) => eprintln!("`{w}` is not defined in the environment."),
-                            }
-                        } else {
-                            eprintln!("?lookup requires a word to look up.");
-                        }
-                    }
-                    Ok(l) => {
-                        r.add_history_entry(&l)?;
-                        match m.read_eval(&l) {
-                            Ok(()) => {}
-                            Err(e) => eprintln!("{e}"),
-                        }
-                    }
-                    Err(ReadlineError::Eof) => {
-                        println!("^D");
-                        break;
-                    }
-                    Err(ReadlineError::Interrupted) => println!("^C"),
-                    Err(e) => eprintln!("Error: {e}"),
+            repl(&settings, config, m)?;
+        }
+        Command::Run {
+            file,
+            machine,
+            report,
+            checkpoint_every,
+            state,
+            resume,
+        } => {
+            let mut m = machine.build();
+            let start = Instant::now();
+            let r = if checkpoint_every.is_none() && !resume {
+                if file == Path::new("-") {
+                    m.read_eval_stream(io::stdin().lock())
+                } else {
+                    m.read_eval_path(file)
                 }
+                .map_err(Error::from)
+            } else {
+                let state = state.map_or_else(paths::machine_state_file, Ok)?;
+                run_checkpointed(&mut m, &file, checkpoint_every, &state, resume)
+            };
+            if let Some(report) = report {
+                eprintln!("{}", report.render(m.stats(), start.elapsed()));
             }
-            r.save_history("history.txt")?;
+            r?;
         }
-        Command::Run { file } => {
-            let mut m = Machine::default();
-            for line in BufReader::new(File::open(file)?).lines() {
-                m.read_eval(&line?)?;
-            }
+        Command::Export {
+            file,
+            word,
+            rust,
+            machine,
+            ..
+        } => {
+            let mut m = machine.build();
+            m.read_eval_path(file)?;
+            print!(
+                "{}",
+                if rust {
+                    m.to_rust(&word)
+                } else {
+                    m.to_wat(&word)
+                }?
+            );
         }
     }
     Ok(())
@@ -150,6 +392,77 @@ Line-editing is enabled, with {mode}-style key bindings (chosen at startup via t
 mod tests {
     use super::*;
 
+    #[test]
+    fn settings() {
+        let args = [
+            "drsm",
+            "repl",
+            "-m",
+            "emacs",
+            "--autoprint",
+            "--color=false",
+        ];
+        let Command::Repl { settings, .. } = Args::parse_from(args).command else {
+            panic!("should parse as `repl`");
+        };
+        let s = settings.settings();
+        assert_eq!(s.edit_mode, Some(EditMode::Emacs));
+        assert_eq!(
+            (s.autoprint, s.color, s.prompt),
+            (Some(true), Some(false), None)
+        );
+        let Command::Repl { settings, .. } = Args::parse_from(["drsm", "repl"]).command else {
+            panic!("should parse as `repl`");
+        };
+        assert_eq!(settings.settings(), Settings::default());
+    }
+    #[test]
+    fn rc() {
+        let rc = std::env::temp_dir().join(format!("drsm-rc-{}", std::process::id()));
+        fs::write(&rc, "def sq ( n -- n )\n  dup mul ;\n1 2\nnope\n3").expect("rc");
+        let mut m = Machine::default();
+        run_rc(&mut m, Some(rc.clone()));
+        assert_eq!(m.lookup("sq").as_deref(), Some("( n -- n ) dup mul"));
+        assert_eq!(m.pop(), Some(2));
+        fs::remove_file(&rc).expect("cleanup");
+        let args = ["drsm", "repl", "--rc", "x", "--no-rc"];
+        assert!(Args::try_parse_from(args).is_err());
+    }
+    #[test]
+    fn resume() {
+        let dir = std::env::temp_dir();
+        let file = dir.join(format!("drsm-resume-{}.drsm", std::process::id()));
+        let state = file.with_extension("state");
+        let start = "#edition 2026\n7 print 1 mark 2 3\ndef sq ( n -- n )\ndup\n";
+        fs::write(&file, start).expect("file");
+        let mut m = Machine::default();
+        m.set_output(io::sink());
+        let every = NonZero::new(1);
+        let r = run_checkpointed(&mut m, &file, every, &state, false);
+        assert!(matches!(r, Err(Error::Execution(drsm::Error::At(_, 4, _)))));
+        fs::write(&file, format!("{start}mul ;\nsq clear-to-marker last\n")).expect("file");
+        let mut m = Machine::default();
+        assert!(run_checkpointed(&mut m, &file, every, &state, true).is_ok());
+        assert_eq!(m.edition(), Edition::Edition2026);
+        assert_eq!(m.lookup("sq").as_deref(), Some("( n -- n ) dup mul"));
+        assert_eq!((m.pop(), m.pop(), m.pop()), (Some(7), Some(1), None));
+        assert!(!state.exists());
+        fs::remove_file(&file).expect("cleanup");
+    }
+    #[test]
+    fn report() {
+        let mut m = Machine::with_profile(Profile::Strict);
+        assert!(m.read_eval("def sq dup mul ; 3 sq 4 [ ] times").is_ok());
+        let elapsed = Duration::from_millis(1500);
+        assert_eq!(
+            Report::Text.render(m.stats(), elapsed),
+            "words executed:    7\nmax stack depth:   3\ndefinitions added: 1\nfuel consumed:     11\nelapsed:           1.5s"
+        );
+        assert_eq!(
+            Report::Json.render(m.stats(), elapsed),
+            r#"{"words_executed":7,"max_stack_depth":3,"defs_added":1,"fuel_consumed":11,"elapsed_secs":1.5}"#
+        );
+    }
     #[test]
     fn mode_roundtrip() {
         for m in [Mode::Vi, Mode::Emacs] {
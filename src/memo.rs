@@ -0,0 +1,240 @@
+use crate::{
+    cell::Cell,
+    core::Core,
+    def::{self, Env},
+    edition::Edition,
+    machine::pure_arity,
+    symbol::{BuildSymbolHasher, Symbol},
+    word::Word,
+};
+use indexmap::{Equivalent, IndexMap};
+use std::collections::HashMap;
+
+/// The results of calls to pure definitions, remembered so calling them again with the same values
+/// on top of the stack needn't evaluate their bodies.
+#[derive(Clone, Debug)]
+pub struct Memo<T> {
+    /// The most results to remember, or 0 not to memoize.
+    capacity: usize,
+    /// The edition the results were computed in.
+    edition: Edition,
+    /// Definitions' stack effects if they're pure & static, or `None`, worked out as they're
+    /// called.
+    effects: HashMap<Symbol, Option<(usize, usize)>, BuildSymbolHasher>,
+    results: Results<T>,
+}
+
+/// What calls to definitions left on the stack in place of the values they took.
+type Results<T> = IndexMap<(Symbol, Box<[T]>), Box<[T]>>;
+
+impl<T> Default for Memo<T> {
+    fn default() -> Self {
+        Self {
+            capacity: 0,
+            edition: Edition::default(),
+            effects: HashMap::default(),
+            results: IndexMap::new(),
+        }
+    }
+}
+
+/// A call to look up without copying its arguments.
+#[derive(Hash)]
+struct Call<'a, T>(&'a Symbol, &'a [T]);
+
+impl<T: Eq> Equivalent<(Symbol, Box<[T]>)> for Call<'_, T> {
+    fn equivalent(&self, (k, xs): &(Symbol, Box<[T]>)) -> bool {
+        self.0 == k && self.1 == &**xs
+    }
+}
+
+/// Definitions whose effects are being worked out, innermost last, each with the effect it's
+/// assumed to have & whether that's been relied on.
+type Visiting = Vec<(Symbol, Effect, bool)>;
+
+/// How many times a recursive definition's assumed effect is refined before giving up on it.
+const MAX_ROUNDS: usize = 8;
+
+/// A body's effect on the stack, as far as it's been worked out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Effect {
+    /// It takes & leaves this many values, computing the latter from the former alone.
+    Known(usize, usize),
+    /// It calls a definition whose effect is still being worked out.
+    Unknown,
+    /// It has side effects, depends on more than the values it takes, or takes or leaves a
+    /// varying number of them.
+    Opaque,
+}
+
+impl From<Option<(usize, usize)>> for Effect {
+    fn from(known: Option<(usize, usize)>) -> Self {
+        known.map_or(Self::Opaque, |(i, o)| Self::Known(i, o))
+    }
+}
+
+impl Effect {
+    /// How many values it takes & leaves, if that's known.
+    const fn known(self) -> Option<(usize, usize)> {
+        match self {
+            Self::Known(i, o) => Some((i, o)),
+            _ => None,
+        }
+    }
+    /// The effect of this, & then that.
+    const fn then(self, that: Self) -> Self {
+        match (self, that) {
+            (Self::Known(i, o), Self::Known(j, p)) => {
+                Self::Known(i + j.saturating_sub(o), o.saturating_sub(j) + p)
+            }
+            (Self::Opaque, _) | (_, Self::Opaque) => Self::Opaque,
+            _ => Self::Unknown,
+        }
+    }
+    /// The effect of either this or that.
+    fn or(self, that: Self) -> Self {
+        match (self, that) {
+            (Self::Unknown, e) | (e, Self::Unknown) => e,
+            // Taking more values only to leave them be has the same effect.
+            (Self::Known(i, o), Self::Known(j, p)) if i + p == j + o => {
+                Self::Known(i.max(j), o.max(p))
+            }
+            _ => Self::Opaque,
+        }
+    }
+}
+
+impl<T: Cell> Memo<T> {
+    /// The most results it remembers, or 0 if it doesn't memoize.
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Remember at most `n` results, or none if `n` is 0.
+    pub fn set_capacity(&mut self, n: usize) {
+        self.capacity = n;
+        if self.results.len() > n {
+            self.clear();
+        }
+    }
+    /// How many results it remembers.
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+    /// Forget the results.
+    pub fn clear(&mut self) {
+        self.results.clear();
+    }
+    /// Forget everything worked out from the environment, as it's changed.
+    pub fn forget(&mut self) {
+        self.effects.clear();
+        self.results.clear();
+    }
+    /// How many values a call to `k` takes & leaves, if it's to be memoized.
+    pub fn effect(&mut self, env: &Env<T>, k: &Symbol) -> Option<(usize, usize)> {
+        if self.capacity == 0 {
+            return None;
+        }
+        if let Some(&e) = self.effects.get(k) {
+            return e;
+        }
+        self.analyze(env, k, &mut Vec::new()).0.known()
+    }
+    /// What a call to `k` left in place of these values before, if it's remembered.
+    pub fn recall(&mut self, edition: Edition, k: &Symbol, xs: &[T]) -> Option<&[T]> {
+        if edition != self.edition {
+            self.edition = edition;
+            self.clear();
+        }
+        self.results.get(&Call(k, xs)).map(|vs| &**vs)
+    }
+    /// Remember what a call to `k` left in place of `xs`. When it's full, it forgets every result
+    /// & starts afresh.
+    pub fn record(&mut self, k: Symbol, xs: Box<[T]>, vs: &[T]) {
+        if self.results.len() >= self.capacity {
+            self.clear();
+        }
+        self.results.insert((k, xs), vs.into());
+    }
+    /// Work out the effect of `k`, given hypotheses about those being worked out (innermost
+    /// last) & whether they've been relied on. Also return the index of the outermost of those it
+    /// relies on, if any, as it can only be remembered once that's been confirmed.
+    fn analyze(&mut self, env: &Env<T>, k: &Symbol, visiting: &mut Visiting) -> (Effect, usize) {
+        if let Some(&known) = self.effects.get(k) {
+            return (Effect::from(known), usize::MAX);
+        }
+        if let Some(d) = visiting.iter().position(|(j, ..)| j == k) {
+            visiting[d].2 = true;
+            return (visiting[d].1, d);
+        }
+        let Some(def) = env.get(k) else {
+            return (Effect::Opaque, usize::MAX);
+        };
+        // Recursive calls are first assumed to have whatever effect the rest of the body implies,
+        // & then that's refined until the body has the effect they're assumed to.
+        let depth = visiting.len();
+        visiting.push((k.clone(), Effect::Unknown, false));
+        let (mut e, mut outer) = self.body(env, def, visiting);
+        for round in 0.. {
+            if !visiting[depth].2 || visiting[depth].1 == e {
+                break;
+            }
+            if round == MAX_ROUNDS || !matches!(e, Effect::Known(..)) {
+                e = Effect::Opaque;
+                break;
+            }
+            visiting[depth].1 = e;
+            visiting[depth].2 = false;
+            (e, outer) = self.body(env, def, visiting);
+        }
+        visiting.pop();
+        if outer < depth {
+            return (e, outer);
+        }
+        self.effects.insert(k.clone(), e.known());
+        (Effect::from(e.known()), usize::MAX)
+    }
+    /// Work out the effect of a body, as `analyze` does.
+    fn body(&mut self, env: &Env<T>, ws: &[Word<T>], visiting: &mut Visiting) -> (Effect, usize) {
+        let (mut e, mut outer, mut rest) = (Effect::Known(0, 0), usize::MAX, ws);
+        while e != Effect::Opaque
+            && let Some(word) = rest.first()
+        {
+            let (f, o, len) = match rest {
+                [
+                    Word::Quote(yes),
+                    Word::Quote(no),
+                    Word::Core(Core::IfElse),
+                    ..,
+                ] => {
+                    let (yes, o) = self.body(env, yes, visiting);
+                    let (no, p) = self.body(env, no, visiting);
+                    (Effect::Known(1, 0).then(yes.or(no)), o.min(p), 3)
+                }
+                [Word::Quote(yes), Word::Core(Core::If), ..] => {
+                    let (yes, o) = self.body(env, yes, visiting);
+                    (Effect::Known(1, 0).then(yes.or(Effect::Known(0, 0))), o, 2)
+                }
+                [Word::Custom(c), ..] => {
+                    let (f, o) = self.analyze(env, c, visiting);
+                    (f, o, 1)
+                }
+                _ => (Effect::from(pure(word)), usize::MAX, 1),
+            };
+            (e, outer, rest) = (e.then(f), outer.min(o), &rest[len..]);
+        }
+        (e, outer)
+    }
+}
+
+/// How many values a word takes & leaves, if it computes the latter from the former alone.
+fn pure<T>(word: &Word<T>) -> Option<(usize, usize)> {
+    match word {
+        // These push handles to values interned as they go, or peek at the whole stack.
+        Word::Str(_) | Word::Quote(_) | Word::Ratio(..) | Word::Core(Core::Depth) => None,
+        #[cfg(feature = "bignum")]
+        Word::Big(_) => None,
+        Word::Send(_) => None,
+        Word::Core(c) => def::effect(word).or_else(|| pure_arity(*c).map(|i| (i, 1))),
+        _ => def::effect(word),
+    }
+}
@@ -0,0 +1,45 @@
+use crate::Error;
+use std::{
+    fmt,
+    io::{self, Write},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// Where printing words (`print`, `emit`, `.s`, & the like) write: standard output, unless a
+/// machine's given another sink.
+///
+/// Cloning is cheap and yields a handle to the _same_ sink, so a machine's clones & the workers
+/// it spawns print to it too.
+#[derive(Clone)]
+pub struct Output(Arc<Mutex<dyn Write + Send>>);
+
+impl Output {
+    /// Print to this sink, e.g. a file, a socket, or a buffer shared with the caller.
+    #[must_use]
+    pub fn new(sink: impl Write + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(sink)))
+    }
+    /// Print to standard output.
+    #[must_use]
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+    /// Write formatted text, failing with `Error::Io` if the sink does.
+    pub(crate) fn print(&self, args: fmt::Arguments) -> Result<(), Error> {
+        let mut sink = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        sink.write_fmt(args)
+            .map_err(|e| Error::Io(format!("output: {e}")))
+    }
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self::stdout()
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Output(..)")
+    }
+}
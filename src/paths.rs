@@ -0,0 +1,106 @@
+//! Where drsm keeps its files.
+//!
+//! Each directory may be overridden with an environment variable (`DRSM_CONFIG_DIR`,
+//! `DRSM_DATA_DIR`, or `DRSM_STATE_DIR`); otherwise it's a `drsm` directory inside the platform's
+//! conventional one (e.g. `$XDG_CONFIG_HOME/drsm` on Linux). Directories are created as needed.
+use std::{env, ffi::OsString, fs, io, path::PathBuf};
+
+/// The directory for configuration, e.g. the config file and prelude.
+///
+/// # Errors
+/// If there's no suitable directory, or it can't be created.
+pub fn config_dir() -> io::Result<PathBuf> {
+    dir(env::var_os("DRSM_CONFIG_DIR"), dirs::config_dir())
+}
+
+/// The directory for data the user may want to keep.
+///
+/// # Errors
+/// If there's no suitable directory, or it can't be created.
+pub fn data_dir() -> io::Result<PathBuf> {
+    dir(env::var_os("DRSM_DATA_DIR"), dirs::data_dir())
+}
+
+/// The directory for state that should persist between sessions, e.g. history.
+///
+/// # Errors
+/// If there's no suitable directory, or it can't be created.
+pub fn state_dir() -> io::Result<PathBuf> {
+    dir(
+        env::var_os("DRSM_STATE_DIR"),
+        dirs::state_dir().or_else(dirs::data_dir),
+    )
+}
+
+/// The REPL's line-editing history.
+///
+/// # Errors
+/// If there's no suitable directory, or it can't be created.
+pub fn history_file() -> io::Result<PathBuf> {
+    state_dir().map(|d| d.join("history.txt"))
+}
+
+/// The REPL's configuration file.
+///
+/// # Errors
+/// If there's no suitable directory, or it can't be created.
+pub fn config_file() -> io::Result<PathBuf> {
+    config_dir().map(|d| d.join("config.toml"))
+}
+
+/// A file of definitions to load at startup.
+///
+/// # Errors
+/// If there's no suitable directory, or it can't be created.
+pub fn prelude_file() -> io::Result<PathBuf> {
+    config_dir().map(|d| d.join("prelude.drsm"))
+}
+
+/// The user's rc file, `.drsmrc` in their home directory, which the REPL runs at startup.
+///
+/// # Errors
+/// If there's no home directory.
+pub fn rc_file() -> io::Result<PathBuf> {
+    dirs::home_dir()
+        .map(|h| h.join(".drsmrc"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))
+}
+
+/// Saved machine state, for resuming later.
+///
+/// # Errors
+/// If there's no suitable directory, or it can't be created.
+pub fn machine_state_file() -> io::Result<PathBuf> {
+    state_dir().map(|d| d.join("machine.state"))
+}
+
+/// Prefer the override, else `drsm` within the base directory, creating it if need be.
+fn dir(over: Option<OsString>, base: Option<PathBuf>) -> io::Result<PathBuf> {
+    let d = over
+        .map(PathBuf::from)
+        .or_else(|| base.map(|b| b.join("drsm")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    fs::create_dir_all(&d)?;
+    Ok(d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn overrides() {
+        let tmp = env::temp_dir().join(format!("drsm-paths-{}", process::id()));
+        let over = tmp.join("over");
+        assert_eq!(
+            dir(Some(over.clone().into()), Some(tmp.clone())).ok(),
+            Some(over.clone())
+        );
+        assert!(over.is_dir());
+        assert_eq!(dir(None, Some(tmp.clone())).ok(), Some(tmp.join("drsm")));
+        assert!(tmp.join("drsm").is_dir());
+        assert!(dir(None, None).is_err());
+        fs::remove_dir_all(tmp).expect("cleanup");
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    Edition, Error,
+    cell::Cell,
+    token::{Token, documents},
+    word::Word,
+};
+use lean_string::LeanString;
+use logos::Logos;
+use std::{fmt, vec};
+
+/// Input parsed but not evaluated, e.g. for tools like formatters & linters.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Program<T = i64> {
+    /// The statements, in order.
+    pub statements: Vec<Statement<T>>,
+}
+
+/// One statement of a program.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Statement<T = i64> {
+    /// A definition: its name, stack-effect comment (if any), & body.
+    Def(LeanString, Option<LeanString>, Vec<Word<T>>),
+    /// A deliberate redefinition, like a definition.
+    Redef(LeanString, Option<LeanString>, Vec<Word<T>>),
+    /// A variable's declaration.
+    Var(LeanString),
+    /// A constant's binding.
+    Const(LeanString),
+    /// A switch of edition.
+    Edition(Edition),
+    /// A word to evaluate.
+    Word(Word<T>),
+}
+
+/// Statements are separated by spaces, & definitions ended by `;`, so a program parses back to
+/// itself.
+impl<T: fmt::Display> fmt::Display for Program<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, s) in self.statements.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{s}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Statement<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Def(k, effect, ws) | Self::Redef(k, effect, ws) => {
+                let kw = if matches!(self, Self::Def(..)) {
+                    "def"
+                } else {
+                    "redef"
+                };
+                write!(f, "{kw} {k}")?;
+                if let Some(e) = effect {
+                    write!(f, " {e}")?;
+                }
+                for w in ws {
+                    write!(f, " {w}")?;
+                }
+                f.write_str(" ;")
+            }
+            Self::Var(k) => write!(f, "var {k}"),
+            Self::Const(k) => write!(f, "const {k}"),
+            Self::Edition(e) => write!(f, "#edition {e}"),
+            Self::Word(w) => write!(f, "{w}"),
+        }
+    }
+}
+
+/// Parse a string into a program, as a machine would read it, but without evaluating anything.
+///
+/// Unlike when a machine reads it, a definition must be ended (by `;`) within the string, unless
+/// it has a body & ends the string; and constants' names aren't replaced by their values.
+///
+/// # Errors
+/// If something goes wrong in lexing or parsing.
+pub fn parse<T: Cell>(s: &str) -> Result<Program<T>, Error> {
+    let mut ts = Vec::new();
+    for t in Token::lexer(s) {
+        let t = t?;
+        if !matches!(t, Token::Effect(_)) || documents(&ts) {
+            ts.push(t);
+        }
+    }
+    let mut ts = ts.into_iter();
+    let mut statements = Vec::new();
+    while let Some(t) = ts.next() {
+        statements.push(match t {
+            Token::Def | Token::Redef => {
+                let k = name::<T>(&mut ts, Error::DefName)?;
+                let effect = match ts.as_slice().first() {
+                    Some(&Token::Effect(e)) => ts.next().map(|_| e.into()),
+                    _ => None,
+                };
+                let mut ws = Vec::new();
+                loop {
+                    match ts.next() {
+                        None if ws.is_empty() => return Err(Error::Unended(k.to_string())),
+                        Some(Token::End) if ws.is_empty() => return Err(Error::DefBody),
+                        None | Some(Token::End) => break,
+                        Some(t) => ws.push(Word::parse(t, &mut ts)?),
+                    }
+                }
+                if t == Token::Def {
+                    Statement::Def(k, effect, ws)
+                } else {
+                    Statement::Redef(k, effect, ws)
+                }
+            }
+            Token::Var => Statement::Var(name::<T>(&mut ts, Error::VarName)?),
+            Token::Const => Statement::Const(name::<T>(&mut ts, Error::ConstName)?),
+            Token::Edition => {
+                let e = ts.next().ok_or(Error::EditionName)?.to_string();
+                Statement::Edition(e.parse().map_err(|_| Error::UnknownEdition(e))?)
+            }
+            t => Statement::Word(Word::parse(t, &mut ts)?),
+        });
+    }
+    Ok(Program { statements })
+}
+
+/// Parse the name following a keyword, or fail with `missing` if there's none.
+fn name<T: Cell>(ts: &mut vec::IntoIter<Token>, missing: Error) -> Result<LeanString, Error> {
+    ts.next()
+        .ok_or(missing)
+        .and_then(Word::<T>::try_from)
+        .and_then(Word::into_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements() {
+        let p = parse::<i64>(
+            "#edition 2026 def sq ( n -- n ) dup mul ; 3 const n var v n sq [ 1 ] redef sq 1 ; def one 1",
+        );
+        assert_eq!(
+            p.as_ref().map(ToString::to_string),
+            Ok(
+                "#edition 2026 def sq ( n -- n ) dup mul ; 3 const n var v n sq [ 1 ] redef sq 1 ; def one 1 ;"
+                    .into()
+            )
+        );
+        let p = p.expect("parses");
+        assert_eq!(p.statements.len(), 10);
+        assert_eq!(parse(&p.to_string()), Ok(p));
+        for (s, e) in [
+            ("def sq", Error::Unended("sq".into())),
+            ("def sq ;", Error::DefBody),
+            ("1 ;", Error::Unbegun),
+            ("var", Error::VarName),
+            ("#edition 1999", Error::UnknownEdition("1999".into())),
+        ] {
+            assert_eq!(parse::<i64>(s), Err(e));
+        }
+    }
+}
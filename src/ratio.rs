@@ -0,0 +1,54 @@
+use crate::core::Core;
+
+/// An exact fraction: a numerator & a (positive) denominator, in lowest terms.
+pub type Ratio = (i64, i64);
+
+/// The fraction `num/den` in lowest terms, if `den` is nonzero & the result fits.
+pub fn normalize(num: i128, den: i128) -> Option<Ratio> {
+    if den == 0 {
+        return None;
+    }
+    let (mut gcd, mut rem) = (num.unsigned_abs(), den.unsigned_abs());
+    while rem != 0 {
+        (gcd, rem) = (rem, gcd % rem);
+    }
+    let g = i128::try_from(gcd).ok()? * den.signum();
+    Some((i64::try_from(num / g).ok()?, i64::try_from(den / g).ok()?))
+}
+
+/// Apply the ratio arithmetic word `c` to `x` (the top of the stack) & `y` (the one below), if
+/// the result is defined & fits.
+pub fn op(c: Core, x: Ratio, y: Ratio) -> Option<Ratio> {
+    let [xn, xd, yn, yd] = [x.0, x.1, y.0, y.1].map(i128::from);
+    match c {
+        Core::RAdd => normalize(xn * yd + yn * xd, xd * yd),
+        Core::RSub => normalize(xn * yd - yn * xd, xd * yd),
+        Core::RMul => normalize(xn * yn, xd * yd),
+        _ => normalize(xn * yd, xd * yn),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn lowest_terms(n in -i64::MAX..=i64::MAX, d in 1..i64::MAX, k in 1..1_000_000i128) {
+            let r = normalize(i128::from(n), i128::from(d));
+            prop_assert!(r.is_some());
+            prop_assert_eq!(normalize(i128::from(n) * k, i128::from(d) * -k), r.map(|(n, d)| (-n, d)));
+        }
+    }
+
+    #[test]
+    fn ops() {
+        assert_eq!(op(Core::RAdd, (1, 2), (1, 3)), Some((5, 6)));
+        assert_eq!(op(Core::RSub, (1, 2), (1, 3)), Some((1, 6)));
+        assert_eq!(op(Core::RMul, (2, 3), (3, 4)), Some((1, 2)));
+        assert_eq!(op(Core::RDiv, (1, 2), (-1, 4)), Some((-2, 1)));
+        assert_eq!(op(Core::RDiv, (1, 2), (0, 1)), None);
+        assert_eq!(op(Core::RMul, (i64::MAX, 1), (2, 1)), None);
+    }
+}
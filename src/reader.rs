@@ -0,0 +1,444 @@
+use crate::{Error, Machine, cell::Cell, token::Token};
+use logos::Logos;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    str,
+};
+
+/// The most bytes read at once, so input's evaluated in pieces of bounded size however long its
+/// lines.
+const CHUNK: usize = 1 << 16;
+
+/// The most bytes of input held at once, waiting for a statement that can't be cut into pieces
+/// (e.g. a definition, or a quotation) to end.
+const MAX_PENDING: usize = 1 << 20;
+
+impl<T: Cell> Machine<T> {
+    /// Read a file & evaluate it, as it's read (see `read_eval_stream`).
+    ///
+    /// Lines beginning with whitespace continue the previous line, so definitions may span
+    /// several lines (as may those ended by `;`, which must be ended in the same file, &
+    /// quotations), and `#include <path>` evaluates another file (relative to this one).
+    ///
+    /// # Errors
+    /// If the file can't be read, or evaluation fails; errors include the file and line.
+    pub fn read_eval_path(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        self.include(path.as_ref(), &mut Vec::new())
+    }
+    /// Read from a reader & evaluate it, as with `read_eval_path`.
+    ///
+    /// It's lexed & evaluated incrementally, a statement (or, for a long one, a run of words
+    /// outside any quotation or definition) at a time, so memory use doesn't grow with the input
+    /// (nor the length of its lines). Included paths are relative to the current directory.
+    ///
+    /// # Errors
+    /// If reading fails, the input isn't UTF-8, a statement that can't be cut into pieces is over
+    /// a mebibyte long, or evaluation fails; errors include the line.
+    pub fn read_eval_stream(&mut self, r: impl BufRead) -> Result<(), Error> {
+        let mut lines = Lines::new("<input>", Path::new(""));
+        self.read_eval_lines(r, &mut lines, &mut Vec::new())
+    }
+    /// Read a file & evaluate it as with `read_eval_path`, but skipping any (top-level) statements
+    /// starting before line `from`, and calling `after` with the line following each statement
+    /// evaluated—so a long run can be checkpointed (with `snapshot`, which saves any definition
+    /// still open, too), and later resumed from that line.
+    ///
+    /// # Errors
+    /// If the file can't be read, evaluation fails, or `after` fails.
+    pub fn read_eval_path_resumable(
+        &mut self,
+        path: impl AsRef<Path>,
+        from: usize,
+        mut after: impl FnMut(&Self, usize) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let (path, name) = (path.as_ref(), path.as_ref().display().to_string());
+        let r = BufReader::new(File::open(path).map_err(|e| io(&name, &e))?);
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut lines = Lines {
+            from,
+            after: Some(&mut after),
+            ..Lines::new(&name, &dir)
+        };
+        self.read_eval_lines(r, &mut lines, &mut Vec::new())
+    }
+    fn include(&mut self, path: &Path, seen: &mut Vec<PathBuf>) -> Result<(), Error> {
+        let name = path.display().to_string();
+        let canon = path.canonicalize().map_err(|e| io(&name, &e))?;
+        if seen.contains(&canon) {
+            return Err(Error::IncludeCycle(name));
+        }
+        let r = BufReader::new(File::open(path).map_err(|e| io(&name, &e))?);
+        let dir = canon.parent().map(Path::to_path_buf).unwrap_or_default();
+        seen.push(canon);
+        let result = self.read_eval_lines(r, &mut Lines::new(&name, &dir), seen);
+        seen.pop();
+        result
+    }
+    fn read_eval_lines(
+        &mut self,
+        mut r: impl BufRead,
+        lines: &mut Lines<T>,
+        seen: &mut Vec<PathBuf>,
+    ) -> Result<(), Error> {
+        let (mut bytes, mut text, mut scan) = (Vec::new(), String::new(), Scan::default());
+        // Where the pending text begins, & the line on which the statement it's part of does.
+        let (mut line, mut col, mut begun) = (1, 0, 1);
+        loop {
+            let read = r
+                .fill_buf()
+                .map_err(|e| lines.at(line, io(lines.name, &e)))?;
+            let (n, eof) = (read.len().min(CHUNK), read.is_empty());
+            bytes.extend_from_slice(&read[..n]);
+            r.consume(n);
+            // Bytes after the valid ones may just be a character split between reads.
+            let (valid, invalid) = match str::from_utf8(&bytes) {
+                Ok(s) => (s.len(), false),
+                Err(e) => (e.valid_up_to(), e.error_len().is_some() || eof),
+            };
+            text.push_str(&String::from_utf8_lossy(&bytes[..valid]));
+            bytes.drain(..valid);
+            if invalid {
+                let e = Error::Io(format!("{}: not UTF-8", lines.name));
+                return Err(lines.at(line + text.matches('\n').count(), e));
+            }
+            while let Some((cut, ends)) = scan.cut(&text, eof) {
+                let s = &text[..cut];
+                let next = line
+                    + if cut == text.len() {
+                        s.lines().count().max(1)
+                    } else {
+                        s.matches('\n').count()
+                    };
+                self.statement(s, (line, col), begun, ends.then_some(next), lines, seen)?;
+                col = s.rfind('\n').map_or(col + cut, |i| cut - i - 1);
+                text.drain(..cut);
+                line = next;
+                if ends {
+                    begun = next;
+                }
+            }
+            if eof {
+                break;
+            }
+            if text.len() > MAX_PENDING {
+                return Err(lines.at(begun, Error::TooLong(MAX_PENDING)));
+            }
+        }
+        self.abandon().map_or(Ok(()), |k| {
+            Err(lines.at(line - 1, Error::Unended(k.to_string())))
+        })
+    }
+    /// Evaluate `s`, starting on line `n` at column `col`, of a statement begun on line `begun`.
+    /// If `s` ends the statement, `next` is the line following it.
+    fn statement(
+        &mut self,
+        s: &str,
+        (n, col): (usize, usize),
+        begun: usize,
+        next: Option<usize>,
+        lines: &mut Lines<T>,
+        seen: &mut Vec<PathBuf>,
+    ) -> Result<(), Error> {
+        if begun < lines.from {
+            return Ok(());
+        }
+        let mut ts = Token::lexer(s);
+        if self.defining().is_none() && ts.next() == Some(Ok(Token::Include)) {
+            match (ts.next(), ts.next()) {
+                (Some(Ok(Token::Custom(p))), None) => self.include(&lines.dir.join(p), seen),
+                _ => Err(Error::Include),
+            }
+        } else {
+            self.read_eval_spanned(s)
+        }
+        .map_err(|e| lines.locate((n, col), s, e))?;
+        match (lines.after.as_mut(), next) {
+            (Some(f), Some(next)) => f(self, next),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The state of scanning pending text for where to cut it, kept as more arrives so it's lexed
+/// about once, not again with every piece read.
+#[derive(Clone, Copy, Default)]
+struct Scan {
+    /// Where to resume lexing: the start of the last token lexed, which may be unfinished.
+    from: usize,
+    /// How much of the text's been scanned.
+    scanned: usize,
+    /// What must arrive before lexing's worth resuming: the close of a comment or string begun at
+    /// `from`, or whitespace after a token that may be unfinished.
+    until: Option<fn(char) -> bool>,
+    /// Where the text may be cut, so far.
+    cut: Option<usize>,
+    /// The end of the token before the one at `from`.
+    end: Option<usize>,
+    /// How deep within quotations the token at `from` is.
+    depth: usize,
+    /// Is it within a definition?
+    def: bool,
+    /// Is it just after a word naming something?
+    named: bool,
+}
+
+impl Scan {
+    /// Where to cut pending text `s` (grown since the last call, unless that cut it) to evaluate
+    /// what's before, if anywhere yet, & whether that ends a statement.
+    ///
+    /// A statement ends where a line begins without whitespace, unless it's within a quotation. A
+    /// long one may also be cut between words outside any quotation or definition, but not just
+    /// after a word naming something (like `var`), nor after a word that may be unfinished: the
+    /// last, or the start of a comment or string that's yet to end. At the end of the input, the
+    /// rest is a statement.
+    fn cut(&mut self, s: &str, eof: bool) -> Option<(usize, bool)> {
+        if eof {
+            *self = Self::default();
+            return Some((s.len(), true)).filter(|_| !s.is_empty());
+        }
+        let fresh = &s[self.scanned..];
+        self.scanned = s.len();
+        if self.until.is_some_and(|f| !fresh.contains(f)) {
+            return None;
+        }
+        // Where to resume, should no cut be found: before the last token.
+        let mut last = Self {
+            until: None,
+            ..*self
+        };
+        for (t, span) in Token::lexer(&s[self.from..]).spanned() {
+            let span = self.from + span.start..self.from + span.end;
+            last = Self {
+                from: span.start,
+                until: (span.end == s.len()).then_some(char::is_whitespace as fn(char) -> bool),
+                ..*self
+            };
+            let Ok(t) = t else { break };
+            if let Some(end) = self.end {
+                let begins_line = s[end..span.start]
+                    .match_indices('\n')
+                    .any(|(i, _)| s[end + i + 1..].starts_with(|c: char| !c.is_whitespace()));
+                if self.depth == 0 && begins_line {
+                    *self = Self::default();
+                    return Some((span.start, true));
+                }
+                if matches!(t, Token::Include) {
+                    break;
+                }
+                if self.depth == 0 && !self.def && !self.named {
+                    self.cut = Some(span.start);
+                }
+            }
+            if let Token::Custom(w) = t
+                && (w == "(" || w.starts_with('"'))
+            {
+                last.until = Some(if w == "(" { |c| c == ')' } else { |c| c == '"' });
+                break;
+            }
+            match t {
+                Token::Def | Token::Redef => self.def = true,
+                Token::End => self.def = false,
+                Token::Open => self.depth += 1,
+                Token::Close => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+            self.named = matches!(
+                t,
+                Token::Var
+                    | Token::Const
+                    | Token::Edition
+                    | Token::Include
+                    | Token::Send
+                    | Token::Recv
+            );
+            #[cfg(feature = "concurrency")]
+            {
+                self.named |= t == Token::Spawn;
+            }
+            self.end = Some(span.end);
+        }
+        if let Some(c) = self.cut {
+            *self = Self::default();
+            Some((c, false))
+        } else {
+            *self = last;
+            None
+        }
+    }
+}
+
+/// Called with the line following each statement evaluated.
+type After<'a, T> = &'a mut dyn FnMut(&Machine<T>, usize) -> Result<(), Error>;
+
+/// How to read the lines of one file.
+struct Lines<'a, T: Cell> {
+    /// Used in error locations.
+    name: &'a str,
+    /// Includes are relative to this.
+    dir: &'a Path,
+    /// Skip statements starting before this line.
+    from: usize,
+    after: Option<After<'a, T>>,
+}
+
+impl<'a, T: Cell> Lines<'a, T> {
+    fn new(name: &'a str, dir: &'a Path) -> Self {
+        Self {
+            name,
+            dir,
+            from: 0,
+            after: None,
+        }
+    }
+    fn at(&self, line: usize, e: Error) -> Error {
+        Error::At(self.name.into(), line, Box::new(e))
+    }
+    /// Locate an error in `s`, starting on line `n` at column `col`: on the line (& at the column)
+    /// its span begins, if it has one.
+    fn locate(&self, (n, col): (usize, usize), s: &str, e: Error) -> Error {
+        match e {
+            Error::Span(span, e) => {
+                let before = &s[..span.start];
+                let (start, col) = before.rfind('\n').map_or((0, col), |i| (i + 1, 0));
+                let span = span.start - start + col..span.end - start + col;
+                self.at(n + before.matches('\n').count(), Error::Span(span, e))
+            }
+            e => self.at(n, e),
+        }
+    }
+}
+
+fn io(name: &str, e: &std::io::Error) -> Error {
+    Error::Io(format!("{name}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env, fs,
+        io::{BufReader, Cursor},
+        process,
+    };
+
+    #[test]
+    fn continuation_lines() {
+        let mut m = Machine::default();
+        let s = "def sq\n  dup\n  mul\n\n3 sq\n4 sq";
+        assert_eq!(m.read_eval_stream(Cursor::new(s)), Ok(()));
+        assert_eq!(m.lookup("sq").as_deref(), Some("dup mul"));
+        let unknown = |span| Box::new(Error::Span(span, Box::new(Error::Unknown("nope".into()))));
+        assert_eq!(
+            m.read_eval_stream(Cursor::new("1\n\n  drop\nnope")),
+            Err(Error::At("<input>".into(), 4, unknown(0..4)))
+        );
+        let e = m.read_eval_stream(Cursor::new("1\n  dup\n  1 nope"));
+        assert_eq!(e, Err(Error::At("<input>".into(), 3, unknown(4..8))));
+        assert_eq!(
+            e.map_err(|e| e.to_string()),
+            Err("<input>:3: column 5: Unknown op: `nope`.".into())
+        );
+    }
+
+    #[test]
+    fn streamed() {
+        let s = "def sq dup mul\n\"héllo wörld\" drop ( a comment ) 3 sq [ 1\n2 ] call add\n\
+                 def cube\n  dup sq mul\n\\ comment\n2 cube 7 const seven seven ( a -- b ) drop\n";
+        for capacity in [1, 2, 3, 5, 64] {
+            let mut m = Machine::default();
+            let r = BufReader::with_capacity(capacity, Cursor::new(s));
+            assert_eq!(m.read_eval_stream(r), Ok(()), "{capacity}");
+            assert_eq!(m.stack(), [9, 3, 8], "{capacity}");
+            let r = BufReader::with_capacity(capacity, Cursor::new("1 2\n3 nope"));
+            let unknown = Box::new(Error::Unknown("nope".into()));
+            let e = Error::At("<input>".into(), 2, Box::new(Error::Span(2..6, unknown)));
+            assert_eq!(m.read_eval_stream(r), Err(e));
+        }
+        let mut m = Machine::default();
+        assert!(matches!(
+            m.read_eval_stream(Cursor::new(b"1\n2 \xff")),
+            Err(Error::At(_, 2, e)) if matches!(*e, Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn bounded() {
+        let mut m = Machine::default();
+        let s = "1 drop ".repeat(MAX_PENDING / 4);
+        assert_eq!(m.read_eval_stream(Cursor::new(s)), Ok(()));
+        let s = format!("1\ndef long\n{}", "  1 drop\n".repeat(MAX_PENDING / 8));
+        let e = Error::At("<input>".into(), 2, Box::new(Error::TooLong(MAX_PENDING)));
+        assert_eq!(m.read_eval_stream(Cursor::new(s)), Err(e));
+        assert_eq!(m.stack(), [1]);
+        let s = format!("2 [ {}", "1 ".repeat(MAX_PENDING / 2));
+        let e = Error::At("<input>".into(), 1, Box::new(Error::TooLong(MAX_PENDING)));
+        assert_eq!(m.read_eval_stream(Cursor::new(s)), Err(e));
+    }
+
+    #[test]
+    fn includes() {
+        let dir = env::temp_dir().join(format!("drsm-includes-{}", process::id()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        fs::write(dir.join("lib.drsm"), "def sq dup mul\n").expect("write lib");
+        fs::write(dir.join("main.drsm"), "#include lib.drsm\n3 sq\n").expect("write main");
+        fs::write(dir.join("loop.drsm"), "1\n#include loop.drsm\n").expect("write loop");
+        let mut m = Machine::default();
+        assert_eq!(m.read_eval_path(dir.join("main.drsm")), Ok(()));
+        assert_eq!(m.lookup("sq").as_deref(), Some("dup mul"));
+        assert!(matches!(
+            m.read_eval_path(dir.join("loop.drsm")),
+            Err(Error::At(_, 2, e)) if matches!(*e, Error::IncludeCycle(_))
+        ));
+        assert!(matches!(
+            m.read_eval_path(dir.join("nope.drsm")),
+            Err(Error::Io(_))
+        ));
+        assert_eq!(m.read_eval("#include lib.drsm"), Err(Error::Include));
+        fs::remove_dir_all(dir).expect("cleanup");
+    }
+
+    #[test]
+    fn resumable() {
+        let path = env::temp_dir().join(format!("drsm-resumable-{}.drsm", process::id()));
+        fs::write(&path, "1\ndef two\n  2\ntwo\n3\n").expect("write");
+        let mut nexts = Vec::new();
+        let mut m = Machine::default();
+        let r = m.read_eval_path_resumable(&path, 0, |m, n| {
+            nexts.push((n, m.snapshot()));
+            Ok(())
+        });
+        assert_eq!(r, Ok(()));
+        assert_eq!(
+            nexts.iter().map(|(n, _)| *n).collect::<Vec<_>>(),
+            [2, 4, 5, 6]
+        );
+        let (n, snapshot) = &nexts[1];
+        let mut m2 = Machine::default();
+        assert_eq!(m2.restore(snapshot), Ok(()));
+        assert_eq!(
+            m2.read_eval_path_resumable(&path, *n, |_, _| Ok(())),
+            Ok(())
+        );
+        assert_eq!(m2.to_string(), m.to_string());
+        fs::remove_file(path).expect("cleanup");
+    }
+
+    #[test]
+    fn ended_defs() {
+        let mut m = Machine::default();
+        let s = "def cube\ndup dup\n( body ) mul mul ;\n2 cube";
+        assert_eq!(m.read_eval_stream(Cursor::new(s)), Ok(()));
+        assert_eq!(m.lookup("cube").as_deref(), Some("dup dup mul mul"));
+        assert_eq!(
+            m.read_eval_stream(Cursor::new("def nope\n1\n")),
+            Err(Error::At(
+                "<input>".into(),
+                2,
+                Box::new(Error::Unended("nope".into()))
+            ))
+        );
+        assert_eq!(m.defining(), None);
+    }
+}
@@ -0,0 +1,31 @@
+use std::{fmt, sync::Arc};
+
+/// What `def` & `var` do when their name's already defined. `redef` always redefines.
+#[derive(Clone, Default)]
+pub enum Redefinition {
+    /// Redefine it.
+    #[default]
+    Allow,
+    /// Redefine it, but first call this with its name, e.g. to print a warning.
+    Warn(Arc<dyn Fn(&str) + Send + Sync>),
+    /// Fail with `Error::Redefined`.
+    Strict,
+}
+
+impl Redefinition {
+    /// Warn of redefinitions by printing to standard error.
+    #[must_use]
+    pub fn warn() -> Self {
+        Self::Warn(Arc::new(|k| eprintln!("Warning: redefining `{k}`.")))
+    }
+}
+
+impl fmt::Debug for Redefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Allow => "Allow",
+            Self::Warn(_) => "Warn(..)",
+            Self::Strict => "Strict",
+        })
+    }
+}
@@ -0,0 +1,1074 @@
+//! An interactive read-eval-print loop, for hosting a machine at a terminal.
+//!
+//! Besides evaluating input, the REPL understands a few `?`-prefixed commands (`?` lists them);
+//! hosts may add their own with [`Repl::command`]. Each line's handling is available separately
+//! via [`Repl::line`], so the REPL can be driven without a terminal, e.g. in tests. At the
+//! terminal, Tab completes core words, custom words, & commands (see [`Repl::complete`]), and
+//! input's colored as it's typed (see [`Repl::highlight`]).
+use crate::{CancelToken, Core, Debugger, Error, Machine, Pause, Resume, SpannedToken, Token, lex};
+use documented::DocumentedFields;
+use indexmap::IndexMap;
+use rustyline::{
+    Config, EditMode, Editor, Helper,
+    completion::Completer,
+    config::Configurer,
+    error::ReadlineError,
+    highlight::{CmdKind, Highlighter},
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::Validator,
+};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::{
+        OnceLock,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, JoinHandle},
+};
+use strum::IntoEnumIterator;
+
+/// How many items per section `?show` displays.
+pub const SHOW_LIMIT: usize = 32;
+
+/// How many lines `?show page` displays at once.
+pub const PAGE_LINES: usize = 24;
+
+/// How many lines (or commands that change the machine) `?undo` can undo.
+pub const UNDO_LIMIT: usize = 64;
+
+static COMMANDS: &str = "
+Commands:
+    ?           =>  show these commands.
+    ?show       =>  show machine's environment & stack (truncated).
+    ?show full  =>  show machine's environment & stack in full.
+    ?show page  =>  show machine's environment & stack a page at a time.
+    ?show --json =>  show machine's environment & stack as JSON.
+    ?lookup <w> =>  look up word <w> in the environment.
+    ?time <c>   =>  evaluate code <c>, showing how long it took & how many words it executed.
+    ?words      =>  list every core word with its doc, & every definition with its body.
+    ?forget <w> =>  remove the definition of <w>, unless others refer to it.
+    ?clear      =>  empty the stack.
+    ?reset      =>  start afresh, with no definitions & an empty stack.
+    ?undo       =>  restore the stack & environment to before the last line.
+    ?mode <m>   =>  switch to vi- or emacs-style key bindings (<m> is `vi` or `emacs`).
+    ?autoprint  =>  toggle showing the top of the stack after each line.
+    ?trace      =>  toggle showing each word as it's executed, & the stack after it.
+    ?break <w>  =>  pause evaluation before each execution of word <w> (again to stop).
+    ?step       =>  when paused, execute the next word & pause again.
+    ?continue   =>  when paused, carry on until the next breakpoint.
+    ?inspect    =>  when paused, show where, the stack, & the environment.
+    ?abort      =>  when paused, give up evaluating.
+    ?record <f> =>  append each line & what it shows to file <f>.
+    ?stop-record => stop appending to the file given to ?record.
+    ?export-session <f> => write file <f>, which `drsm run` rebuilds the machine from.
+    ?quit       =>  quit the REPL.";
+
+/// The built-in commands, as `COMMANDS` describes them, for completion.
+const BUILTINS: [&str; 21] = [
+    "?",
+    "?show",
+    "?lookup",
+    "?words",
+    "?time",
+    "?forget",
+    "?clear",
+    "?reset",
+    "?undo",
+    "?mode",
+    "?autoprint",
+    "?trace",
+    "?break",
+    "?step",
+    "?continue",
+    "?inspect",
+    "?abort",
+    "?record",
+    "?stop-record",
+    "?export-session",
+    "?quit",
+];
+
+/// How `highlight` colors numbers, as an ANSI escape code.
+const NUMBER: &str = "\x1b[36m";
+/// How `highlight` colors core words.
+const CORE: &str = "\x1b[34m";
+/// How `highlight` colors keywords, e.g. `def` & `;`.
+const KEYWORD: &str = "\x1b[1;35m";
+/// How `highlight` colors strings & characters.
+const STRING: &str = "\x1b[32m";
+/// How `highlight` colors unknown words, & input that can't be lexed.
+const UNKNOWN: &str = "\x1b[31m";
+/// Ends a color.
+const RESET: &str = "\x1b[0m";
+
+/// The token Ctrl-C cancels evaluation with, shared by every REPL that's run.
+static INTERRUPT: OnceLock<CancelToken> = OnceLock::new();
+
+/// The result of handling one line.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Response {
+    /// Nothing to show.
+    Nothing,
+    /// Something to show.
+    Output(String),
+    /// Something long, to show a page at a time.
+    Pages(String),
+    /// Something went wrong.
+    Error(String),
+    /// The REPL should stop.
+    Quit,
+}
+
+/// How the REPL's set up, as far as it's said: each setting is `None` if not.
+///
+/// A configuration file (see `paths::config_file`) holds settings as lines of `key = value`, where
+/// values are strings in double quotes or booleans, & `#` begins a comment; e.g.
+///
+/// ```toml
+/// mode = "emacs"      # or "vi"
+/// prompt = "drsm> "
+/// color = false
+/// autoprint = true
+/// history = "/tmp/drsm-history.txt"
+/// ```
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Settings {
+    /// The key bindings to edit lines with.
+    pub edit_mode: Option<EditMode>,
+    /// What to prompt for lines with.
+    pub prompt: Option<String>,
+    /// Whether to color input as it's typed.
+    pub color: Option<bool>,
+    /// Whether to show the top of the stack after each line.
+    pub autoprint: Option<bool>,
+    /// Where to load line-editing history from & save it to.
+    pub history: Option<PathBuf>,
+}
+
+impl Settings {
+    /// Read settings from a configuration file; if there isn't one, nothing's set.
+    ///
+    /// # Errors
+    /// If the file can't be read, or isn't valid.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(s) => Self::parse(&s).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {e}", path.display()),
+                )
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+    /// Parse the contents of a configuration file.
+    ///
+    /// # Errors
+    /// If a line isn't blank, a comment, or a known key with a valid value.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut settings = Self::default();
+        for (n, l) in s.lines().enumerate() {
+            let l = l.trim();
+            if l.is_empty() || l.starts_with('#') {
+                continue;
+            }
+            settings
+                .set(l)
+                .map_err(|e| format!("line {}: {e}", n + 1))?;
+        }
+        Ok(settings)
+    }
+    /// Set whatever the line `key = value` (perhaps followed by a comment) says.
+    fn set(&mut self, l: &str) -> Result<(), String> {
+        let (key, value) = l
+            .split_once('=')
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .ok_or("expected `key = value`")?;
+        let string = || {
+            value
+                .strip_prefix('"')
+                .and_then(|v| v.split_once('"'))
+                .filter(|(_, rest)| uncommented(rest))
+                .map(|(v, _)| v.to_string())
+                .ok_or_else(|| format!("`{key}` takes a string in double quotes"))
+        };
+        let boolean = || {
+            let (v, rest) = value.split_at(value.find([' ', '\t', '#']).unwrap_or(value.len()));
+            match v {
+                "true" if uncommented(rest) => Ok(true),
+                "false" if uncommented(rest) => Ok(false),
+                _ => Err(format!("`{key}` takes `true` or `false`")),
+            }
+        };
+        match key {
+            "mode" => {
+                self.edit_mode = Some(match string()?.as_str() {
+                    "vi" => EditMode::Vi,
+                    "emacs" => EditMode::Emacs,
+                    _ => return Err("`mode` takes \"vi\" or \"emacs\"".into()),
+                });
+            }
+            "prompt" => self.prompt = Some(string()?),
+            "color" => self.color = Some(boolean()?),
+            "autoprint" => self.autoprint = Some(boolean()?),
+            "history" => self.history = Some(string()?.into()),
+            _ => return Err(format!("unknown setting `{key}`")),
+        }
+        Ok(())
+    }
+    /// These settings, but overridden by any that `over` sets, e.g. from the command line.
+    #[must_use]
+    pub fn overridden_by(self, over: Self) -> Self {
+        Self {
+            edit_mode: over.edit_mode.or(self.edit_mode),
+            prompt: over.prompt.or(self.prompt),
+            color: over.color.or(self.color),
+            autoprint: over.autoprint.or(self.autoprint),
+            history: over.history.or(self.history),
+        }
+    }
+}
+
+/// Whether what follows a value is just (perhaps) whitespace & a comment.
+fn uncommented(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+/// Handles a host's command, given the machine & the rest of the line.
+type Handler = Box<dyn FnMut(&mut Machine, &str) -> Response>;
+
+/// A command added by the host.
+struct Command {
+    help: String,
+    run: Handler,
+}
+
+/// An interactive REPL around a machine.
+pub struct Repl {
+    machine: Machine,
+    config: Config,
+    /// The key bindings to edit lines with, which `?mode` switches between.
+    edit_mode: EditMode,
+    history: Option<PathBuf>,
+    /// What lines are prompted for with, unless input's being continued.
+    prompt: String,
+    /// Whether input's colored as it's typed.
+    color: bool,
+    commands: IndexMap<String, Command>,
+    autoprint: bool,
+    /// Where lines & what they show are being recorded, if anywhere.
+    record: Option<File>,
+    /// The lines of a quotation not yet closed, to be evaluated once it is.
+    pending: String,
+    /// The words evaluation pauses before, in the order they were set.
+    breakpoints: Vec<String>,
+    /// Evaluation that's paused, if any.
+    paused: Option<Paused>,
+    /// The most recent lines that changed the machine, each with the machine as it was before,
+    /// oldest first.
+    undo: VecDeque<(String, Machine)>,
+}
+
+/// Evaluation paused by the debugger, carrying on in another thread when told to.
+struct Paused {
+    /// Where it's paused.
+    at: Pause<i64>,
+    /// Tells it how to go on.
+    resume: Sender<Resume>,
+    /// Tells of its next pause, or (with `None`) that it's finished.
+    pauses: Receiver<Option<Pause<i64>>>,
+    /// Gives back the machine, & the result of evaluation, once it's finished.
+    done: JoinHandle<(Machine, Result<(), Error>)>,
+}
+
+impl fmt::Debug for Repl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Repl")
+            .field("machine", &self.machine)
+            .field("config", &self.config)
+            .field("edit_mode", &self.edit_mode)
+            .field("history", &self.history)
+            .field("prompt", &self.prompt)
+            .field("color", &self.color)
+            .field("commands", &self.commands.keys().collect::<Vec<_>>())
+            .field("autoprint", &self.autoprint)
+            .field("record", &self.record)
+            .field("pending", &self.pending)
+            .field("breakpoints", &self.breakpoints)
+            .field("paused", &self.paused.as_ref().map(|p| &p.at))
+            .field(
+                "undo",
+                &self.undo.iter().map(|(l, _)| l).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new(Machine::default())
+    }
+}
+
+impl Repl {
+    /// A REPL around a (perhaps pre-configured) machine.
+    #[must_use]
+    pub fn new(machine: Machine) -> Self {
+        Self {
+            machine,
+            config: Config::default(),
+            edit_mode: Config::default().edit_mode(),
+            history: None,
+            prompt: ">  ".into(),
+            color: true,
+            commands: IndexMap::new(),
+            autoprint: false,
+            record: None,
+            pending: String::new(),
+            breakpoints: Vec::new(),
+            paused: None,
+            undo: VecDeque::new(),
+        }
+    }
+    /// Configure line-editing, e.g. key bindings (which `?mode` may switch later).
+    #[must_use]
+    pub fn config(mut self, config: Config) -> Self {
+        self.edit_mode = config.edit_mode();
+        self.config = config;
+        self
+    }
+    /// Load line-editing history from, & save it to, this file.
+    #[must_use]
+    pub fn history(mut self, path: PathBuf) -> Self {
+        self.history = Some(path);
+        self
+    }
+    /// Prompt for lines with this, rather than `>  `. (While input's being continued, the prompt
+    /// is still `..`.)
+    #[must_use]
+    pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+    /// Whether to color input as it's typed, as `highlight` does; it is by default.
+    #[must_use]
+    pub const fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+    /// Apply whichever settings are given, e.g. from a configuration file.
+    #[must_use]
+    pub fn settings(mut self, settings: &Settings) -> Self {
+        if let Some(m) = settings.edit_mode {
+            self = self.config(Config::builder().edit_mode(m).build());
+        }
+        if let Some(p) = &settings.prompt {
+            self.prompt.clone_from(p);
+        }
+        self.color = settings.color.unwrap_or(self.color);
+        self.autoprint = settings.autoprint.unwrap_or(self.autoprint);
+        if let Some(h) = &settings.history {
+            self.history = Some(h.clone());
+        }
+        self
+    }
+    /// After each line that's evaluated successfully, show the top of the stack (without popping
+    /// it), as a calculator would; `?autoprint` toggles this.
+    #[must_use]
+    pub const fn autoprint(mut self, autoprint: bool) -> Self {
+        self.autoprint = autoprint;
+        self
+    }
+    /// Add a command `?name`, described by `help`, which `run` handles given the rest of the line.
+    /// It takes precedence over any built-in command of the same name.
+    #[must_use]
+    pub fn command(
+        mut self,
+        name: &str,
+        help: &str,
+        run: impl FnMut(&mut Machine, &str) -> Response + 'static,
+    ) -> Self {
+        let _ = self.commands.insert(
+            name.into(),
+            Command {
+                help: help.into(),
+                run: Box::new(run),
+            },
+        );
+        self
+    }
+    /// Whether input's being continued, as a definition's begun but not yet ended, or a
+    /// quotation's opened but not yet closed.
+    #[must_use]
+    pub fn continuing(&self) -> bool {
+        self.paused.is_none() && (self.machine.defining().is_some() || !self.pending.is_empty())
+    }
+    /// The machine being driven (while evaluation's paused, as it was before the line).
+    #[must_use]
+    pub const fn machine(&self) -> &Machine {
+        &self.machine
+    }
+    /// The machine being driven, mutably.
+    pub const fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+    /// What Tab would complete the word before `pos` in `line` to: where that word starts, & the
+    /// candidates, in order. A word leading the line with `?` is completed to a command; any other
+    /// to a core word or a word defined in the environment.
+    #[must_use]
+    pub fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        self.vocabulary().candidates(line, pos)
+    }
+    /// A line as the terminal shows it, colored (with ANSI escape codes) by the machine's own
+    /// lexer: numbers, core words, keywords, strings, & words not (yet) defined each have a
+    /// color, & the names keywords take are left plain.
+    #[must_use]
+    pub fn highlight(&self, line: &str) -> String {
+        self.vocabulary().highlight(line)
+    }
+    /// The words & commands to complete to, as things stand (where evaluation's paused, if it
+    /// is).
+    fn vocabulary(&self) -> Vocabulary {
+        let mut words: Vec<_> = Core::iter().map(|c| c.to_string()).collect();
+        match &self.paused {
+            Some(p) => words.extend(p.at.definitions.iter().map(|(k, _)| k.clone())),
+            None => words.extend(self.machine.words().map(String::from)),
+        }
+        words.extend(self.machine.constants().map(String::from));
+        let mut commands: Vec<_> = BUILTINS.iter().map(|&c| c.to_string()).collect();
+        commands.extend(self.commands.keys().map(|c| format!("?{c}")));
+        for ws in [&mut words, &mut commands] {
+            ws.sort_unstable();
+            ws.dedup();
+        }
+        Vocabulary {
+            words,
+            commands,
+            color: self.color,
+        }
+    }
+    /// A description of the available commands.
+    #[must_use]
+    pub fn help(&self) -> String {
+        let mut lines = vec![COMMANDS.to_string()];
+        lines.extend(
+            self.commands
+                .iter()
+                .map(|(name, c)| format!("    {:<11} =>  {}", format!("?{name}"), c.help)),
+        );
+        lines.push(String::new());
+        lines.join("\n")
+    }
+    /// Handle one line of input: a command, or else something to evaluate. While recording, the
+    /// line & what it shows are appended to the transcript.
+    pub fn line(&mut self, l: &str) -> Response {
+        let r = self.respond(l);
+        match self.transcribe(l, &r) {
+            Ok(()) => r,
+            Err(e) => {
+                self.record = None;
+                Response::Error(format!("Recording stopped: {e}."))
+            }
+        }
+    }
+    /// Append a line & its response to the transcript, if one's being recorded. So that it can be
+    /// run, commands & whatever's shown are commented out.
+    fn transcribe(&mut self, l: &str, r: &Response) -> io::Result<()> {
+        let Some(f) = &mut self.record else {
+            return Ok(());
+        };
+        let mut lines = vec![if l.starts_with('?') {
+            format!("\\ {l}")
+        } else {
+            l.to_string()
+        }];
+        if let Response::Output(s) | Response::Pages(s) | Response::Error(s) = r {
+            lines.extend(s.lines().map(|s| format!("\\ {s}").trim_end().to_string()));
+        }
+        lines.push(String::new());
+        f.write_all(lines.join("\n").as_bytes())
+    }
+    /// Handle one line of input, as `line` does, but without recording it.
+    fn respond(&mut self, l: &str) -> Response {
+        let (cmd, rest) = l.split_once(char::is_whitespace).unwrap_or((l, ""));
+        let rest = rest.trim();
+        if let Some(p) = self.paused.take() {
+            return self.debug(p, cmd);
+        }
+        if let Some(c) = cmd.strip_prefix('?').and_then(|c| self.commands.get_mut(c)) {
+            return (c.run)(&mut self.machine, rest);
+        }
+        let m = &self.machine;
+        match cmd {
+            "?" => Response::Output(self.help()),
+            "?show" => match rest {
+                "" => Response::Output(format!("{m:.SHOW_LIMIT$}")),
+                "full" => Response::Output(m.to_string()),
+                "page" => Response::Pages(format!("{m:#}")),
+                #[cfg(feature = "serde")]
+                "--json" => Response::Output(m.to_json()),
+                #[cfg(not(feature = "serde"))]
+                "--json" => Response::Error("?show --json needs the `serde` feature.".into()),
+                _ => Response::Error("?show takes `full`, `page`, `--json`, or nothing.".into()),
+            },
+            "?quit" => Response::Quit,
+            "?record" if rest.is_empty() => {
+                Response::Error("?record requires a file to record to.".into())
+            }
+            "?record" => match OpenOptions::new().create(true).append(true).open(rest) {
+                Ok(f) => {
+                    self.record = Some(f);
+                    Response::Output(format!("Recording to {rest}."))
+                }
+                Err(e) => Response::Error(format!("Can't record to {rest}: {e}.")),
+            },
+            "?export-session" if rest.is_empty() => {
+                Response::Error("?export-session requires a file to write.".into())
+            }
+            "?export-session" => match fs::write(rest, m.to_source()) {
+                Ok(()) => Response::Output(format!("Session exported to {rest}.")),
+                Err(e) => Response::Error(format!("Can't export to {rest}: {e}.")),
+            },
+            "?stop-record" => match self.record.take() {
+                Some(_) => Response::Output("Recording stopped.".into()),
+                None => Response::Error("Nothing is being recorded.".into()),
+            },
+            "?clear" | "?reset" | "?forget" | "?undo" => self.change(l, cmd, rest),
+            "?mode" => {
+                match rest {
+                    "" => {}
+                    "vi" => self.edit_mode = EditMode::Vi,
+                    "emacs" => self.edit_mode = EditMode::Emacs,
+                    _ => return Response::Error("?mode takes `vi`, `emacs`, or nothing.".into()),
+                }
+                let mode = if self.edit_mode == EditMode::Vi {
+                    "vi"
+                } else {
+                    "emacs"
+                };
+                Response::Output(format!("Key bindings are {mode}-style."))
+            }
+            "?autoprint" => {
+                self.autoprint = !self.autoprint;
+                let state = if self.autoprint { "on" } else { "off" };
+                Response::Output(format!("Autoprint is {state}."))
+            }
+            "?trace" => {
+                let output = self.machine.output().clone();
+                let trace = self.machine.trace().is_none().then_some(&output);
+                self.machine.set_trace(trace);
+                let state = if trace.is_some() { "on" } else { "off" };
+                Response::Output(format!("Tracing is {state}."))
+            }
+            "?lookup" => match (rest, doc(rest), m.lookup(rest)) {
+                ("", ..) => Response::Error("?lookup requires a word to look up.".into()),
+                (w, Some(d), _) => Response::Output(format!("`{w}` is a core word: {d}")),
+                (w, _, Some(d)) => Response::Output(format!("`{w}` is defined as `{d}`")),
+                (w, _, None) => {
+                    Response::Error(format!("`{w}` is not defined in the environment."))
+                }
+            },
+            "?words" => Response::Pages(self.words()),
+            "?break" => self.toggle_breakpoint(rest),
+            "?step" | "?continue" | "?inspect" | "?abort" => {
+                Response::Error("Evaluation isn't paused.".into())
+            }
+            "?time" if rest.is_empty() => Response::Error("?time requires code to time.".into()),
+            "?time" => {
+                self.checkpoint(l);
+                self.time(rest)
+            }
+            _ => self.eval(l),
+        }
+    }
+    /// Evaluate a line, or (if it leaves a quotation unclosed) keep it until the rest comes.
+    fn eval(&mut self, l: &str) -> Response {
+        let l = if self.pending.is_empty() {
+            l.to_string()
+        } else {
+            format!("{}\n{l}", mem::take(&mut self.pending))
+        };
+        if unclosed(&l) {
+            self.pending = l;
+            return Response::Nothing;
+        }
+        self.checkpoint(&l);
+        if !self.breakpoints.is_empty() {
+            return self.eval_debugged(l);
+        }
+        let r = self.machine.read_eval_spanned(&l);
+        self.evaluated(r)
+    }
+    /// Handle a command that changes the machine (or undoes such a change), as line `l` does.
+    fn change(&mut self, l: &str, cmd: &str, rest: &str) -> Response {
+        if cmd == "?undo" {
+            return self.undo.pop_back().map_or_else(
+                || Response::Error("Nothing to undo.".into()),
+                |(l, m)| {
+                    self.machine = m;
+                    Response::Output(format!("Undid `{l}`."))
+                },
+            );
+        }
+        self.checkpoint(l);
+        match cmd {
+            "?clear" => {
+                self.machine.clear_stack();
+                Response::Output("Stack cleared.".into())
+            }
+            "?reset" => {
+                self.machine.reset();
+                self.pending.clear();
+                Response::Output("Machine reset.".into())
+            }
+            _ => match self.machine.undefine(rest, false) {
+                Ok(()) => Response::Nothing,
+                Err(e) => {
+                    self.undo.pop_back();
+                    Response::Error(e.to_string())
+                }
+            },
+        }
+    }
+    /// Remember the machine as it is, before a line changes it, for `?undo`. Its environment &
+    /// tables are shared with the machine until either changes them, so only what a line changes
+    /// is copied.
+    fn checkpoint(&mut self, l: &str) {
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.pop_front();
+        }
+        self.undo.push_back((l.into(), self.machine.clone()));
+    }
+    /// What to show once a line's been evaluated.
+    fn evaluated(&self, r: Result<(), Error>) -> Response {
+        match r {
+            Ok(()) if self.autoprint && self.machine.defining().is_none() => self
+                .machine
+                .peek()
+                .map_or(Response::Nothing, |x| Response::Output(x.to_string())),
+            Ok(()) => Response::Nothing,
+            Err(e) => Response::Error(e.to_string()),
+        }
+    }
+    /// Set a breakpoint at a word, or remove the one that's there; or, given no word, list them.
+    fn toggle_breakpoint(&mut self, w: &str) -> Response {
+        if w.is_empty() {
+            return Response::Output(if self.breakpoints.is_empty() {
+                "No breakpoints.".into()
+            } else {
+                format!("Breakpoints: {}.", self.breakpoints.join(", "))
+            });
+        }
+        if let Some(i) = self.breakpoints.iter().position(|b| b == w) {
+            self.breakpoints.remove(i);
+            Response::Output(format!("Breakpoint at `{w}` removed."))
+        } else {
+            self.breakpoints.push(w.into());
+            Response::Output(format!("Breakpoint set at `{w}`."))
+        }
+    }
+    /// Evaluate a line in another thread, so it can pause at breakpoints & wait to be told how to
+    /// go on.
+    fn eval_debugged(&mut self, l: String) -> Response {
+        let (resume, resumes) = mpsc::channel();
+        let (report, pauses) = mpsc::channel();
+        let tell = report.clone();
+        let mut debugger = Debugger::new(move |p| {
+            if tell.send(Some(p)).is_err() {
+                return Resume::Abort;
+            }
+            resumes.recv().unwrap_or(Resume::Abort)
+        });
+        for b in &self.breakpoints {
+            debugger.set_breakpoint(b);
+        }
+        // The machine's kept as it was, in case evaluation panics.
+        let mut m = self.machine.clone();
+        m.set_debugger(Some(debugger));
+        let done = thread::spawn(move || {
+            let r = m.read_eval_spanned(&l);
+            m.set_debugger(None);
+            let _ = report.send(None);
+            (m, r)
+        });
+        self.wait(resume, pauses, done)
+    }
+    /// Wait for evaluation in another thread to pause or finish.
+    fn wait(
+        &mut self,
+        resume: Sender<Resume>,
+        pauses: Receiver<Option<Pause<i64>>>,
+        done: JoinHandle<(Machine, Result<(), Error>)>,
+    ) -> Response {
+        if let Ok(Some(at)) = pauses.recv() {
+            let r = Response::Output(format!(
+                "Paused at `{}` (depth {}), with stack {}.",
+                at.word,
+                at.depth,
+                stack(&at.stack)
+            ));
+            self.paused = Some(Paused {
+                at,
+                resume,
+                pauses,
+                done,
+            });
+            return r;
+        }
+        if let Ok((m, r)) = done.join() {
+            self.machine = m;
+            self.evaluated(r)
+        } else {
+            self.undo.pop_back();
+            Response::Error("Evaluation panicked, so the line's been undone.".into())
+        }
+    }
+    /// Handle a command while evaluation's paused.
+    fn debug(&mut self, p: Paused, cmd: &str) -> Response {
+        let resume = match cmd {
+            "?step" => Resume::Step,
+            "?continue" => Resume::Continue,
+            "?abort" => Resume::Abort,
+            "?quit" => {
+                let _ = p.resume.send(Resume::Abort);
+                let _ = p.done.join();
+                return Response::Quit;
+            }
+            _ => {
+                let r = match cmd {
+                    "?" => Response::Output(self.help()),
+                    "?inspect" => Response::Output(inspect(&p.at)),
+                    _ => Response::Error(format!(
+                        "Evaluation's paused at `{}`: ?step, ?continue, ?inspect, or ?abort.",
+                        p.at.word
+                    )),
+                };
+                self.paused = Some(p);
+                return r;
+            }
+        };
+        let _ = p.resume.send(resume);
+        self.wait(p.resume, p.pauses, p.done)
+    }
+    /// Evaluate some code, reporting how long it took & how many words it executed.
+    fn time(&mut self, code: &str) -> Response {
+        let (result, stats) = self.machine.read_eval_stats(code);
+        let (t, n) = (stats.duration, stats.words_executed);
+        let words = if n == 1 { "word" } else { "words" };
+        match result {
+            Ok(()) => Response::Output(format!("Took {t:?}, executing {n} {words}.")),
+            Err(e) => Response::Error(format!("{e} (after {t:?}, executing {n} {words})")),
+        }
+    }
+    /// Every core word with its doc (on one line), then every definition & constant in the
+    /// environment with its source, in aligned columns.
+    fn words(&self) -> String {
+        let m = &self.machine;
+        let core: Vec<_> = Core::iter()
+            .zip(Core::FIELD_DOCS)
+            .map(|(c, d)| {
+                (
+                    c.to_string(),
+                    d.split_whitespace().collect::<Vec<_>>().join(" "),
+                )
+            })
+            .collect();
+        let env: Vec<_> = m
+            .words()
+            .chain(m.constants())
+            .map(|w| (w.to_string(), m.lookup(w).unwrap_or_default()))
+            .collect();
+        let width = core.iter().chain(&env).map(|(w, _)| w.len()).max();
+        let column = |ws: Vec<(String, String)>| {
+            ws.into_iter()
+                .map(|(w, d)| format!("    {w:<width$}  {d}", width = width.unwrap_or(0)))
+                .map(|l| l.trim_end().to_string())
+                .collect::<Vec<_>>()
+        };
+        let mut lines = vec!["Core words:".to_string()];
+        lines.extend(column(core));
+        if !env.is_empty() {
+            lines.push("Definitions:".into());
+            lines.extend(column(env));
+        }
+        lines.join("\n")
+    }
+    /// Read lines from the terminal & handle them, until told to quit or input ends. While input's
+    /// being continued (until a definition's `;`, or a quotation's `]`), the prompt is `..`, &
+    /// Ctrl-C abandons an unclosed quotation; while evaluation's paused, it's `||`; while a line's
+    /// being evaluated, Ctrl-C cancels it.
+    ///
+    /// # Errors
+    /// If the terminal can't be set up, or history can't be saved.
+    pub fn run(&mut self) -> Result<(), ReadlineError> {
+        let mut r = Editor::with_config(self.config.clone())?;
+        let token = INTERRUPT.get_or_init(|| self.machine.cancel_token().clone());
+        self.machine.set_cancel_token(token);
+        // After the editor's set up, as it handles `SIGINT` itself otherwise.
+        cancel_on_interrupt();
+        if let Some(h) = &self.history
+            && r.load_history(h).is_err()
+        {
+            eprintln!("No previous history.");
+        }
+        loop {
+            r.set_helper(Some(self.vocabulary()));
+            if r.config_mut().edit_mode() != self.edit_mode {
+                r.set_edit_mode(self.edit_mode);
+            }
+            let prompt = if self.paused.is_some() {
+                "|| "
+            } else if self.continuing() {
+                ".. "
+            } else {
+                &self.prompt
+            };
+            match r.readline(prompt) {
+                Ok(l) => {
+                    if !l.starts_with('?') {
+                        r.add_history_entry(&l)?;
+                    }
+                    match self.line(&l) {
+                        Response::Nothing => {}
+                        Response::Output(s) => println!("{s}"),
+                        Response::Pages(s) => page(&mut r, &s),
+                        Response::Error(e) => eprintln!("{e}"),
+                        Response::Quit => {
+                            println!("Bye!");
+                            break;
+                        }
+                    }
+                }
+                Err(ReadlineError::Eof) => {
+                    println!("^D");
+                    break;
+                }
+                Err(ReadlineError::Interrupted) => {
+                    token.reset();
+                    self.pending.clear();
+                    println!("^C");
+                }
+                Err(e) => eprintln!("Error: {e}"),
+            }
+        }
+        if let Some(h) = &self.history {
+            r.save_history(h)?;
+        }
+        Ok(())
+    }
+}
+
+/// Make Ctrl-C (i.e. `SIGINT`) cancel evaluation rather than kill the process. While a line's
+/// being read, the terminal's in raw mode, so Ctrl-C arrives as `ReadlineError::Interrupted`
+/// instead.
+#[cfg(unix)]
+#[allow(unsafe_code)]
+fn cancel_on_interrupt() {
+    use nix::sys::signal::{SigHandler, Signal, signal};
+    extern "C" fn interrupt(_: std::ffi::c_int) {
+        if let Some(token) = INTERRUPT.get() {
+            token.cancel();
+        }
+    }
+    // SAFETY: the handler only loads & stores atomics, which is async-signal-safe.
+    if unsafe { signal(Signal::SIGINT, SigHandler::Handler(interrupt)) }.is_err() {
+        eprintln!("Ctrl-C will quit, rather than cancel evaluation.");
+    }
+}
+
+/// Elsewhere, Ctrl-C still quits during evaluation.
+#[cfg(not(unix))]
+const fn cancel_on_interrupt() {}
+
+/// The doc of the core word `w`, if it is one.
+fn doc(w: &str) -> Option<&'static str> {
+    Core::iter()
+        .zip(Core::FIELD_DOCS)
+        .find_map(|(c, d)| (c.to_string() == w).then_some(*d))
+}
+
+/// Where evaluation's paused, the stack, & the environment, on separate lines.
+fn inspect(at: &Pause<i64>) -> String {
+    let mut lines = vec![
+        format!("Paused at `{}` (depth {}).", at.word, at.depth),
+        format!("stack: {}", stack(&at.stack)),
+        "env:".into(),
+    ];
+    lines.extend(at.definitions.iter().map(|(k, d)| format!("  {k}  {d}")));
+    lines.join("\n")
+}
+
+/// A stack as `Display` shows a machine's, top first.
+fn stack(xs: &[i64]) -> String {
+    let xs: Vec<_> = xs.iter().rev().map(ToString::to_string).collect();
+    if xs.is_empty() {
+        "[ ]".into()
+    } else {
+        format!("[ {} ]", xs.join(" "))
+    }
+}
+
+/// Whether `s` opens more quotations than it closes, so its end's yet to come. If it can't be
+/// lexed, it's left to evaluation to say why.
+fn unclosed(s: &str) -> bool {
+    lex(s)
+        .try_fold(0_i64, |depth, t| {
+            t.map(|t| match t.token {
+                Token::Open => depth + 1,
+                Token::Close => depth - 1,
+                _ => depth,
+            })
+        })
+        .is_ok_and(|depth| depth > 0)
+}
+
+/// Print `s` a page at a time, until it's exhausted or the user quits.
+fn page(r: &mut Editor<Vocabulary, DefaultHistory>, s: &str) {
+    let lines = s.lines().collect::<Vec<_>>();
+    for (i, chunk) in lines.chunks(PAGE_LINES).enumerate() {
+        if i > 0 && !matches!(r.readline("-- more (q to quit) --"), Ok(l) if l != "q") {
+            break;
+        }
+        for l in chunk {
+            println!("{l}");
+        }
+    }
+}
+
+/// What the terminal completes words to: core & custom words, & (leading a line) commands.
+struct Vocabulary {
+    /// Sorted, so it can be searched when highlighting.
+    words: Vec<String>,
+    commands: Vec<String>,
+    /// Whether to color input as it's typed.
+    color: bool,
+}
+
+impl Vocabulary {
+    /// Where the word before `pos` in `line` starts, & what it might be completed to.
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let prefix = &before[start..];
+        let pool = if start == 0 && prefix.starts_with('?') {
+            &self.commands
+        } else {
+            &self.words
+        };
+        let candidates = pool.iter().filter(|w| w.starts_with(prefix)).cloned();
+        (start, candidates.collect())
+    }
+    /// Color a line, as `Repl::highlight` does.
+    fn highlight(&self, line: &str) -> String {
+        let (mut out, mut end, mut naming) = (String::with_capacity(line.len()), 0, false);
+        for t in lex(line) {
+            let (color, span) = match t {
+                Ok(SpannedToken { token, span }) => {
+                    let color = match token {
+                        _ if naming => None,
+                        Token::Def
+                        | Token::Redef
+                        | Token::Var
+                        | Token::Const
+                        | Token::End
+                        | Token::Edition
+                        | Token::Include
+                        | Token::Send
+                        | Token::Recv => Some(KEYWORD),
+                        #[cfg(feature = "concurrency")]
+                        Token::Spawn | Token::Join => Some(KEYWORD),
+                        Token::Core(_) => Some(CORE),
+                        Token::Num(_)
+                        | Token::Hex(_)
+                        | Token::Bin(_)
+                        | Token::Oct(_)
+                        | Token::Float(_)
+                        | Token::Ratio(_) => Some(NUMBER),
+                        #[cfg(feature = "bignum")]
+                        Token::Long(_) | Token::Big(_) => Some(NUMBER),
+                        Token::Str(_) | Token::Char(_) => Some(STRING),
+                        Token::Custom(w)
+                            if self.words.binary_search_by(|k| k.as_str().cmp(w)).is_err() =>
+                        {
+                            Some(UNKNOWN)
+                        }
+                        _ => None,
+                    };
+                    naming = !naming && keyword_takes_name(&token);
+                    (color, span)
+                }
+                Err(Error::Lex { span, .. }) => (Some(UNKNOWN), span),
+                Err(_) => continue,
+            };
+            out.push_str(&line[end..span.start]);
+            end = span.end;
+            let text = &line[span];
+            match color {
+                Some(c) => out.extend([c, text, RESET]),
+                None => out.push_str(text),
+            }
+        }
+        out.push_str(&line[end..]);
+        out
+    }
+}
+
+/// Is this token a keyword followed by a name (or, for `#edition` & `#include`, an argument)?
+const fn keyword_takes_name(token: &Token) -> bool {
+    match token {
+        Token::Def
+        | Token::Redef
+        | Token::Var
+        | Token::Const
+        | Token::Edition
+        | Token::Include
+        | Token::Send
+        | Token::Recv => true,
+        #[cfg(feature = "concurrency")]
+        Token::Spawn => true,
+        _ => false,
+    }
+}
+
+impl Completer for Vocabulary {
+    type Candidate = String;
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &rustyline::Context,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for Vocabulary {
+    type Hint = String;
+}
+
+impl Highlighter for Vocabulary {
+    fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
+        if self.color {
+            Cow::Owned(Self::highlight(self, line))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+    // Coloring doesn't depend on where the cursor is.
+    fn highlight_char(&self, _: &str, _: usize, kind: CmdKind) -> bool {
+        self.color && kind != CmdKind::MoveCursor
+    }
+}
+
+impl Validator for Vocabulary {}
+
+impl Helper for Vocabulary {}
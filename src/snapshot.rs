@@ -0,0 +1,341 @@
+use crate::{
+    Error,
+    cell::Cell,
+    core::Core,
+    word::{Fused, Word},
+};
+
+/// The bytes every snapshot starts with.
+const MAGIC: &[u8; 4] = b"DRSM";
+/// The version of the format written.
+const VERSION: u8 = 1;
+/// The length of the header: magic, version, & cell type.
+const HEADER: usize = MAGIC.len() + 3;
+/// The length of the checksum ending a snapshot.
+const CHECKSUM: usize = 8;
+
+/// Writes a snapshot: a header, a payload of variable-length integers & length-prefixed bytes,
+/// then a checksum of everything before it.
+pub struct Encoder(Vec<u8>);
+
+/// Reads the payload of a snapshot whose header & checksum have been verified.
+pub struct Decoder<'a>(&'a [u8]);
+
+/// What a snapshot's cell type is: its width, & whether it's signed.
+fn cell_type<T: Cell>() -> [u8; 2] {
+    // Every primitive cell type is at most 128 bits wide, so only big cells are 0.
+    [
+        u8::try_from(T::BITS).unwrap_or(0),
+        u8::from(T::from_i64(-1) < T::ZERO),
+    ]
+}
+
+/// The 64-bit FNV-1a hash of some bytes, which catches truncation & corruption (but not
+/// tampering).
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl Encoder {
+    /// Start a snapshot of a machine with cells of type `T`.
+    pub fn new<T: Cell>() -> Self {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend(cell_type::<T>());
+        Self(bytes)
+    }
+    /// Finish the snapshot, returning its bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        let sum = fnv1a(&self.0);
+        self.0.extend(sum.to_le_bytes());
+        self.0
+    }
+    /// Write an unsigned integer, in LEB128.
+    pub fn uint(&mut self, mut n: u128) {
+        while n >= 0x80 {
+            #[allow(clippy::cast_possible_truncation)]
+            self.0.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        self.0.push(n as u8);
+    }
+    /// Write a signed integer, zigzagged so small magnitudes stay short.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn int(&mut self, n: i128) {
+        self.uint(((n << 1) ^ (n >> 127)) as u128);
+    }
+    /// Write a length, count, or index.
+    pub fn len(&mut self, n: usize) {
+        self.uint(n as u128);
+    }
+    /// Write a flag.
+    pub fn bool(&mut self, b: bool) {
+        self.0.push(u8::from(b));
+    }
+    /// Write some bytes, preceded by their length.
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.len(bytes.len());
+        self.0.extend(bytes);
+    }
+    /// Write a string.
+    pub fn str(&mut self, s: &str) {
+        self.bytes(s.as_bytes());
+    }
+    /// Write a cell.
+    pub fn cell<T: Cell>(&mut self, n: &T) {
+        #[cfg(feature = "bignum")]
+        if T::BITS > 128 {
+            return self.bytes(&n.to_big().to_signed_bytes_le());
+        }
+        self.int(n.as_i128());
+    }
+    /// Write a word, tagged by its kind.
+    pub fn word<T: Cell>(&mut self, w: &Word<T>) {
+        match w {
+            Word::Core(c) => {
+                self.0.push(0);
+                self.str(&c.to_string());
+            }
+            Word::Num(n) => {
+                self.0.push(1);
+                self.cell(n);
+            }
+            Word::Float(x) => {
+                self.0.push(2);
+                self.uint(x.to_bits().into());
+            }
+            #[cfg(feature = "bignum")]
+            Word::Big(n) => {
+                self.0.push(3);
+                self.bytes(&n.to_signed_bytes_le());
+            }
+            Word::Ratio(n, d) => {
+                self.0.push(4);
+                self.int((*n).into());
+                self.int((*d).into());
+            }
+            Word::Str(s) => {
+                self.0.push(5);
+                self.str(s);
+            }
+            Word::Custom(k) => {
+                self.0.push(6);
+                self.str(k);
+            }
+            Word::Send(c) => {
+                self.0.push(7);
+                self.str(c);
+            }
+            Word::Recv(c) => {
+                self.0.push(8);
+                self.str(c);
+            }
+            #[cfg(feature = "concurrency")]
+            Word::Spawn(k) => {
+                self.0.push(9);
+                self.str(k);
+            }
+            #[cfg(feature = "concurrency")]
+            Word::Join => self.0.push(10),
+            Word::Quote(ws) => {
+                self.0.push(11);
+                self.words(ws);
+            }
+            Word::Fused(f) => {
+                self.0.push(12);
+                self.len(Fused::ALL.iter().position(|g| g == f).unwrap_or_default());
+            }
+            Word::Unbound(k) => {
+                self.0.push(13);
+                self.str(k);
+            }
+        }
+    }
+    /// Write some words, preceded by how many there are.
+    pub fn words<T: Cell>(&mut self, ws: &[Word<T>]) {
+        self.len(ws.len());
+        for w in ws {
+            self.word(w);
+        }
+    }
+}
+
+impl<'a> Decoder<'a> {
+    /// Verify a snapshot of a machine with cells of type `T`, returning a decoder of its payload.
+    pub fn new<T: Cell>(bytes: &'a [u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER + CHECKSUM || !bytes.starts_with(MAGIC) {
+            return Err(Error::Snapshot);
+        }
+        let (body, sum) = bytes.split_at(bytes.len() - CHECKSUM);
+        if sum != fnv1a(body).to_le_bytes() {
+            return Err(Error::Snapshot);
+        }
+        match body[MAGIC.len()] {
+            VERSION => {}
+            v => return Err(Error::SnapshotVersion(v)),
+        }
+        if body[MAGIC.len() + 1..HEADER] != cell_type::<T>() {
+            return Err(Error::SnapshotCell);
+        }
+        Ok(Self(&body[HEADER..]))
+    }
+    /// Check that the whole payload's been read.
+    pub const fn finish(self) -> Result<(), Error> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Snapshot)
+        }
+    }
+    /// Read a byte.
+    fn byte(&mut self) -> Result<u8, Error> {
+        let (&b, rest) = self.0.split_first().ok_or(Error::Snapshot)?;
+        self.0 = rest;
+        Ok(b)
+    }
+    /// Read an unsigned integer, in LEB128.
+    pub fn uint(&mut self) -> Result<u128, Error> {
+        let mut n = 0_u128;
+        for shift in (0..u128::BITS).step_by(7) {
+            let b = self.byte()?;
+            n |= u128::from(b & 0x7f)
+                .checked_shl(shift)
+                .filter(|m| m >> shift == u128::from(b & 0x7f))
+                .ok_or(Error::Snapshot)?;
+            if b < 0x80 {
+                return Ok(n);
+            }
+        }
+        Err(Error::Snapshot)
+    }
+    /// Read a zigzagged signed integer.
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn int(&mut self) -> Result<i128, Error> {
+        let n = self.uint()?;
+        Ok((n >> 1) as i128 ^ -((n & 1) as i128))
+    }
+    /// Read a length or count, which can't exceed the bytes left (as each item takes at least
+    /// one), so a corrupted one can't exhaust memory.
+    pub fn len(&mut self) -> Result<usize, Error> {
+        usize::try_from(self.uint()?)
+            .ok()
+            .filter(|&n| n <= self.0.len())
+            .ok_or(Error::Snapshot)
+    }
+    /// Read an index, or the length of something not itself in the snapshot (e.g. a sparse
+    /// array).
+    pub fn index(&mut self) -> Result<usize, Error> {
+        usize::try_from(self.uint()?).map_err(|_| Error::Snapshot)
+    }
+    /// Read a flag.
+    pub fn bool(&mut self) -> Result<bool, Error> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::Snapshot),
+        }
+    }
+    /// Read some bytes, preceded by their length.
+    pub fn bytes(&mut self) -> Result<&'a [u8], Error> {
+        let n = self.len()?;
+        let (bytes, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(bytes)
+    }
+    /// Read a string.
+    pub fn str(&mut self) -> Result<&'a str, Error> {
+        std::str::from_utf8(self.bytes()?).map_err(|_| Error::Snapshot)
+    }
+    /// Read an integer that must fit in an `i64`.
+    pub fn i64(&mut self) -> Result<i64, Error> {
+        i64::try_from(self.int()?).map_err(|_| Error::Snapshot)
+    }
+    /// Read a cell.
+    pub fn cell<T: Cell>(&mut self) -> Result<T, Error> {
+        #[cfg(feature = "bignum")]
+        if T::BITS > 128 {
+            let n = num_bigint::BigInt::from_signed_bytes_le(self.bytes()?);
+            return T::from_big(&n).ok_or(Error::Snapshot);
+        }
+        self.int().map(T::from_i128)
+    }
+    /// Read a word.
+    pub fn word<T: Cell>(&mut self) -> Result<Word<T>, Error> {
+        Ok(match self.byte()? {
+            0 => Word::Core(self.str()?.parse::<Core>().map_err(|_| Error::Snapshot)?),
+            1 => Word::Num(self.cell()?),
+            2 => Word::Float(f64::from_bits(
+                u64::try_from(self.uint()?).map_err(|_| Error::Snapshot)?,
+            )),
+            #[cfg(feature = "bignum")]
+            3 => Word::Big(num_bigint::BigInt::from_signed_bytes_le(self.bytes()?)),
+            4 => Word::Ratio(self.i64()?, self.i64()?),
+            5 => Word::Str(self.str()?.into()),
+            6 => Word::Unbound(self.str()?.into()),
+            7 => Word::Send(self.str()?.into()),
+            8 => Word::Recv(self.str()?.into()),
+            #[cfg(feature = "concurrency")]
+            9 => Word::Spawn(self.str()?.into()),
+            #[cfg(feature = "concurrency")]
+            10 => Word::Join,
+            11 => Word::Quote(self.words()?),
+            12 => Word::Fused(*Fused::ALL.get(self.index()?).ok_or(Error::Snapshot)?),
+            13 => Word::Unbound(self.str()?.into()),
+            _ => return Err(Error::Snapshot),
+        })
+    }
+    /// Read some words, preceded by how many there are.
+    pub fn words<T: Cell>(&mut self) -> Result<Vec<Word<T>>, Error> {
+        (0..self.len()?).map(|_| self.word()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::word::tests::word;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn roundtrip(ws in proptest::collection::vec(word(), 0..8), ns: Vec<i128>) {
+            let mut e = Encoder::new::<i64>();
+            e.words(&ws);
+            for &n in &ns {
+                e.int(n);
+            }
+            let bytes = e.finish();
+            let mut d = Decoder::new::<i64>(&bytes).expect("verifies");
+            prop_assert_eq!(d.words(), Ok(ws));
+            for n in ns {
+                prop_assert_eq!(d.int(), Ok(n));
+            }
+            prop_assert_eq!(d.finish(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn verified() {
+        let bytes = Encoder::new::<i64>().finish();
+        assert!(Decoder::new::<i64>(&bytes).is_ok());
+        assert_eq!(
+            Decoder::new::<u64>(&bytes).map(|_| ()),
+            Err(Error::SnapshotCell)
+        );
+        let mut newer = bytes[..HEADER].to_vec();
+        newer[MAGIC.len()] = VERSION + 1;
+        let newer = Encoder(newer).finish();
+        assert_eq!(
+            Decoder::new::<i64>(&newer).map(|_| ()),
+            Err(Error::SnapshotVersion(VERSION + 1))
+        );
+        let mut corrupt = bytes.clone();
+        corrupt[HEADER] ^= 1;
+        for bytes in [&bytes[1..], &corrupt, b"DRSM"] {
+            assert_eq!(Decoder::new::<i64>(bytes).map(|_| ()), Err(Error::Snapshot));
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+/// Resource usage accumulated by a machine while evaluating.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Stats {
+    /// How many words have been executed, counting those inside custom words.
+    pub words_executed: u64,
+    /// The largest the stack has been after executing a word.
+    pub max_stack_depth: usize,
+    /// How many definitions have been added (or replaced) via `def`.
+    pub defs_added: usize,
+    /// How much fuel has been burned: a unit per word executed, & per loop iteration.
+    pub fuel_consumed: u64,
+}
+
+/// Resource usage of a single evaluation, as `Machine::read_eval_stats` reports.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct EvalStats {
+    /// How many words were executed, counting those inside custom words.
+    pub words_executed: u64,
+    /// The largest the stack was after executing a word.
+    pub max_stack_depth: usize,
+    /// How many definitions were added (or replaced) via `def`.
+    pub defs_added: usize,
+    /// How much fuel was burned: a unit per word executed, & per loop iteration.
+    pub fuel_consumed: u64,
+    /// How long the evaluation took.
+    pub duration: Duration,
+}
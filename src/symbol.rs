@@ -0,0 +1,175 @@
+use indexmap::IndexSet;
+use lean_string::LeanString;
+use std::{
+    fmt,
+    hash::{BuildHasherDefault, Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+};
+
+/// The names a machine has interned, each once, so a symbol may stand for one by its index.
+///
+/// Only names given definitions (by `def` or `var`) are interned: words merely read are looked
+/// up, so unknown ones don't accumulate here. Copied on write, like the machine's other tables.
+#[derive(Clone, Debug, Default)]
+pub struct Symbols(Arc<IndexSet<LeanString>>);
+
+/// A custom word's name, as a machine interned it.
+///
+/// It's the name's index in the machine's table, so comparing & hashing one (e.g. to look up its
+/// definition) needn't touch its text, along with the name, so reading it needn't touch the
+/// table. Only symbols a machine interned mean anything to it.
+#[derive(Clone)]
+pub struct Symbol {
+    id: u32,
+    name: LeanString,
+}
+
+/// Builds hashers for maps keyed by symbols.
+pub type BuildSymbolHasher = BuildHasherDefault<SymbolHasher>;
+
+/// Hashes a symbol by scattering its index, far faster than hashing its name.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SymbolHasher(u64);
+
+impl Symbols {
+    /// Intern a name, or find the symbol it's already interned as.
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(s) = self.get(name) {
+            return s;
+        }
+        let (i, _) = Arc::make_mut(&mut self.0).insert_full(name.into());
+        self.symbol(i)
+    }
+    /// The symbol a name's interned as, if it is, without interning it otherwise.
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.0.get_index_of(name).map(|i| self.symbol(i))
+    }
+    /// The names interned, in the order they were.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(LeanString::as_str)
+    }
+    /// The symbol for the `i`th name interned.
+    fn symbol(&self, i: usize) -> Symbol {
+        Symbol {
+            id: u32::try_from(i).expect("Internal error @ symbol"),
+            name: self.0[i].clone(),
+        }
+    }
+}
+
+impl Symbol {
+    /// The name this symbol stands for.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Symbols are the same just if they're the same entry in their table, which, for symbols the
+/// same machine interned, is just if their names are.
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u32(self.id);
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl From<Symbol> for LeanString {
+    fn from(s: Symbol) -> Self {
+        s.name
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, s: &str) -> bool {
+        self.as_str() == s
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, s: &&str) -> bool {
+        self == *s
+    }
+}
+
+impl Hasher for SymbolHasher {
+    /// Folding the well-mixed high bits into the low ones, which hash tables index by.
+    fn finish(&self) -> u64 {
+        self.0 ^ (self.0 >> 32)
+    }
+    /// Only symbols are hashed, as their indices, but just in case.
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(b.into());
+        }
+    }
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0 ^ n).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(n.into());
+    }
+}
+
+/// Serialized by name, as it's written in source. (There's no deserializing one, lacking a
+/// machine to intern it: a custom word deserializes as unbound, & is bound as it's defined.)
+#[cfg(feature = "serde")]
+impl serde::Serialize for Symbol {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::BuildHasher;
+
+    #[test]
+    fn interned() {
+        let mut symbols = Symbols::default();
+        let s = symbols.intern("symbol_interned");
+        assert_eq!(symbols.intern("symbol_interned"), s);
+        assert_eq!(symbols.get("symbol_interned"), Some(s.clone()));
+        assert_eq!(symbols.get("symbol_never_interned"), None);
+        assert_eq!(s.as_str(), "symbol_interned");
+        assert_eq!(s, "symbol_interned");
+        assert_ne!(s, "symbol_never_interned");
+        let other = symbols.intern("symbol_other");
+        assert_ne!(other, s);
+        assert_eq!(
+            symbols.names().collect::<Vec<_>>(),
+            ["symbol_interned", "symbol_other"]
+        );
+        assert_eq!(Symbols::default().get("symbol_interned"), None);
+        let h = BuildSymbolHasher::default();
+        assert_ne!(h.hash_one(&s), h.hash_one(&other));
+    }
+}
@@ -1,32 +1,223 @@
-use crate::core::Core;
+use crate::{Error, core::Core};
 use logos::Logos;
+use std::{fmt, ops::Range};
 
 /// Tokens are lexed from input strings.
-#[derive(Logos, Debug, PartialEq, Eq, Clone, strum::Display)]
-#[logos(skip r"\s", error = crate::Error)]
+///
+/// As in Forth, `\ ` comments out the rest of the line, and `( ` begins a comment ending at the
+/// next `)`. A comment showing a stack effect, like `( a b -- c )`, is a token of its own, which
+/// documents a definition when it follows `def <name>` (& is otherwise ignored).
+///
+/// Single underscores may separate the digits of integers (in any base), e.g. `1_000_000`.
+#[derive(Logos, Debug, PartialEq, Clone, strum::Display)]
+#[logos(skip r"\s", error(crate::Error, lex_error))]
+#[logos(skip r"\\([ \t][^\n]*)?")]
+#[logos(skip r"[(]\s[^)]*[)]")]
+#[non_exhaustive]
 pub enum Token<'source> {
     /// Define a new word.
     #[token("def")]
     #[strum(serialize = "def")]
     Def,
+    /// Define a word, even if it's already defined.
+    #[token("redef")]
+    #[strum(serialize = "redef")]
+    Redef,
+    /// Declare a variable.
+    #[token("var")]
+    #[strum(serialize = "var")]
+    Var,
+    /// End a definition.
+    #[token(";")]
+    #[strum(serialize = ";")]
+    End,
+    /// A stack-effect comment.
+    #[regex(r"[(]\s([^)]*\s)?--\s[^)]*[)]", priority = 10)]
+    #[strum(serialize = "{0}")]
+    Effect(&'source str),
+    /// Bind a constant.
+    #[token("const")]
+    #[strum(serialize = "const")]
+    Const,
+    /// Select the language edition for the rest of the input.
+    #[token("#edition")]
+    #[strum(serialize = "#edition")]
+    Edition,
+    /// Include another file (only valid when reading files).
+    #[token("#include")]
+    #[strum(serialize = "#include")]
+    Include,
+    /// Send to a named channel.
+    #[token("send")]
+    #[strum(serialize = "send")]
+    Send,
+    /// Receive from a named channel.
+    #[token("recv")]
+    #[strum(serialize = "recv")]
+    Recv,
+    /// Spawn a custom word on a worker thread.
+    #[cfg(feature = "concurrency")]
+    #[token("spawn")]
+    #[strum(serialize = "spawn")]
+    Spawn,
+    /// Join a spawned task.
+    #[cfg(feature = "concurrency")]
+    #[token("join")]
+    #[strum(serialize = "join")]
+    Join,
+    /// Begin a quotation.
+    #[token("[")]
+    #[strum(serialize = "[")]
+    Open,
+    /// End a quotation.
+    #[token("]")]
+    #[strum(serialize = "]")]
+    Close,
     /// A core word.
-    #[regex(r"(drop|swap|dup|add|sub|mul|div|mod|zero[?]|print)", |lex| lex.slice().parse::<Core>().unwrap())]
+    #[regex(r"(drop|swap|dup|over|rot|nip|tuck|pick|roll|2drop|2swap|2dup|2over|depth|clear|sum|product|reverse|iota|range|[.]s|add|sub|mul|div|mod|zero[?]|neg[?]|pos[?]|select|print|peek|[.]|print-|print-w|print-0w|hexprint|binprint|last|_|mark|clear-to-marker|call|if|if-else|times|while|throw|catch|exit|fadd|fsub|fmul|fdiv|f>i|i>f|fprint|concat|length|sprint|true|false|and|or|not|xor|abs|neg|min|max|pow|gcd|isqrt|log2|popcount|eq[?]|neq[?]|lt[?]|gt[?]|le[?]|ge[?]|band|bor|bxor|bnot|shl|shr|radd|rsub|rmul|rdiv|num|den|floor|i>r|rprint|emit|read|array|get|set|len|@|!)", |lex| lex.slice().parse::<Core>().unwrap())]
+    #[cfg_attr(feature = "bignum", regex(r"(big|badd|bsub|bmul|bdiv|bmod|b>i|bprint)", |lex| lex.slice().parse::<Core>().unwrap()))]
     #[strum(serialize = "{0}")]
     Core(Core),
     /// An integer in decimal notation.
-    #[regex(r"-?[[:digit:]]+", |lex| lex.slice().parse())]
+    #[cfg_attr(not(feature = "bignum"), regex(r"-?[[:digit:]](_?[[:digit:]])*", |lex| lex.slice().replace('_', "").parse().ok()))]
+    #[cfg_attr(feature = "bignum", regex(r"-?[[:digit:]](_?[[:digit:]])*", decimal))]
     #[strum(serialize = "{0}")]
     Num(i64),
+    /// An integer in decimal notation too big for an `i64`, which only wider cells can hold.
+    /// It's lexed by `Num`'s pattern.
+    #[cfg(feature = "bignum")]
+    #[strum(serialize = "{0}")]
+    Long(num_bigint::BigInt),
     /// An integer in hexadecimal notation.
-    #[regex(r"#[[:xdigit:]]+", |lex| i64::from_str_radix(&lex.slice()[1..], 16))]
-    #[strum(serialize = "#{0:x}")]
-    Hex(i64),
+    #[regex(r"-?#[[:xdigit:]](_?[[:xdigit:]])*", |lex| based(lex.slice(), "#"))]
+    #[strum(serialize = "{0}")]
+    Hex(Based<16>),
+    /// An integer in binary notation.
+    #[regex(r"-?0b[01](_?[01])*", |lex| based(lex.slice(), "0b"))]
+    #[strum(serialize = "{0}")]
+    Bin(Based<2>),
+    /// An integer in octal notation.
+    #[regex(r"-?0o[0-7](_?[0-7])*", |lex| based(lex.slice(), "0o"))]
+    #[strum(serialize = "{0}")]
+    Oct(Based<8>),
+    /// A float, with a decimal point or an exponent (or both).
+    #[regex(r"-?[[:digit:]]+([.][[:digit:]]+([eE][-+]?[[:digit:]]+)?|[eE][-+]?[[:digit:]]+)", |lex| lex.slice().parse().ok())]
+    #[strum(serialize = "{0:?}")]
+    Float(f64),
+    /// An integer of any size, suffixed with `n`.
+    #[cfg(feature = "bignum")]
+    #[regex(r"-?[[:digit:]](_?[[:digit:]])*n", |lex| lex.slice().trim_end_matches('n').replace('_', "").parse().ok())]
+    #[strum(serialize = "{0}n")]
+    Big(num_bigint::BigInt),
+    /// A character, between single quotes, standing for its code point; `\n` is a newline, & `\`
+    /// escapes any other character.
+    #[regex(r"'([^'\\]|\\.)'", |lex| { let s = lex.slice(); &s[1..s.len() - 1] })]
+    #[strum(serialize = "'{0}'")]
+    Char(&'source str),
+    /// A ratio of integers, the second positive.
+    #[regex(r"-?[[:digit:]]+/[[:digit:]]+")]
+    #[strum(serialize = "{0}")]
+    Ratio(&'source str),
+    /// A string, between double quotes; `\n` is a newline, & `\` escapes any other character.
+    #[regex(r#""([^"\\]|\\.)*""#, |lex| { let s = lex.slice(); &s[1..s.len() - 1] })]
+    #[strum(serialize = "\"{0}\"")]
+    Str(&'source str),
     /// A (possibly unknown) custom token.
     #[regex(r"\S+", priority = 0)]
     #[strum(serialize = "{0}")]
     Custom(&'source str),
 }
 
+/// Lexed floats are never NaN.
+impl Eq for Token<'_> {}
+
+/// An integer written in base `B`: 2, 8, or 16.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Based<const B: u32>(pub i64);
+
+/// Any sign comes first, then the base's prefix (`0b`, `0o`, or `#`), then the digits.
+impl<const B: u32> fmt::Display for Based<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Wide::<B>(self.0.into()).fmt(f)
+    }
+}
+
+/// An integer as wide as any cell, written in base `B` as `Based` is, e.g. by `hexprint`.
+pub struct Wide<const B: u32>(pub i128);
+
+impl<const B: u32> fmt::Display for Wide<B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (sign, n) = (if self.0 < 0 { "-" } else { "" }, self.0.unsigned_abs());
+        match B {
+            2 => write!(f, "{sign}0b{n:b}"),
+            8 => write!(f, "{sign}0o{n:o}"),
+            _ => write!(f, "{sign}#{n:x}"),
+        }
+    }
+}
+
+/// Parse a (perhaps negative) integer written with a base's prefix, if it fits.
+fn based<const B: u32>(s: &str, prefix: &str) -> Option<Based<B>> {
+    let (sign, s) = s.strip_prefix('-').map_or(("", s), |s| ("-", s));
+    let digits = s[prefix.len()..].replace('_', "");
+    i64::from_str_radix(&format!("{sign}{digits}"), B)
+        .ok()
+        .map(Based)
+}
+
+/// A decimal integer, as a `Num` if it fits an `i64`, & a `Long` if not.
+#[cfg(feature = "bignum")]
+fn decimal<'s>(lex: &logos::Lexer<'s, Token<'s>>) -> Token<'s> {
+    let digits = lex.slice().replace('_', "");
+    digits.parse().map_or_else(
+        |_| Token::Long(digits.parse().expect("Lexed integers are all digits")),
+        Token::Num,
+    )
+}
+
+/// Lets `decimal`, the callback for `Num`'s pattern, choose the token itself, as `logos` only lets
+/// callbacks for variants without fields do otherwise.
+#[cfg(feature = "bignum")]
+impl<'s> logos::internal::CallbackRetVal<'s, i64, Self> for Token<'s> {
+    fn construct<C: Fn(i64) -> Self>(self, _: C) -> logos::internal::CallbackResult<'s, Self> {
+        logos::internal::CallbackResult::Emit(self)
+    }
+}
+
+/// The error for input that couldn't be lexed (or, e.g., is a number too big to fit).
+fn lex_error<'s>(lex: &logos::Lexer<'s, Token<'s>>) -> Error {
+    Error::Lex {
+        slice: lex.slice().into(),
+        span: lex.span(),
+    }
+}
+
+/// Does a stack-effect comment following tokens `ts` document a definition, i.e. follow
+/// `def <name>` (or `redef <name>`)?
+pub fn documents(ts: &[Token]) -> bool {
+    matches!(ts, [.., Token::Def | Token::Redef, _])
+}
+
+/// A token, along with the byte range of the input it was lexed from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SpannedToken<'source> {
+    /// The token itself.
+    pub token: Token<'source>,
+    /// Where in the input the token was found.
+    pub span: Range<usize>,
+}
+
+/// Lex a string into tokens, exactly as the machine does, along with their spans—so tools like
+/// syntax highlighters needn't reimplement the lexer. Whitespace & comments are skipped.
+///
+/// # Errors
+/// Each item is an error if that part of the input couldn't be lexed.
+pub fn lex(s: &str) -> impl Iterator<Item = Result<SpannedToken<'_>, Error>> {
+    Token::lexer(s)
+        .spanned()
+        .map(|(t, span)| t.map(|token| SpannedToken { token, span }))
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::{super::core::tests::core, *};
@@ -34,6 +225,19 @@ pub mod tests {
     use proptest::prelude::*;
 
     proptest! {
+        #[test]
+        fn spans(ts in prop::collection::vec(token(), 0..64), sep in r"\s+") {
+            let strs = ts.iter().map(ToString::to_string).collect::<Vec<_>>();
+            let s = strs.join(&sep);
+            let sts = lex(&s).collect::<Result<Vec<_>, _>>();
+            prop_assert!(sts.is_ok());
+            let sts = sts.expect("is_ok");
+            prop_assert_eq!(sts.len(), ts.len());
+            for ((st, t), x) in sts.into_iter().zip(ts).zip(strs) {
+                prop_assert_eq!(&s[st.span], x);
+                prop_assert_eq!(st.token, t);
+            }
+        }
         #[test]
         fn roundtrip(t in token()) {
             let s = t.to_string();
@@ -46,13 +250,101 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn comments() {
+        let s = "1 \\ one\n2 ( two\n) 3 \\";
+        let ts = Token::lexer(s).collect::<Result<Vec<_>, _>>();
+        assert_eq!(ts, Ok(vec![Token::Num(1), Token::Num(2), Token::Num(3)]));
+    }
+
+    #[test]
+    fn bases() {
+        let s = "0b1010 -0o777 -#ff -#8000000000000000";
+        let ts = Token::lexer(s).collect::<Result<Vec<_>, _>>();
+        assert_eq!(
+            ts,
+            Ok(vec![
+                Token::Bin(Based(10)),
+                Token::Oct(Based(-511)),
+                Token::Hex(Based(-255)),
+                Token::Hex(Based(i64::MIN)),
+            ])
+        );
+        let strs = ts
+            .iter()
+            .flatten()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(strs.join(" "), s);
+        let n = i128::from(u64::MAX);
+        assert_eq!(Wide::<16>(n).to_string(), "#ffffffffffffffff");
+    }
+
+    #[test]
+    fn lex_errors() {
+        let ts = Token::lexer("1 #8000000000000000");
+        assert_eq!(
+            ts.collect::<Result<Vec<_>, _>>(),
+            Err(Error::Lex {
+                slice: "#8000000000000000".into(),
+                span: 2..19
+            })
+        );
+    }
+
+    #[test]
+    fn separators() {
+        let ts = Token::lexer("1_000_000 #dead_beef -0b1_0 1__0 _1 1_");
+        assert_eq!(
+            ts.collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                Token::Num(1_000_000),
+                Token::Hex(Based(0xdead_beef)),
+                Token::Bin(Based(-2)),
+                Token::Custom("1__0"),
+                Token::Custom("_1"),
+                Token::Custom("1_"),
+            ])
+        );
+    }
+
+    // The union's extended under `bignum`.
+    #[allow(clippy::let_and_return)]
     pub fn token() -> impl Strategy<Value = Token<'static>> {
-        prop_oneof![
+        let t = prop_oneof![
             Just(Token::Def),
+            Just(Token::Redef),
+            Just(Token::Var),
+            Just(Token::End),
+            Just("( a b -- c )").prop_map(Token::Effect),
+            Just(Token::Const),
+            Just(Token::Edition),
+            Just(Token::Include),
+            Just(Token::Send),
+            Just(Token::Recv),
+            Just(Token::Open),
+            Just(Token::Close),
             core().prop_map(Token::Core),
             any::<i64>().prop_map(Token::Num),
-            (0..i64::MAX).prop_map(Token::Hex),
+            any::<i64>().prop_map(|n| Token::Hex(Based(n))),
+            any::<i64>().prop_map(|n| Token::Bin(Based(n))),
+            any::<i64>().prop_map(|n| Token::Oct(Based(n))),
+            prop::num::f64::NORMAL.prop_map(Token::Float),
             Just("custom_token").prop_map(Token::Custom),
-        ]
+            Just(r#"a \"string\" \\ token"#).prop_map(Token::Str),
+            Just("-3/4").prop_map(Token::Ratio),
+            Just(r"\'").prop_map(Token::Char),
+        ];
+        #[cfg(feature = "concurrency")]
+        let t = prop_oneof![t, Just(Token::Spawn), Just(Token::Join)];
+        #[cfg(feature = "bignum")]
+        let t = prop_oneof![
+            t,
+            any::<i128>().prop_map(|n| Token::Big(n.into())),
+            any::<i128>()
+                .prop_filter("Too big for an `i64`", |&n| i64::try_from(n).is_err())
+                .prop_map(|n| Token::Long(n.into())),
+        ];
+        t
     }
 }
@@ -1,65 +1,379 @@
-use crate::{Error, core::Core, token::Token};
+use crate::{
+    Error,
+    cell::Cell,
+    core::Core,
+    symbol::{Symbol, Symbols},
+    token::{Based, Token},
+};
 use lean_string::LeanString;
-use std::convert::TryFrom;
+use logos::Logos;
+use std::{
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    mem,
+};
 
 /// The words upon which our stack machine works.
-#[derive(Debug, PartialEq, Eq, Clone, strum::Display)]
-pub enum Word {
-    /// A core word,
-    #[strum(serialize = "{0}")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Word<T = i64> {
+    /// A core word.
     Core(Core),
     /// An integer.
-    #[strum(serialize = "{0}")]
-    Num(i64),
-    /// A custom word.
-    #[strum(serialize = "{0}")]
-    Custom(LeanString),
+    Num(T),
+    /// A float, kept on the stack as its bit pattern.
+    Float(f64),
+    /// An integer of any size, pushed as a handle.
+    #[cfg(feature = "bignum")]
+    Big(num_bigint::BigInt),
+    /// A ratio of integers (the second positive), pushed as a handle to it in lowest terms.
+    Ratio(i64, i64),
+    /// A string, pushed as a handle.
+    Str(LeanString),
+    /// A custom word, by its name as the machine that read it interned it. It's equal to the
+    /// unbound word of the same name, & deserializes as one.
+    #[cfg_attr(feature = "serde", serde(skip_deserializing))]
+    Custom(Symbol),
+    /// A custom word by its name, as it's parsed, looked up when it's executed unless a machine
+    /// reads it once it's defined. (Defining a word makes those in its body custom words.)
+    #[cfg_attr(feature = "serde", serde(alias = "Custom"))]
+    Unbound(LeanString),
+    /// Send the top of the stack to a named channel.
+    Send(LeanString),
+    /// Receive from a named channel onto the stack.
+    Recv(LeanString),
+    /// Spawn a custom word, by its name, on a worker thread.
+    #[cfg(feature = "concurrency")]
+    Spawn(LeanString),
+    /// Join a spawned task, pushing its final stack.
+    #[cfg(feature = "concurrency")]
+    Join,
+    /// A quotation: a block of words, pushed as a handle for `call` to execute.
+    Quote(Vec<Self>),
+    /// A superinstruction: a common pair of core words executed as one, as optimized definitions
+    /// use. It's written (& read back) as the pair.
+    Fused(Fused),
 }
 
-impl TryFrom<Token<'_>> for Word {
+/// Common pairs of core words that can execute as one, with less dispatch & stack traffic.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fused {
+    /// `dup add`: double the first element of the stack.
+    DupAdd,
+    /// `dup mul`: square the first element of the stack.
+    DupMul,
+    /// `swap drop`: drop the second element of the stack, like `nip`.
+    SwapDrop,
+}
+
+impl Fused {
+    /// Every superinstruction.
+    pub const ALL: [Self; 3] = [Self::DupAdd, Self::DupMul, Self::SwapDrop];
+    /// The pair of core words this executes as one.
+    #[must_use]
+    pub const fn parts(self) -> [Core; 2] {
+        match self {
+            Self::DupAdd => [Core::Dup, Core::Add],
+            Self::DupMul => [Core::Dup, Core::Mul],
+            Self::SwapDrop => [Core::Swap, Core::Drop],
+        }
+    }
+    /// The superinstruction for a pair of core words, if there is one.
+    #[must_use]
+    pub fn of(a: Core, b: Core) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.parts() == [a, b])
+    }
+    /// How many items this needs on the stack.
+    #[must_use]
+    pub const fn arity(self) -> usize {
+        match self {
+            Self::DupAdd | Self::DupMul => 1,
+            Self::SwapDrop => 2,
+        }
+    }
+}
+
+impl fmt::Display for Fused {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b] = self.parts();
+        write!(f, "{a} {b}")
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Word<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Core(c) => write!(f, "{c}"),
+            Self::Num(n) => write!(f, "{n}"),
+            Self::Float(x) => write!(f, "{x:?}"),
+            #[cfg(feature = "bignum")]
+            Self::Big(n) => write!(f, "{n}n"),
+            Self::Ratio(n, d) => write!(f, "{n}/{d}"),
+            Self::Str(s) => {
+                f.write_str("\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' | '\\' => write!(f, "\\{c}")?,
+                        '\n' => f.write_str("\\n")?,
+                        _ => write!(f, "{c}")?,
+                    }
+                }
+                f.write_str("\"")
+            }
+            Self::Custom(w) => write!(f, "{w}"),
+            Self::Unbound(w) => write!(f, "{w}"),
+            Self::Send(c) => write!(f, "send {c}"),
+            Self::Recv(c) => write!(f, "recv {c}"),
+            #[cfg(feature = "concurrency")]
+            Self::Spawn(c) => write!(f, "spawn {c}"),
+            #[cfg(feature = "concurrency")]
+            Self::Join => f.write_str("join"),
+            Self::Quote(ws) => {
+                f.write_str("[")?;
+                for w in ws {
+                    write!(f, " {w}")?;
+                }
+                f.write_str(" ]")
+            }
+            Self::Fused(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+/// Floats are compared (& hashed) by bit pattern, so e.g. `0.0` & `-0.0` differ.
+impl<T: PartialEq> PartialEq for Word<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Core(a), Self::Core(b)) => a == b,
+            (Self::Num(a), Self::Num(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            #[cfg(feature = "bignum")]
+            (Self::Big(a), Self::Big(b)) => a == b,
+            (Self::Ratio(a, b), Self::Ratio(p, q)) => (a, b) == (p, q),
+            (Self::Custom(a), Self::Custom(b)) => a.as_str() == b.as_str(),
+            (Self::Custom(a), Self::Unbound(b)) | (Self::Unbound(b), Self::Custom(a)) => {
+                a == b.as_str()
+            }
+            (Self::Str(a), Self::Str(b))
+            | (Self::Unbound(a), Self::Unbound(b))
+            | (Self::Send(a), Self::Send(b))
+            | (Self::Recv(a), Self::Recv(b)) => a == b,
+            #[cfg(feature = "concurrency")]
+            (Self::Spawn(a), Self::Spawn(b)) => a == b,
+            #[cfg(feature = "concurrency")]
+            (Self::Join, Self::Join) => true,
+            (Self::Quote(a), Self::Quote(b)) => a == b,
+            (Self::Fused(a), Self::Fused(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<T: Eq> Eq for Word<T> {}
+
+impl<T: Hash> Hash for Word<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            // As the unbound word of the same name, which it's equal to.
+            Self::Custom(_) => mem::discriminant(&Self::Unbound(LeanString::new())),
+            w => mem::discriminant(w),
+        }
+        .hash(state);
+        match self {
+            Self::Core(c) => c.hash(state),
+            Self::Num(n) => n.hash(state),
+            Self::Float(x) => x.to_bits().hash(state),
+            #[cfg(feature = "bignum")]
+            Self::Big(n) => n.hash(state),
+            Self::Ratio(n, d) => (n, d).hash(state),
+            Self::Custom(w) => w.as_str().hash(state),
+            Self::Str(w) | Self::Unbound(w) | Self::Send(w) | Self::Recv(w) => w.hash(state),
+            #[cfg(feature = "concurrency")]
+            Self::Spawn(w) => w.hash(state),
+            #[cfg(feature = "concurrency")]
+            Self::Join => {}
+            Self::Quote(ws) => ws.hash(state),
+            Self::Fused(f) => f.hash(state),
+        }
+    }
+}
+
+impl<T: Cell> TryFrom<Token<'_>> for Word<T> {
     type Error = Error;
     fn try_from(t: Token<'_>) -> Result<Self, Self::Error> {
         match t {
             Token::Def => Err(Error::DefReserved),
+            Token::Redef => Err(Error::RedefReserved),
+            Token::Var => Err(Error::VarReserved),
+            Token::End => Err(Error::Unbegun),
+            Token::Effect(e) => Err(Error::Unknown(e.into())),
+            Token::Const => Err(Error::ConstReserved),
+            Token::Edition => Err(Error::EditionReserved),
+            Token::Include => Err(Error::Include),
+            Token::Send | Token::Recv => Err(Error::ChannelName(t.to_string())),
+            Token::Open => Err(Error::Unclosed),
+            Token::Close => Err(Error::Unopened),
+            #[cfg(feature = "concurrency")]
+            Token::Spawn => Err(Error::SpawnName),
+            #[cfg(feature = "concurrency")]
+            Token::Join => Ok(Self::Join),
             Token::Core(c) => Ok(Self::Core(c)),
-            Token::Num(n) | Token::Hex(n) => Ok(Self::Num(n)),
-            Token::Custom(w) => Ok(Self::Custom(LeanString::from(w))),
+            Token::Num(n) | Token::Hex(Based(n)) | Token::Bin(Based(n)) | Token::Oct(Based(n)) => {
+                Ok(Self::Num(T::from_i64(n)))
+            }
+            Token::Float(x) => Ok(Self::Float(x)),
+            #[cfg(feature = "bignum")]
+            Token::Long(n) => T::from_big(&n)
+                .map(Self::Num)
+                .ok_or_else(|| n.to_string().parse::<i64>().unwrap_err().into()),
+            #[cfg(feature = "bignum")]
+            Token::Big(n) => Ok(Self::Big(n)),
+            Token::Ratio(s) => {
+                let (n, d) = s.split_once('/').expect("Lexed ratios have a `/`");
+                match (n.parse()?, d.parse()?) {
+                    (_, 0) => Err(Error::NotNonzero(s.to_string())),
+                    (n, d) => Ok(Self::Ratio(n, d)),
+                }
+            }
+            Token::Str(s) => Ok(Self::Str(unescape(s))),
+            Token::Char(s) => {
+                let c = unescape(s).chars().next();
+                Ok(Self::Num(
+                    c.map(|c| T::from_i64(u32::from(c).into()))
+                        .expect("Lexed characters aren't empty"),
+                ))
+            }
+            Token::Custom(w) => Ok(Self::Unbound(w.into())),
         }
     }
 }
 
-impl PartialEq<String> for Word {
+/// The string a string (or character) token stands for: `\n` is a newline, & `\` otherwise stands for the
+/// character following it.
+fn unescape(s: &str) -> LeanString {
+    let mut out = String::with_capacity(s.len());
+    let mut cs = s.chars();
+    while let Some(c) = cs.next() {
+        if c == '\\' {
+            match cs.next() {
+                Some('n') => out.push('\n'),
+                Some(c) => out.push(c),
+                None => out.push(c),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    LeanString::from(out)
+}
+
+impl<T> PartialEq<String> for Word<T> {
     fn eq(&self, s: &String) -> bool {
-        matches!(self, Self::Custom(w) if w == s)
+        self == s.as_str()
     }
 }
 
-impl PartialEq<LeanString> for Word {
+impl<T> PartialEq<LeanString> for Word<T> {
     fn eq(&self, s: &LeanString) -> bool {
-        matches!(self, Self::Custom(w) if w == s)
+        self == s.as_str()
     }
 }
 
-impl Word {
+impl<T> PartialEq<str> for Word<T> {
+    fn eq(&self, s: &str) -> bool {
+        match self {
+            Self::Custom(w) => w == s,
+            Self::Unbound(w) => w == s,
+            _ => false,
+        }
+    }
+}
+
+impl<T: Cell> Word<T> {
+    /// Parse a word starting at token `t`, pulling operands (e.g. channel names) from `ts`.
+    ///
+    /// # Errors
+    /// If the token isn't a word, or a required operand is missing or invalid.
+    pub fn parse<'a>(
+        t: Token<'a>,
+        ts: &mut impl Iterator<Item = Token<'a>>,
+    ) -> Result<Self, Error> {
+        match t {
+            Token::Send | Token::Recv => {
+                let n = ts
+                    .next()
+                    .ok_or_else(|| Error::ChannelName(t.to_string()))
+                    .and_then(Self::try_from)
+                    .and_then(Self::into_name)?;
+                Ok(if t == Token::Send {
+                    Self::Send(n)
+                } else {
+                    Self::Recv(n)
+                })
+            }
+            #[cfg(feature = "concurrency")]
+            Token::Spawn => ts
+                .next()
+                .ok_or(Error::SpawnName)
+                .and_then(Self::try_from)
+                .and_then(Self::into_name)
+                .map(Self::Spawn),
+            Token::Open => {
+                let mut ws = Vec::new();
+                loop {
+                    match ts.next() {
+                        None => return Err(Error::Unclosed),
+                        Some(Token::Close) => return Ok(Self::Quote(ws)),
+                        Some(t) => ws.push(Self::parse(t, ts)?),
+                    }
+                }
+            }
+            _ => Self::try_from(t),
+        }
+    }
+    /// The name `s` stands for, as after `def`.
+    ///
+    /// # Errors
+    /// If `s` isn't a single custom word.
+    pub fn name(s: &str) -> Result<LeanString, Error> {
+        let mut ts = Token::lexer(s);
+        match (ts.next(), ts.next()) {
+            (Some(t), None) => Self::try_from(t?)?.into_name(),
+            _ => Err(Error::DefName),
+        }
+    }
     /// Transform this word into a name, if possible.
     ///
     /// # Errors
     /// If the word is a number or a core word.
     pub fn into_name(self) -> Result<LeanString, Error> {
         match self {
-            Self::Custom(w) => Ok(w),
-            Self::Num(n) => Err(Error::NumNotName(n)),
-            Self::Core(_) => Err(Error::CoreNotName(self.to_string())),
+            Self::Custom(w) => Ok(w.into()),
+            Self::Unbound(w) => Ok(w),
+            Self::Num(_) | Self::Float(_) | Self::Ratio(..) => {
+                Err(Error::NumNotName(self.to_string()))
+            }
+            #[cfg(feature = "bignum")]
+            Self::Big(_) => Err(Error::NumNotName(self.to_string())),
+            Self::Str(_) => Err(Error::StrNotName(self.to_string())),
+            Self::Core(_) | Self::Send(_) | Self::Recv(_) | Self::Quote(_) | Self::Fused(_) => {
+                Err(Error::CoreNotName(self.to_string()))
+            }
+            #[cfg(feature = "concurrency")]
+            Self::Spawn(_) | Self::Join => Err(Error::CoreNotName(self.to_string())),
         }
     }
-    /// Unsafely grab the inner lean string of this custom word.
-    ///
-    /// # Panics
-    /// If this isn't a custom word.
-    pub(crate) fn unsafe_custom_inner(&self) -> &LeanString {
+    /// The symbol for the custom word this calls (or spawns), if its name's been interned (in
+    /// `symbols`, unless it's a custom word already).
+    pub(crate) fn symbol(&self, symbols: &Symbols) -> Option<Symbol> {
         match self {
-            Self::Custom(w) => w,
-            _ => panic!("Unsafe custom inner called on non-custom word {self}"),
+            Self::Custom(w) => Some(w.clone()),
+            Self::Unbound(w) => symbols.get(w),
+            #[cfg(feature = "concurrency")]
+            Self::Spawn(w) => symbols.get(w),
+            _ => None,
         }
     }
 }
@@ -76,8 +390,27 @@ pub mod tests {
     proptest! {
         #[test]
         fn from_token(t in token()) {
-            let w = Word::try_from(t.clone());
-            prop_assert_eq!(w.is_ok(), t != Token::Def);
+            // Every lexed integer fits an `i128`.
+            let w = Word::<i128>::try_from(t.clone());
+            // Keywords, & words taking a name, aren't words by themselves.
+            let alone = match t {
+                Token::Def
+                | Token::Redef
+                | Token::Var
+                | Token::End
+                | Token::Effect(_)
+                | Token::Const
+                | Token::Edition
+                | Token::Include
+                | Token::Send
+                | Token::Recv
+                | Token::Open
+                | Token::Close => false,
+                #[cfg(feature = "concurrency")]
+                Token::Spawn => false,
+                _ => true,
+            };
+            prop_assert_eq!(w.is_ok(), alone);
         }
         #[test]
         fn self_eq(w in word()) {
@@ -93,24 +426,36 @@ pub mod tests {
             let s = w.to_string();
             let ts = Token::lexer(&s).collect::<Result<Vec<Token>, _>>();
             prop_assert!(ts.is_ok());
-            let mut ts = ts.expect("is_ok");
-            prop_assert_eq!(ts.len(), 1);
-            let w2 = Word::try_from(ts.pop().expect("len == 1"));
+            let mut ts = ts.expect("is_ok").into_iter();
+            let t = ts.next();
+            prop_assert!(t.is_some());
+            let w2 = Word::parse(t.expect("is_some"), &mut ts);
             prop_assert!(w2.is_ok());
             prop_assert_eq!(w2.expect("is_ok"), w);
+            prop_assert!(ts.next().is_none());
         }
         #[test]
         fn into_name(w in word()) {
             let n = w.clone().into_name();
-            prop_assert_eq!(n.is_ok(), w == Word::Custom(n.unwrap_or_default()));
+            prop_assert_eq!(n.is_ok(), w == n.unwrap_or_default());
         }
     }
 
     pub fn word() -> impl Strategy<Value = Word> {
-        prop_oneof![
+        let leaf = prop_oneof![
             core().prop_map(Word::Core),
             any::<i64>().prop_map(Word::Num),
-            r"custom_[a-zA-Z]+".prop_map(|s| Word::Custom(s.into()))
-        ]
+            prop::num::f64::NORMAL.prop_map(Word::Float),
+            (any::<i64>(), 1..i64::MAX).prop_map(|(n, d)| Word::Ratio(n, d)),
+            r#"[a-z "\\\n]*"#.prop_map(|s| Word::Str(s.into())),
+            r"custom_[a-zA-Z]+".prop_map(|s| Word::Custom(Symbols::default().intern(&s))),
+            r"chan_[a-zA-Z]+".prop_map(|s| Word::Send(s.into())),
+            r"chan_[a-zA-Z]+".prop_map(|s| Word::Recv(s.into())),
+        ];
+        #[cfg(feature = "bignum")]
+        let leaf = prop_oneof![leaf, any::<i128>().prop_map(|n| Word::Big(n.into()))];
+        leaf.prop_recursive(2, 8, 4, |w| {
+            prop::collection::vec(w, 0..4).prop_map(Word::Quote)
+        })
     }
 }
@@ -0,0 +1,90 @@
+use crate::{cell::Cell, error::Error};
+use std::{
+    collections::HashMap,
+    num::NonZero,
+    thread::{self, JoinHandle},
+};
+
+/// Tasks spawned onto worker threads, bounded by how many may be outstanding at once.
+#[derive(Debug)]
+pub struct Workers<T: Cell> {
+    max: usize,
+    next: i64,
+    running: HashMap<i64, JoinHandle<Result<Vec<T>, Error>>>,
+}
+
+impl<T: Cell> Default for Workers<T> {
+    fn default() -> Self {
+        Self::new(thread::available_parallelism().map_or(1, NonZero::get))
+    }
+}
+
+/// A clone has no outstanding tasks, since each may only be joined once.
+impl<T: Cell> Clone for Workers<T> {
+    fn clone(&self) -> Self {
+        Self {
+            max: self.max,
+            next: self.next,
+            running: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Cell> Workers<T> {
+    /// Allow at most `max` outstanding (spawned but not yet joined) tasks.
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            next: 0,
+            running: HashMap::new(),
+        }
+    }
+    /// Change the most tasks that may be outstanding at once.
+    pub const fn set_max(&mut self, max: usize) {
+        self.max = max;
+    }
+    /// The most tasks that may be outstanding at once.
+    pub const fn max(&self) -> usize {
+        self.max
+    }
+    /// Are there already as many outstanding tasks as allowed?
+    pub fn is_full(&self) -> bool {
+        self.running.len() >= self.max
+    }
+    /// Is there an outstanding task with this handle?
+    pub fn contains(&self, id: i64) -> bool {
+        self.running.contains_key(&id)
+    }
+    /// Run `f` on a new thread, returning a handle with which to `join` it.
+    pub fn spawn(&mut self, f: impl FnOnce() -> Result<Vec<T>, Error> + Send + 'static) -> i64 {
+        let id = self.next;
+        self.next = self.next.wrapping_add(1);
+        let _ = self.running.insert(id, thread::spawn(f));
+        id
+    }
+    /// Wait for the task with this handle, returning its final stack.
+    pub fn join(&mut self, id: i64) -> Result<Vec<T>, Error> {
+        self.running
+            .remove(&id)
+            .ok_or(Error::UnknownTask(id))?
+            .join()
+            .map_err(|_| Error::WorkerPanicked(id))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded() {
+        let mut w = Workers::<i64>::new(2);
+        let a = w.spawn(|| Ok(vec![1]));
+        let b = w.spawn(|| Err(Error::Thrown(1)));
+        assert!(w.is_full());
+        assert_eq!(w.join(b), Err(Error::Thrown(1)));
+        assert!(!w.is_full());
+        assert_eq!(w.join(a), Ok(vec![1]));
+        assert_eq!(w.join(a), Err(Error::UnknownTask(a)));
+    }
+}
@@ -0,0 +1,20 @@
+//! Lex input as tooling (e.g. a syntax highlighter) would, from outside the crate.
+use drsm::{Based, Core, SpannedToken, Token, lex};
+
+#[test]
+fn spans() {
+    let s = "def sq dup mul ; ( squares ) #ff sq";
+    let ts = lex(s).collect::<Result<Vec<_>, _>>().expect("lexes");
+    assert_eq!(
+        ts.iter().map(|t| &s[t.span.clone()]).collect::<Vec<_>>(),
+        ["def", "sq", "dup", "mul", ";", "#ff", "sq"]
+    );
+    assert_eq!(
+        ts[5],
+        SpannedToken {
+            token: Token::Hex(Based(255)),
+            span: 29..32
+        }
+    );
+    assert_eq!(ts[2].token, Token::Core(Core::Dup));
+}
@@ -0,0 +1,429 @@
+//! Drive the REPL as a host would, a line at a time.
+#![cfg(feature = "repl")]
+use drsm::{
+    Edition, Machine,
+    repl::{Repl, Response, Settings, UNDO_LIMIT},
+};
+use rustyline::{Config, EditMode};
+use std::{
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+#[test]
+fn builtins() {
+    let mut r = Repl::new(Machine::with_edition(Edition::Edition2026));
+    assert_eq!(r.line("def sq dup mul"), Response::Nothing);
+    assert_eq!(r.line("3 sq"), Response::Nothing);
+    assert_eq!(r.machine().to_string().lines().last(), Some("stack: [ 9 ]"));
+    assert_eq!(
+        r.line("?lookup sq"),
+        Response::Output("`sq` is defined as `dup mul`".into())
+    );
+    assert!(matches!(r.line("?lookup"), Response::Error(_)));
+    assert_eq!(r.line("def cube ( n -- n ) dup sq mul"), Response::Nothing);
+    assert_eq!(
+        r.line("?lookup cube"),
+        Response::Output("`cube` is defined as `( n -- n ) dup sq mul`".into())
+    );
+    assert!(matches!(r.line("?lookup fadd"), Response::Output(s) if s.contains("as floats")));
+    assert!(matches!(r.line("?show page"), Response::Pages(s) if s.starts_with("edition: 2026")));
+    assert!(matches!(r.line("?show nope"), Response::Error(_)));
+    #[cfg(feature = "serde")]
+    assert!(
+        matches!(r.line("?show --json"), Response::Output(s) if s.contains(r#""sq":"dup mul""#))
+    );
+    assert!(matches!(r.line("nope"), Response::Error(e) if e.contains("nope")));
+    assert!(matches!(r.line("?forget sq"), Response::Error(e) if e.contains("cube")));
+    assert_eq!(r.line("?forget cube"), Response::Nothing);
+    assert_eq!(r.line("?forget sq"), Response::Nothing);
+    assert_eq!(r.line("?quit"), Response::Quit);
+}
+
+#[test]
+fn words() {
+    let mut r = Repl::default();
+    assert_eq!(
+        r.line("def sq ( n -- n ) dup mul ; 10 const ten"),
+        Response::Nothing
+    );
+    let Response::Pages(s) = r.line("?words") else {
+        panic!("?words should show pages");
+    };
+    let ls: Vec<_> = s
+        .lines()
+        .map(str::split_whitespace)
+        .map(Iterator::collect::<Vec<_>>)
+        .collect();
+    assert_eq!(ls[0], ["Core", "words:"]);
+    assert!(ls.iter().any(|l| l[..2] == ["dup", "Duplicate"]));
+    assert!(ls.iter().any(|l| l[..2] == ["fadd", "Add"]));
+    assert!(ls.iter().all(|l| l.len() > 1 || l[0].ends_with(':')), "{s}");
+    let defs = ls
+        .iter()
+        .position(|l| l == &["Definitions:"])
+        .expect("definitions");
+    assert_eq!(ls[defs + 1], ["sq", "(", "n", "--", "n", ")", "dup", "mul"]);
+    assert_eq!(ls[defs + 2], ["ten", "10"]);
+}
+
+#[test]
+fn commands() {
+    let mut r = Repl::default().command("depth", "show the stack's depth.", |m, _| {
+        Response::Output(m.to_source().lines().skip(1).collect::<Vec<_>>().join(" "))
+    });
+    assert!(r.help().contains("?depth      =>  show the stack's depth."));
+    assert_eq!(r.line("1 2 3"), Response::Nothing);
+    assert_eq!(r.line("?depth"), Response::Output("1 2 3".into()));
+    r.machine_mut().read_eval("drop").expect("drop");
+    assert_eq!(r.line("?depth"), Response::Output("1 2".into()));
+}
+
+#[test]
+fn clear_reset() {
+    let mut r = Repl::default().autoprint(true);
+    assert_eq!(
+        r.line("def sq dup mul ; 1 2 3"),
+        Response::Output("3".into())
+    );
+    assert_eq!(r.line("?clear"), Response::Output("Stack cleared.".into()));
+    assert!(r.machine().is_empty());
+    assert_eq!(r.line("4 sq"), Response::Output("16".into()));
+    assert_eq!(r.line("[ 1"), Response::Nothing);
+    assert_eq!(r.line("?reset"), Response::Output("Machine reset.".into()));
+    assert!(!r.continuing());
+    assert!(r.machine().is_empty());
+    assert_eq!(r.machine().words().len(), 0);
+    assert!(matches!(r.line("4 sq"), Response::Error(_)));
+}
+
+#[test]
+fn mode() {
+    let mut r = Repl::default().config(Config::builder().edit_mode(EditMode::Vi).build());
+    let vi = Response::Output("Key bindings are vi-style.".into());
+    assert_eq!(r.line("?mode"), vi);
+    assert_eq!(
+        r.line("?mode emacs"),
+        Response::Output("Key bindings are emacs-style.".into())
+    );
+    assert_eq!(r.line("?mode vi"), vi);
+    assert!(matches!(r.line("?mode nano"), Response::Error(_)));
+    assert_eq!(r.line("?mode"), vi);
+}
+
+#[test]
+fn time() {
+    let mut r = Repl::default();
+    assert!(matches!(r.line("?time"), Response::Error(_)));
+    let Response::Output(s) = r.line("?time 3 4 add") else {
+        panic!("?time should report the time taken");
+    };
+    assert!(
+        s.starts_with("Took ") && s.ends_with(", executing 3 words."),
+        "{s}"
+    );
+    assert_eq!(r.machine().peek(), Some(7));
+    assert!(
+        matches!(r.line("?time drop drop"), Response::Error(e) if e.ends_with(", executing 1 word)"))
+    );
+}
+
+#[test]
+fn trace() {
+    #[derive(Clone, Default)]
+    struct Sink(Arc<Mutex<Vec<u8>>>);
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().map_or(Ok(0), |mut v| v.write(buf))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let (sink, mut m) = (Sink::default(), Machine::default());
+    m.set_output(sink.clone());
+    let mut r = Repl::new(m);
+    assert_eq!(r.line("def sq dup mul ;"), Response::Nothing);
+    assert_eq!(r.line("?trace"), Response::Output("Tracing is on.".into()));
+    assert_eq!(r.line("3 sq"), Response::Nothing);
+    assert_eq!(r.line("?trace"), Response::Output("Tracing is off.".into()));
+    assert_eq!(r.line("sq"), Response::Nothing);
+    let traced = sink.0.lock().map(|v| v.clone()).unwrap_or_default();
+    assert_eq!(
+        String::from_utf8(traced).as_deref(),
+        Ok("3  [ 3 ]\nsq  [ 3 ]\n  dup  [ 3 3 ]\n  mul  [ 9 ]\n")
+    );
+}
+
+#[test]
+fn settings() {
+    let file = Settings::parse(
+        "# Mine.\nmode = \"emacs\"  # not vi\n\nprompt = \"drsm> \"\ncolor = false\nautoprint = true # yes\n",
+    );
+    let file = file.expect("valid settings");
+    assert_eq!(file.edit_mode, Some(EditMode::Emacs));
+    assert_eq!(file.prompt.as_deref(), Some("drsm> "));
+    assert_eq!(
+        (file.color, file.autoprint, &file.history),
+        (Some(false), Some(true), &None)
+    );
+    for (bad, e) in [
+        (
+            "mode = vi",
+            "line 1: `mode` takes a string in double quotes",
+        ),
+        (
+            "\nmode = \"nano\"",
+            "line 2: `mode` takes \"vi\" or \"emacs\"",
+        ),
+        ("color = yes", "line 1: `color` takes `true` or `false`"),
+        (
+            "color = true false",
+            "line 1: `color` takes `true` or `false`",
+        ),
+        ("colour = true", "line 1: unknown setting `colour`"),
+        ("autoprint", "line 1: expected `key = value`"),
+    ] {
+        assert_eq!(Settings::parse(bad), Err(e.into()));
+    }
+    let cli = Settings {
+        autoprint: Some(false),
+        history: Some("h.txt".into()),
+        ..Settings::default()
+    };
+    let both = file.overridden_by(cli);
+    assert_eq!((both.color, both.autoprint), (Some(false), Some(false)));
+    assert_eq!(both.history, Some("h.txt".into()));
+    assert_eq!(both.edit_mode, Some(EditMode::Emacs));
+    let missing = Settings::load(Path::new("/nonexistent/drsm/config.toml"));
+    assert_eq!(missing.ok(), Some(Settings::default()));
+    let mut r = Repl::default().settings(&Settings {
+        autoprint: Some(true),
+        ..Settings::default()
+    });
+    assert_eq!(r.line("1 2"), Response::Output("2".into()));
+    assert_eq!(
+        r.line("?mode"),
+        Response::Output("Key bindings are emacs-style.".into())
+    );
+}
+
+#[test]
+fn debugger() {
+    let mut r = Repl::default();
+    let out = |s: &str| Response::Output(s.into());
+    assert_eq!(
+        r.line("def sq dup mul ; def quad sq sq ;"),
+        Response::Nothing
+    );
+    assert!(matches!(r.line("?step"), Response::Error(_)));
+    assert_eq!(r.line("?break"), out("No breakpoints."));
+    assert_eq!(r.line("?break sq"), out("Breakpoint set at `sq`."));
+    assert_eq!(r.line("?break"), out("Breakpoints: sq."));
+    assert_eq!(
+        r.line("3 quad"),
+        out("Paused at `sq` (depth 1), with stack [ 3 ].")
+    );
+    assert!(matches!(r.line("4"), Response::Error(e) if e.contains("paused at `sq`")));
+    assert_eq!(
+        r.line("?inspect"),
+        out("Paused at `sq` (depth 1).\nstack: [ 3 ]\nenv:\n  sq  dup mul\n  quad  sq sq")
+    );
+    assert_eq!(
+        r.line("?step"),
+        out("Paused at `dup` (depth 2), with stack [ 3 ].")
+    );
+    assert_eq!(
+        r.line("?step"),
+        out("Paused at `mul` (depth 2), with stack [ 3 3 ].")
+    );
+    assert_eq!(
+        r.line("?continue"),
+        out("Paused at `sq` (depth 1), with stack [ 9 ].")
+    );
+    assert_eq!(r.line("?continue"), Response::Nothing);
+    assert_eq!(r.machine().peek(), Some(81));
+    assert_eq!(
+        r.line("sq"),
+        out("Paused at `sq` (depth 0), with stack [ 81 ].")
+    );
+    assert!(matches!(r.line("?abort"), Response::Error(e) if e.contains("cancel")));
+    assert_eq!(r.machine().peek(), Some(81));
+    assert_eq!(r.line("?break sq"), out("Breakpoint at `sq` removed."));
+    assert_eq!(r.line("2 quad"), Response::Nothing);
+    assert_eq!(r.machine().peek(), Some(16));
+    assert_eq!(r.line("?break dup"), out("Breakpoint set at `dup`."));
+    assert!(matches!(
+        r.line("def cube dup sq mul ; 2 cube"),
+        Response::Output(_)
+    ));
+    assert!(!r.continuing());
+    assert_eq!(r.complete("cu", 2), (0, vec!["cube".into()]));
+    assert_eq!(r.machine().peek(), Some(16));
+    assert_eq!(r.line("?quit"), Response::Quit);
+}
+
+#[test]
+fn undo() {
+    let mut r = Repl::default();
+    let out = |s: &str| Response::Output(s.into());
+    assert!(matches!(r.line("?undo"), Response::Error(_)));
+    assert_eq!(r.line("def sq dup mul ; 3"), Response::Nothing);
+    assert_eq!(r.line("sq sq"), Response::Nothing);
+    assert!(matches!(r.line("1 nope"), Response::Error(_)));
+    assert_eq!(r.machine().peek(), Some(1));
+    assert_eq!(r.line("?undo"), out("Undid `1 nope`."));
+    assert_eq!(r.machine().peek(), Some(81));
+    assert_eq!(r.line("?reset"), out("Machine reset."));
+    assert!(matches!(r.line("?forget sq"), Response::Error(_)));
+    assert_eq!(r.line("?undo"), out("Undid `?reset`."));
+    assert_eq!(r.line("?forget sq"), Response::Nothing);
+    assert_eq!(r.line("?undo"), out("Undid `?forget sq`."));
+    assert!(matches!(r.line("?time 2 3 add"), Response::Output(_)));
+    assert_eq!(r.line("?undo"), out("Undid `?time 2 3 add`."));
+    assert_eq!(r.machine().peek(), Some(81));
+    assert_eq!(r.line("?undo"), out("Undid `sq sq`."));
+    assert_eq!(r.machine().peek(), Some(3));
+    assert_eq!(r.line("?undo"), out("Undid `def sq dup mul ; 3`."));
+    assert_eq!((r.machine().words().len(), r.machine().peek()), (0, None));
+    assert!(matches!(r.line("?undo"), Response::Error(_)));
+    for i in 0..=UNDO_LIMIT {
+        assert_eq!(r.line(&i.to_string()), Response::Nothing);
+    }
+    for _ in 0..UNDO_LIMIT {
+        assert!(matches!(r.line("?undo"), Response::Output(_)));
+    }
+    assert_eq!(r.machine().peek(), Some(0));
+    assert!(matches!(r.line("?undo"), Response::Error(_)));
+}
+
+#[test]
+fn autoprint() {
+    let mut r = Repl::default().autoprint(true);
+    assert_eq!(r.line("3 4 add"), Response::Output("7".into()));
+    assert_eq!(r.line("2 mul"), Response::Output("14".into()));
+    assert_eq!(r.line("def sq"), Response::Nothing);
+    assert_eq!(r.line("dup mul ;"), Response::Output("14".into()));
+    assert_eq!(r.line("drop"), Response::Nothing);
+    assert_eq!(
+        r.line("?autoprint"),
+        Response::Output("Autoprint is off.".into())
+    );
+    assert_eq!(r.line("5"), Response::Nothing);
+}
+
+#[test]
+fn continuation() {
+    let mut r = Repl::default().autoprint(true);
+    assert!(!r.continuing());
+    assert_eq!(r.line("2 [ dup"), Response::Nothing);
+    assert!(r.continuing());
+    assert_eq!(r.line("  [ 1 add ] call"), Response::Nothing);
+    assert!(r.continuing());
+    assert_eq!(r.line("mul ] call"), Response::Output("6".into()));
+    assert!(!r.continuing());
+    assert_eq!(r.line("def sq"), Response::Nothing);
+    assert_eq!(r.line("[ dup"), Response::Nothing);
+    assert!(r.continuing());
+    assert_eq!(r.line("mul ] call ;"), Response::Output("6".into()));
+    assert_eq!(r.line("sq"), Response::Output("36".into()));
+    assert!(matches!(r.line("1 ] ["), Response::Error(_)));
+    assert!(!r.continuing());
+}
+
+#[test]
+fn record() {
+    let path = std::env::temp_dir().join(format!("drsm-record-{}.drsm", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut r = Repl::default();
+    assert!(matches!(r.line("?stop-record"), Response::Error(_)));
+    assert_eq!(r.line("1"), Response::Nothing);
+    let p = path.to_str().expect("UTF-8 path");
+    assert_eq!(
+        r.line(&format!("?record {p}")),
+        Response::Output(format!("Recording to {p}."))
+    );
+    assert_eq!(r.line("2 add"), Response::Nothing);
+    assert!(matches!(r.line("nope"), Response::Error(_)));
+    assert!(matches!(r.line("?lookup add"), Response::Output(_)));
+    assert_eq!(
+        r.line("?stop-record"),
+        Response::Output("Recording stopped.".into())
+    );
+    assert_eq!(r.line("3"), Response::Nothing);
+    let transcript = std::fs::read_to_string(&path).expect("read");
+    std::fs::remove_file(&path).expect("cleanup");
+    assert_eq!(
+        transcript,
+        format!(
+            "\\ ?record {p}\n\\ Recording to {p}.\n2 add\nnope\n\\ column 1: Unknown op: `nope`.\n\\ ?lookup add\n\\ `add` is a core word: Add the first two elements of the stack.\n"
+        )
+    );
+    let mut m = Machine::default();
+    let replay = format!("1\n{}", transcript.replace("nope\n", ""));
+    assert!(m.read_eval_stream(replay.as_bytes()).is_ok());
+    assert_eq!(m.stack(), [3]);
+}
+
+#[test]
+fn export_session() {
+    let path = std::env::temp_dir().join(format!("drsm-session-{}.drsm", std::process::id()));
+    let p = path.to_str().expect("UTF-8 path");
+    let mut r = Repl::new(Machine::with_edition(Edition::Edition2026));
+    assert_eq!(
+        r.line("def sq ( n -- n ) dup mul ; 3 sq [ 1 ]"),
+        Response::Nothing
+    );
+    assert!(matches!(r.line("oops"), Response::Error(_)));
+    assert!(matches!(r.line("?export-session"), Response::Error(_)));
+    assert_eq!(
+        r.line(&format!("?export-session {p}")),
+        Response::Output(format!("Session exported to {p}."))
+    );
+    let mut m = Machine::default();
+    let replayed = m.read_eval_path(&path);
+    std::fs::remove_file(&path).expect("cleanup");
+    assert_eq!(replayed, Ok(()));
+    assert_eq!(&m, r.machine());
+    assert_eq!(m.edition(), Edition::Edition2026);
+}
+
+#[test]
+fn complete() {
+    let mut r =
+        Repl::default().command("depth", "show the stack's depth.", |_, _| Response::Nothing);
+    assert_eq!(r.line("def square dup mul"), Response::Nothing);
+    assert_eq!(r.complete("3 zer", 5), (2, vec!["zero?".to_string()]));
+    assert_eq!(r.complete("3 squ", 5), (2, vec!["square".to_string()]));
+    assert_eq!(r.complete("2dro 1", 3), (0, vec!["2drop".to_string()]));
+    assert_eq!(
+        r.complete("?s", 2),
+        (
+            0,
+            ["?show", "?step", "?stop-record"]
+                .map(String::from)
+                .to_vec()
+        )
+    );
+    assert_eq!(r.complete("?d", 2), (0, vec!["?depth".to_string()]));
+    assert_eq!(
+        r.complete("?lookup squ", 11),
+        (8, vec!["square".to_string()])
+    );
+    assert!(r.complete("3 nope", 6).1.is_empty());
+}
+
+#[test]
+fn highlight() {
+    let mut r = Repl::default();
+    assert_eq!(r.line("def sq dup mul ; 2 const two"), Response::Nothing);
+    let colored = r.highlight("def cube ( n -- n ) dup sq mul ;  two 0b10 nope \"s\" 1/2 \\ note");
+    assert_eq!(
+        colored,
+        "\x1b[1;35mdef\x1b[0m cube ( n -- n ) \x1b[34mdup\x1b[0m sq \x1b[34mmul\x1b[0m \
+         \x1b[1;35m;\x1b[0m  two \x1b[36m0b10\x1b[0m \x1b[31mnope\x1b[0m \x1b[32m\"s\"\x1b[0m \
+         \x1b[36m1/2\x1b[0m \\ note"
+    );
+    assert_eq!(r.highlight("\"open"), "\x1b[31m\"open\x1b[0m");
+}